@@ -1,6 +1,7 @@
 //! # Generates the top-level command line application.
-use clap::SubCommand;
+use clap::{AppSettings, SubCommand};
 
+use crate::util::dockerfile;
 use crate::util::template::APP_TEMPLATE;
 use crate::util::template::BIFROST_USAGE;
 use crate::util::template::SUBCOMMAND_HELP_TEMPLATE;
@@ -37,20 +38,195 @@ pub fn cli() -> App {
 /// list.
 fn all_sub_commands() -> Vec<App> {
     let mut sub_commands: Vec<App> = vec![];
+    sub_command_cache(&mut sub_commands);
+    sub_command_image(&mut sub_commands);
     sub_command_init(&mut sub_commands);
     sub_command_load(&mut sub_commands);
+    sub_command_reload(&mut sub_commands);
     sub_command_run(&mut sub_commands);
     sub_command_setup(&mut sub_commands);
     sub_command_show(&mut sub_commands);
     sub_command_teardown(&mut sub_commands);
     sub_command_unload(&mut sub_commands);
+    sub_command_verify(&mut sub_commands);
 
     sub_commands
 }
 
+fn sub_command_cache(commands: &mut Vec<App>) {
+    const ABOUT: &str = "Manage the named cache volumes of bifrost realms";
+    const USAGE: &str = "bifrost cache <SUBCOMMAND>";
+
+    let s = SubCommand::with_name("cache")
+        .about(ABOUT)
+        .usage(USAGE)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(sub_command_cache_clear());
+
+    commands.push(s);
+}
+
+fn sub_command_cache_clear() -> App {
+    const ABOUT: &str = "Remove the named cache volumes of this realm";
+    const USAGE: &str = "bifrost cache clear [OPTIONS]";
+    const LONG: &str = "
+Remove the named cache volumes (i.e. `[[container.mounts]]` entries of type
+`volume`) the container engine holds for this realm. Bind mounts are never
+touched. Use `--all` to remove the cache volumes of every realm.
+
+\t$ bifrost cache clear
+\t$ bifrost cache clear --all
+
+
+";
+
+    let a = Arg::with_name("all")
+        .long("all")
+        .help("Remove the cache volumes of every realm");
+
+    SubCommand::with_name("clear")
+        .about(ABOUT)
+        .long_about(LONG)
+        .usage(USAGE)
+        .arg(a)
+}
+
+fn sub_command_image(commands: &mut Vec<App>) {
+    const ABOUT: &str = "Manage bifrost images and their packages";
+    const USAGE: &str = "bifrost image <SUBCOMMAND>";
+
+    let s = SubCommand::with_name("image")
+        .about(ABOUT)
+        .usage(USAGE)
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(sub_command_image_add())
+        .subcommand(sub_command_image_remove())
+        .subcommand(sub_command_image_list())
+        .subcommand(sub_command_image_rebuild())
+        .subcommand(sub_command_image_inventory())
+        .subcommand(sub_command_image_prune());
+
+    commands.push(s);
+}
+
+fn sub_command_image_add() -> App {
+    const ABOUT: &str = "Install package(s) in the bifrost image";
+    const USAGE: &str = "bifrost image add <PACKAGES>...";
+    const LONG: &str = "
+Install package(s) in the bifrost image. The packages are kept in a section
+of ~/.bifrost/container/bifrost/Dockerfile managed by bifrost and installed
+with the package manager of the base image (apt, apk, or dnf). The image is
+rebuilt afterwards; adding an installed package does nothing.
+
+\t$ bifrost image add curl git
+
+
+";
+
+    SubCommand::with_name("add")
+        .about(ABOUT)
+        .long_about(LONG)
+        .usage(USAGE)
+        .arg(arg_image_packages())
+}
+
+fn sub_command_image_remove() -> App {
+    const ABOUT: &str = "Uninstall package(s) from the bifrost image";
+    const USAGE: &str = "bifrost image remove <PACKAGES>...";
+    const LONG: &str = "
+Uninstall package(s) previously installed with `bifrost image add`. The
+image is rebuilt afterwards; removing a package that is not installed does
+nothing.
+
+\t$ bifrost image remove git
+
+
+";
+
+    SubCommand::with_name("remove")
+        .about(ABOUT)
+        .long_about(LONG)
+        .usage(USAGE)
+        .arg(arg_image_packages())
+}
+
+fn sub_command_image_list() -> App {
+    const ABOUT: &str = "List the packages installed with `bifrost image add`";
+    const USAGE: &str = "bifrost image list";
+
+    SubCommand::with_name("list").about(ABOUT).usage(USAGE)
+}
+
+fn sub_command_image_rebuild() -> App {
+    const ABOUT: &str = "Rebuild the bifrost image if its Dockerfile changed";
+    const USAGE: &str = "bifrost image rebuild [OPTIONS]";
+    const LONG: &str = "
+Rebuild the bifrost image from ~/.bifrost/container/bifrost/Dockerfile. The
+Dockerfile and the rest of its build context are hashed and the image is only
+rebuilt if they changed since it was last built. `bifrost run` does the same
+before running, so calling this is only needed to rebuild ahead of time.
+
+\t$ bifrost image rebuild
+\t$ bifrost image rebuild --force
+
+
+";
+
+    let a = Arg::with_name("force")
+        .long("force")
+        .help("Rebuild even if nothing changed");
+
+    SubCommand::with_name("rebuild")
+        .about(ABOUT)
+        .long_about(LONG)
+        .usage(USAGE)
+        .arg(a)
+}
+
+fn sub_command_image_inventory() -> App {
+    const ABOUT: &str = "List the images bifrost has built";
+    const USAGE: &str = "bifrost image inventory";
+
+    SubCommand::with_name("inventory").about(ABOUT).usage(USAGE)
+}
+
+fn sub_command_image_prune() -> App {
+    const ABOUT: &str = "Remove images bifrost has built that no realm uses";
+    const USAGE: &str = "bifrost image prune [OPTIONS]";
+    const LONG: &str = "
+Remove the images bifrost has built that are no longer used by a loaded realm,
+e.g. images built from an older version of a realm's Dockerfile, and the
+images that rebuilds have replaced. The image built by `bifrost setup` is
+always kept. Use `--dry-run` to see what would be removed.
+
+\t$ bifrost image prune --dry-run
+\t$ bifrost image prune
+
+
+";
+
+    let a = Arg::with_name("dry-run")
+        .long("dry-run")
+        .help("Show what would be removed without removing anything");
+
+    SubCommand::with_name("prune")
+        .about(ABOUT)
+        .long_about(LONG)
+        .usage(USAGE)
+        .arg(a)
+}
+
+fn arg_image_packages() -> Arg {
+    Arg::with_name("packages")
+        .value_name("PACKAGES")
+        .required(true)
+        .multiple(true)
+        .help("The name(s) of the package(s)")
+}
+
 fn sub_command_setup(commands: &mut Vec<App>) {
     const ABOUT: &str = "Setup the utilities bifrost requires to operate";
-    const USAGE: &str = "bifrost setup";
+    const USAGE: &str = "bifrost setup [OPTIONS]";
     const LONG: &str = "
  Setup the utilities bifrost requires to operate. This command is intended
  to be run once after a successful install. Bifrost aims to be transparent
@@ -61,17 +237,76 @@ fn sub_command_setup(commands: &mut Vec<App>) {
 └── container
     └── bifrost
         └── Dockerfile
+
+ The Dockerfile starts from a `--base` image (debian:bookworm by default)
+ with one of the built-in `--template`s: minimal, cpp (the default), rust,
+ python, or node. Use `--dockerfile` to adopt an existing Dockerfile instead.
+
+\t$ bifrost setup
+\t$ bifrost setup --base alpine:3.18 --template rust
+\t$ bifrost setup --dockerfile path/to/Dockerfile
  
  ";
 
-    let s = SubCommand::with_name("setup")
+    let mut s = SubCommand::with_name("setup")
         .about(ABOUT)
         .long_about(LONG)
         .usage(USAGE);
 
+    for a in all_setup_args() {
+        s = s.arg(a);
+    }
+
     commands.push(s);
 }
 
+fn all_setup_args() -> Vec<Arg> {
+    let mut setup_args: Vec<Arg> = vec![];
+    arg_setup_base(&mut setup_args);
+    arg_setup_template(&mut setup_args);
+    arg_setup_dockerfile(&mut setup_args);
+
+    setup_args
+}
+
+fn arg_setup_base(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Base image of the bifrost image (default: debian:bookworm)";
+
+    let a = Arg::with_name("base")
+        .long("base")
+        .value_name("IMAGE")
+        .takes_value(true)
+        .conflicts_with("dockerfile")
+        .help(SHORT);
+
+    args.push(a);
+}
+
+fn arg_setup_template(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Dockerfile template of the bifrost image (default: cpp)";
+
+    let a = Arg::with_name("template")
+        .long("template")
+        .takes_value(true)
+        .possible_values(dockerfile::TEMPLATES)
+        .conflicts_with("dockerfile")
+        .help(SHORT);
+
+    args.push(a);
+}
+
+fn arg_setup_dockerfile(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Existing Dockerfile to build the bifrost image from";
+
+    let a = Arg::with_name("dockerfile")
+        .long("dockerfile")
+        .value_name("PATH")
+        .takes_value(true)
+        .help(SHORT);
+
+    args.push(a);
+}
+
 fn sub_command_teardown(commands: &mut Vec<App>) {
     const ABOUT: &str = "Teardown the utilities bifrost requires to operate";
     const USAGE: &str = "bifrost teardown";
@@ -104,6 +339,15 @@ fn sub_command_unload(commands: &mut Vec<App>) {
     commands.push(s);
 }
 
+fn sub_command_verify(commands: &mut Vec<App>) {
+    const ABOUT: &str = "Check a loaded workspace against the checksums of its files";
+    const USAGE: &str = "bifrost verify [OPTIONS]";
+
+    let s = SubCommand::with_name("verify").about(ABOUT).usage(USAGE);
+
+    commands.push(s);
+}
+
 fn sub_command_run(commands: &mut Vec<App>) {
     const ABOUT: &str = "Run command string(s) on a bifrost workspace";
     const USAGE: &str = "bifrost run [OPTIONS]";
@@ -120,10 +364,146 @@ fn sub_command_run(commands: &mut Vec<App>) {
 fn all_run_args() -> Vec<Arg> {
     let mut run_args: Vec<Arg> = vec![];
     arg_run_commands(&mut run_args);
+    arg_run_cpus(&mut run_args);
+    arg_run_memory(&mut run_args);
+    arg_run_pids_limit(&mut run_args);
+    arg_run_network(&mut run_args);
+    arg_run_publish(&mut run_args);
+    arg_run_update_lock(&mut run_args);
 
     run_args
 }
 
+fn arg_run_network(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Networking mode of the container (none, bridge, host)";
+    const LONG: &str = "
+The networking mode of the bifrost container: `none`, `bridge`, or `host`.
+Use `none` to force a hermetic, offline run. This takes precedence over
+`network` in the [container] section of the Bifrost.toml manifest.
+
+\t$ bifrost run --network none
+
+
+";
+
+    let a = Arg::with_name("network")
+        .long("network")
+        .takes_value(true)
+        .possible_values(&["none", "bridge", "host"])
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_run_publish(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Publish container port(s) to the host";
+    const LONG: &str = "
+Publish container port(s) to the host as `[ip:][host_port:]container_port`.
+These are added to `ports` in the [container] section of the Bifrost.toml
+manifest.
+
+\t$ bifrost run -p 8080:80
+\t$ bifrost run -p 8080:80 -p 5432:5432
+
+
+";
+
+    let a = Arg::with_name("publish")
+        .short("p")
+        .long("publish")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_run_update_lock(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Pin the current image in Bifrost.lock";
+    const LONG: &str = "
+The first run of a realm pins the digest of its image (and of the image's base
+image) in a Bifrost.lock next to the Bifrost.toml manifest. Later runs refuse
+to run in an image with a different digest unless `--update-lock` is given,
+in which case the lock is updated to the current image.
+
+\t$ bifrost run --update-lock
+
+
+";
+
+    let a = Arg::with_name("update-lock")
+        .long("update-lock")
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_run_cpus(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Number of CPUs the container may use";
+    const LONG: &str = "
+The number of CPUs the bifrost container may use. This takes precedence
+over `cpus` in the [container] section of the Bifrost.toml manifest.
+
+\t$ bifrost run --cpus 1.5
+
+
+";
+
+    let a = Arg::with_name("cpus")
+        .long("cpus")
+        .takes_value(true)
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_run_memory(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Memory limit of the container (e.g. 512m, 2g)";
+    const LONG: &str = "
+The memory limit of the bifrost container. Commands that exceed this
+limit are killed and reported as such. This takes precedence over
+`memory` in the [container] section of the Bifrost.toml manifest.
+
+\t$ bifrost run --memory 2g
+
+
+";
+
+    let a = Arg::with_name("memory")
+        .long("memory")
+        .takes_value(true)
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_run_pids_limit(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Maximum number of processes within the container";
+    const LONG: &str = "
+The maximum number of processes within the bifrost container. This takes
+precedence over `pids_limit` in the [container] section of the Bifrost.toml
+manifest.
+
+\t$ bifrost run --pids-limit 256
+
+
+";
+
+    let a = Arg::with_name("pids-limit")
+        .long("pids-limit")
+        .takes_value(true)
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
 fn arg_run_commands(args: &mut Vec<Arg>) {
     const SHORT: &str = "Commands passed to the target application";
     const LONG: &str = "
@@ -164,6 +544,8 @@ fn all_show_args() -> Vec<Arg> {
     let mut show_args: Vec<Arg> = vec![];
     arg_show_all(&mut show_args);
     arg_show_diff(&mut show_args);
+    arg_show_patch(&mut show_args);
+    arg_show_ignored(&mut show_args);
     show_args
 }
 
@@ -193,7 +575,9 @@ fn arg_show_diff(args: &mut Vec<Arg>) {
     const LONG: &str = "
 When `diff` is passed, `bifrost show` will display the contents 
 that have modified locally but have not been re-loaded into
-the Bifrost container realm.
+the Bifrost container realm: files that were added, modified, or
+deleted. `bifrost show` exits with a non-zero status when there
+are differences.
 
 
 \t$ bifrost show --diff
@@ -209,6 +593,50 @@ the Bifrost container realm.
     args.push(a);
 }
 
+fn arg_show_patch(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Show how modified text files differ (implies --diff).";
+    const LONG: &str = "
+When `patch` is passed, `bifrost show --diff` also displays a
+unified diff of each text file that differs between the current
+workspace realm and the Bifrost container realm.
+
+
+\t$ bifrost show --patch
+
+
+";
+
+    let a = Arg::with_name("patch")
+        .long("patch")
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_show_ignored(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Show what loading the realm excludes, and why.";
+    const LONG: &str = "
+When `ignored` is passed, `bifrost show` will display the files and
+directories that `bifrost load` excludes from the current realm, each
+with the rule that excludes it: a pattern of `[workspace] ignore`, a
+pattern of a `.gitignore` or `.bifrostignore` file, or not matching
+any pattern of `[workspace] include`.
+
+
+\t$ bifrost show --ignored
+
+
+";
+
+    let a = Arg::with_name("ignored")
+        .long("ignored")
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
 fn sub_command_load(commands: &mut Vec<App>) {
     const SHORT: &str = "Load directory, file, or files into the bifrost container";
     const USAGE: &str = "
@@ -237,7 +665,12 @@ fn all_load_args() -> Vec<Arg> {
     let mut load_args: Vec<Arg> = vec![];
     arg_load_auto(&mut load_args);
     arg_load_contents(&mut load_args);
+    arg_load_dry_run(&mut load_args);
+    arg_load_git(&mut load_args);
+    arg_load_json(&mut load_args);
     arg_load_modified(&mut load_args);
+    arg_load_rev(&mut load_args);
+    arg_load_run(&mut load_args);
 
     load_args
 }
@@ -246,9 +679,11 @@ fn arg_load_auto(args: &mut Vec<Arg>) {
     const SHORT: &str = "Turn on auto reloading";
     const LONG: &str = "When `auto` is enabled, modified files are automatically \
 detected and loaded into the bifrost container without having to manually run \
-`bifrost load --modified <files>`:
+`bifrost load --modified <files>`. The realm is watched (ignoring what the \
+manifest ignores) until Ctrl-C is pressed:
 
-\t$ bifrost run
+\t$ bifrost load --auto
+\t$ bifrost load --auto --run
 
 
 ";
@@ -262,6 +697,26 @@ detected and loaded into the bifrost container without having to manually run \
     args.push(a);
 }
 
+fn arg_load_run(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Run the realm's commands after each auto reload";
+    const LONG: &str = "
+Run the realm's `[command]` in the bifrost container after each
+auto reload (and once the realm is first loaded).
+
+\t$ bifrost load --auto --run
+
+
+";
+
+    let a = Arg::with_name("run")
+        .long("run")
+        .requires("auto")
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
 /// Builds `args`. Running `bifrost load`, without specifying what should be
 /// loaded, will result in the entire current working directory to be loaded
 /// into the container.
@@ -279,12 +734,96 @@ fn arg_load_contents(args: &mut Vec<Arg>) {
     args.push(a);
 }
 
+fn arg_load_dry_run(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Show what would be loaded without loading anything";
+    const LONG: &str = "
+Walk the realm (honoring its ignore and include patterns) and list the
+files that would be loaded, their total size, the largest of them, and
+what is excluded by which rule. Nothing is copied into the bifrost
+container. With `--json`, the plan is printed as JSON.
+
+\t$ bifrost load --dry-run
+\t$ bifrost load --dry-run --json --contents src/
+
+
+";
+
+    let a = Arg::with_name("dry-run")
+        .long("dry-run")
+        .conflicts_with_all(&["modified", "auto"])
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_load_git(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Load only the files git tracks";
+    const LONG: &str = "
+Load only the files that are tracked in the index of the realm's git
+repository, leaving out untracked (and ignored) files. `.gitignore`
+files are not consulted; `[workspace] ignore` and `.bifrostignore`
+patterns still apply.
+
+\t$ bifrost load --git
+
+
+";
+
+    let a = Arg::with_name("git")
+        .long("git")
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
+fn arg_load_json(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Print the plan of a dry run as JSON";
+
+    let a = Arg::with_name("json")
+        .long("json")
+        .requires("dry-run")
+        .help(SHORT);
+
+    args.push(a);
+}
+
+fn arg_load_rev(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Load the realm as of a git revision";
+    const LONG: &str = "
+Load the files of the realm as of a commit (or a branch or tag) of
+its git repository instead of the files on disk. The id of the commit
+is recorded so that `bifrost show` reports which revision is loaded.
+
+\t$ bifrost load --rev HEAD~1
+\t$ bifrost reload --rev v1.2.0
+
+
+";
+
+    let a = Arg::with_name("rev")
+        .long("rev")
+        .value_name("COMMIT")
+        .takes_value(true)
+        .conflicts_with_all(&["git", "modified", "auto"])
+        .help(SHORT)
+        .long_help(LONG);
+
+    args.push(a);
+}
+
 fn arg_load_modified(args: &mut Vec<Arg>) {
     const SHORT: &str = "Load only files that have been \
                          modified since the last run";
     const LONG: &str = "
 Load only files that have been modified since the last
-run.
+run. The realm is compared with the loaded workspace by
+size and modification time (and by content when those
+disagree): new and changed files are copied and files
+removed from the realm are removed from the workspace.
+
+\t$ bifrost load --modified
 
 
 ";
@@ -298,12 +837,36 @@ run.
     args.push(a);
 }
 
+fn sub_command_reload(commands: &mut Vec<App>) {
+    const SHORT: &str = "Replace the loaded workspace with a fresh copy of the realm";
+    const USAGE: &str = "
+    bifrost reload [OPTIONS]
+    bifrost reload [OPTIONS] --contents project/
+";
+
+    let mut s = SubCommand::with_name("reload")
+        .about(SHORT)
+        .template(SUBCOMMAND_HELP_TEMPLATE)
+        .usage(USAGE);
+
+    let mut args: Vec<Arg> = vec![];
+    arg_load_contents(&mut args);
+    arg_load_git(&mut args);
+    arg_load_rev(&mut args);
+    for a in args {
+        s = s.arg(a);
+    }
+
+    commands.push(s);
+}
+
 fn sub_command_init(commands: &mut Vec<App>) {
     const ABOUT: &str = "Initialize a bifrost directory within the current working directory";
     const USAGE: &str = "bifrost init
     bifrost init --project=jupiter
     bifrost init --project=quasar --workspace=shattuck --ignore=relu
-    bifrost init --project=jupiter --ignore .git --container=docker";
+    bifrost init --project=jupiter --ignore .git --container=docker
+    bifrost init --from-devcontainer";
 
     let mut s = SubCommand::with_name("init")
         .about(ABOUT)
@@ -324,9 +887,37 @@ fn all_init_args() -> Vec<Arg> {
     arg_init_ignore(&mut init_args);
     arg_init_command(&mut init_args);
     arg_init_container(&mut init_args);
+    arg_init_from_devcontainer(&mut init_args);
     init_args
 }
 
+fn arg_init_from_devcontainer(args: &mut Vec<Arg>) {
+    const SHORT: &str = "Translate an existing devcontainer.json into the manifest";
+    const LONG: &str = "
+Translate the image (or Dockerfile), `containerEnv`, `mounts`,
+`forwardPorts` and `postCreateCommand` of an existing devcontainer.json
+into the Bifrost.toml manifest. Fields that cannot be translated are
+reported. Without a path, .devcontainer/devcontainer.json and then
+.devcontainer.json are used.
+
+\t$ bifrost init --from-devcontainer
+\t$ bifrost init --from-devcontainer=.devcontainer/rust/devcontainer.json
+
+
+";
+
+    let a = Arg::with_name("from-devcontainer")
+        .help(SHORT)
+        .long_help(LONG)
+        .long("from-devcontainer")
+        .takes_value(true)
+        .require_equals(true)
+        .min_values(0)
+        .value_name("PATH");
+
+    args.push(a);
+}
+
 fn arg_init_project(args: &mut Vec<Arg>) {
     const SHORT: &str = "The project name for a Bifrost realm";
     const LONG: &str = "
@@ -376,14 +967,16 @@ in the Bifrost.toml manifest.
 fn arg_init_ignore(args: &mut Vec<Arg>) {
     const SHORT: &str = "The files and/or directories to be ignored";
     const LONG: &str = "
-The names of files and/or directories to be ignored from the Bifrost
-workspace. Typically, these values are .git directories, .gitignore
-files, or other contents that are non-essential to the functionality
-of a project.
+The names (or gitignore-style patterns) of files and/or directories
+to be ignored from the Bifrost workspace. Typically, these values are
+.git directories, .gitignore files, or other contents that are
+non-essential to the functionality of a project. Patterns with a
+slash (e.g. `/target` or `docs/build/`) are anchored to the realm.
 
     bifrost init --ignore .git
     bifrost init --ignore .git .gitignore
     bifrost init --ignore .gitignore target
+    bifrost init --ignore /target '**/*.o'
 
 
 ";
@@ -1,12 +1,19 @@
 //! Primary configuration structure and utilities for Bifrost realms.
+use serde::ser::{Serialize, SerializeMap, Serializer};
 use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::time::Duration;
 
+use crate::core::devcontainer::DevContainer;
 use crate::core::hofund;
+use crate::core::workingdir::Symlinks;
+use crate::util::docker::{NetworkOptions, ResourceLimits};
+use crate::util::services::Service;
 use crate::util::BifrostResult;
 use crate::ArgMatches;
 
@@ -163,14 +170,122 @@ impl Config {
     pub fn manifest_mut(&mut self) -> Option<&mut BifrostManifest> {
         self.manifest.as_mut()
     }
+
+    /// Resolves the `[env]` table of the manifest into `EnvVar`s.
+    ///
+    /// Values are taken, in order of precedence, from the untracked
+    /// `Bifrost.secrets.toml` file in the current working directory, the
+    /// manifest itself, and finally the host environment (for entries that
+    /// are declared without a value). Every value that comes from the secrets
+    /// file is considered secret.
+    ///
+    /// # Errors
+    ///
+    /// If the secrets file exists but cannot be read or parsed, or if an entry
+    /// has no value in any of the above locations, then this method returns
+    /// an error.
+    pub fn env_vars(&self) -> BifrostResult<Vec<EnvVar>> {
+        let secrets = SecretsFile::from_cwd(self.cwd())?;
+        resolve_env(self.manifest().and_then(|m| m.env.as_ref()), secrets.env)
+    }
+
+    /// Returns the sidecar services of the manifest ordered by name. Their
+    /// environments are resolved like the `[env]` table (see `env_vars`),
+    /// with the secrets of service `name` taken from `[services.<name>.env]`
+    /// of `Bifrost.secrets.toml`.
+    ///
+    /// # Errors
+    ///
+    /// If the secrets file exists but cannot be read or parsed, or if an entry
+    /// has no value, then this method returns an error.
+    pub fn services(&self) -> BifrostResult<Vec<Service>> {
+        let mut secrets = SecretsFile::from_cwd(self.cwd())?;
+
+        let mut services = vec![];
+        for (name, service) in self
+            .manifest()
+            .and_then(|m| m.services.as_ref())
+            .iter()
+            .flat_map(|s| s.iter())
+        {
+            let secrets = secrets
+                .services
+                .remove(name)
+                .map(|s| s.env)
+                .unwrap_or_default();
+            let env = resolve_env(service.env.as_ref(), secrets)?;
+            services.push(service.to_service(name, env));
+        }
+        Ok(services)
+    }
+}
+
+/// Resolves an `env` `table` into `EnvVar`s, taking values from `secrets`, the
+/// table itself, and the host environment, in that order. Entries that only
+/// appear in `secrets` are included too.
+///
+/// # Errors
+///
+/// If an entry has no value in any of those places, then this function
+/// returns an error.
+fn resolve_env(
+    table: Option<&BTreeMap<String, EnvValue>>,
+    secrets: BTreeMap<String, String>,
+) -> BifrostResult<Vec<EnvVar>> {
+    let mut vars: BTreeMap<String, EnvVar> = BTreeMap::new();
+    for (name, entry) in table.iter().flat_map(|t| t.iter()) {
+        let value = match secrets.get(name) {
+            Some(v) => Some(v.clone()),
+            None => entry
+                .value()
+                .map(String::from)
+                .or_else(|| env::var(name).ok()),
+        };
+
+        match value {
+            Some(value) => {
+                vars.insert(
+                    name.clone(),
+                    EnvVar {
+                        name: name.clone(),
+                        value,
+                        secret: entry.is_secret() || secrets.contains_key(name),
+                    },
+                );
+            }
+            None => failure::bail!(
+                "error: env var `{}` has no value in `Bifrost.toml`, `{}`, \
+                 or the host environment",
+                name,
+                SECRETS_FILE
+            ),
+        }
+    }
+
+    // Entries that only appear in the secrets file are passed along too.
+    for (name, value) in secrets {
+        vars.entry(name.clone()).or_insert(EnvVar {
+            name,
+            value,
+            secret: true,
+        });
+    }
+
+    Ok(vars.into_values().collect())
 }
 
+/// Name of the untracked file that holds secret values for a Bifrost realm.
+pub const SECRETS_FILE: &str = "Bifrost.secrets.toml";
+
 /// Primary structure to serialize and deserialize Bifrost manifest data.
 #[derive(Debug, Deserialize, Serialize)]
 pub struct BifrostManifest {
     workspace: Option<WorkSpaceConfig>,
     container: Option<ContainerConfig>,
     command: Option<CommandConfig>,
+    #[serde(serialize_with = "serialize_env")]
+    env: Option<BTreeMap<String, EnvValue>>,
+    services: Option<BTreeMap<String, ServiceConfig>>,
 }
 
 /// Constructs a default `BifrostManifest` from a raw string literal.
@@ -230,6 +345,38 @@ impl BifrostManifest {
         }
     }
 
+    /// Constructs a manifest for the workspace `name` from a translated
+    /// `devcontainer.json`.
+    pub fn from_devcontainer(name: &str, devcontainer: DevContainer) -> Self {
+        let mut manifest = BifrostManifest::default();
+        if let Some(ref mut workspace) = manifest.workspace {
+            workspace.name = Some(String::from(name));
+        }
+
+        let container = ContainerConfig {
+            name: Some(String::from("docker")),
+            user: devcontainer.user,
+            image: devcontainer.image,
+            dockerfile: devcontainer.dockerfile,
+            context: devcontainer.context,
+            mount_point: devcontainer.mount_point,
+            ports: Some(devcontainer.ports).filter(|p| !p.is_empty()),
+            mounts: Some(devcontainer.mounts).filter(|m| !m.is_empty()),
+            ..Default::default()
+        };
+        manifest.container = Some(container);
+
+        if !devcontainer.cmds.is_empty() {
+            manifest.command = Some(CommandConfig {
+                cmds: Some(devcontainer.cmds),
+            });
+        }
+        if !devcontainer.env.is_empty() {
+            manifest.env = Some(devcontainer.env);
+        }
+        manifest
+    }
+
     /// Constructs a `BifrostManifest` from an existing Bifrost.toml manifest file.
     ///
     /// # Errors
@@ -282,7 +429,19 @@ try:
     pub fn combine_with(mut self, args: &ArgMatches) -> Self {
         self.container = match value_of("container", &args) {
             None => self.container,
-            Some(c) => Some(ContainerConfig { name: Some(c) }),
+            Some(c) => {
+                let mut container = self.container.unwrap_or_default();
+                container.name = Some(c);
+                Some(container)
+            }
+        };
+
+        self.container = match self.container {
+            Some(c) => Some(c.combine_with(args)),
+            None if ContainerConfig::args_present(args) => {
+                Some(ContainerConfig::default().combine_with(args))
+            }
+            None => None,
         };
 
         self.workspace = match value_of("workspace", &args) {
@@ -297,13 +456,17 @@ try:
                 }
             },
             Some(ws) => {
+                let workspace = self.workspace.unwrap();
                 let ignore = match values_of("ignore", &args) {
-                    None => self.workspace.unwrap().ignore,
+                    None => workspace.ignore,
                     Some(ig) => Some(ig),
                 };
                 Some(WorkSpaceConfig {
                     name: Some(ws),
                     ignore,
+                    include: workspace.include,
+                    gitignore: workspace.gitignore,
+                    symlinks: workspace.symlinks,
                 })
             }
         };
@@ -319,7 +482,8 @@ try:
     /// Returns a `BifrostResult`.
     ///
     /// If this value is `Ok`, then this result contains the string representation
-    /// of the TOML-serialized `BifrostManifest`.
+    /// of the TOML-serialized `BifrostManifest`. Values of secret `[env]` entries
+    /// are never serialized; only the fact that they are secret is written.
     pub fn to_str(&self) -> BifrostResult<String> {
         match toml::to_string(&self) {
             Ok(s) => Ok(String::from(s)),
@@ -335,6 +499,11 @@ try:
         self.workspace.as_ref()
     }
 
+    /// Gets a reference to the manifest's `ContainerConfig`.
+    pub fn get_container_config(&self) -> Option<&ContainerConfig> {
+        self.container.as_ref()
+    }
+
     /// Gets a reference to the manifest's `BinaryConfig`.
     pub fn get_command_config(&self) -> Option<&CommandConfig> {
         self.command.as_ref()
@@ -372,15 +541,374 @@ pub fn values_of(arg: &str, from_args: &ArgMatches) -> Option<Vec<String>> {
     None
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct ContainerConfig {
+/// An entry in the `[env]` table of a Bifrost.toml manifest. An entry is either
+/// a plain string or a table that marks the variable as secret:
+///
+/// ```toml
+/// [env]
+/// RUST_LOG = "debug"
+/// API_TOKEN = { secret = true }
+/// ```
+///
+/// Secret entries without a `value` are resolved from `Bifrost.secrets.toml`
+/// or the host environment when a workspace is run.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Plain(String),
+    Detailed {
+        value: Option<String>,
+        #[serde(default)]
+        secret: bool,
+    },
+}
+
+impl EnvValue {
+    /// Returns the value written in the manifest, if any.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            EnvValue::Plain(v) => Some(v),
+            EnvValue::Detailed { value, .. } => value.as_ref().map(|v| v.as_ref()),
+        }
+    }
+
+    /// Returns whether or not this entry has been marked secret.
+    pub fn is_secret(&self) -> bool {
+        match self {
+            EnvValue::Plain(_) => false,
+            EnvValue::Detailed { secret, .. } => *secret,
+        }
+    }
+}
+
+/// Serializes an `EnvValue` such that secret values are never written back to
+/// disk; a secret entry is written as `{ secret = true }` and an entry taken
+/// from the host environment as `{}`.
+impl Serialize for EnvValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_secret() {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry("secret", &true)?;
+            return map.end();
+        }
+        match self.value() {
+            Some(value) => serializer.serialize_str(value),
+            None => serializer.serialize_map(Some(0))?.end(),
+        }
+    }
+}
+
+/// Serializes the `[env]` table with plain values ahead of other entries;
+/// TOML requires that values are emitted before (sub-)tables.
+fn serialize_env<S: Serializer>(
+    env: &Option<BTreeMap<String, EnvValue>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match env {
+        None => serializer.serialize_none(),
+        Some(env) => {
+            let mut map = serializer.serialize_map(Some(env.len()))?;
+            let is_table = |v: &EnvValue| v.is_secret() || v.value().is_none();
+            for (name, value) in env.iter().filter(|(_, v)| !is_table(v)) {
+                map.serialize_entry(name, value)?;
+            }
+            for (name, value) in env.iter().filter(|(_, v)| is_table(v)) {
+                map.serialize_entry(name, value)?;
+            }
+            map.end()
+        }
+    }
+}
+
+/// The number of seconds to wait for a service to become healthy by default.
+const DEFAULT_HEALTH_TIMEOUT: u64 = 60;
+
+/// An entry in the `[services]` table of a Bifrost.toml manifest, a sidecar
+/// service started for the duration of `bifrost run`:
+///
+/// ```toml
+/// [services.db]
+/// image = "postgres:15"
+/// health_check = "pg_isready -U postgres"
+///
+/// [services.db.env]
+/// POSTGRES_PASSWORD = { secret = true }
+/// ```
+///
+/// The realm's container reaches the service by its name (e.g. `db:5432`).
+/// Like the `[env]` table, `env` entries may be secret and take their values
+/// from `[services.<name>.env]` of `Bifrost.secrets.toml`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ServiceConfig {
+    image: String,
+    /// Ports published to the host (e.g. `["5432:5432"]`).
+    ports: Option<Vec<String>>,
+    /// A shell command, run within the service's container, that succeeds
+    /// once the service is ready.
+    health_check: Option<String>,
+    /// The number of seconds to wait for the service to become healthy.
+    health_timeout: Option<u64>,
+    #[serde(serialize_with = "serialize_env")]
+    env: Option<BTreeMap<String, EnvValue>>,
+}
+
+impl ServiceConfig {
+    /// Returns the service named `name` with its resolved `env`.
+    pub fn to_service(&self, name: &str, env: Vec<EnvVar>) -> Service {
+        Service {
+            name: String::from(name),
+            image: self.image.clone(),
+            env,
+            ports: self.ports.clone().unwrap_or_default(),
+            health_check: self.health_check.clone(),
+            health_timeout: Duration::from_secs(
+                self.health_timeout.unwrap_or(DEFAULT_HEALTH_TIMEOUT),
+            ),
+        }
+    }
+}
+
+/// A resolved environment variable that is passed to the bifrost container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+    pub secret: bool,
+}
+
+/// Contents of the untracked `Bifrost.secrets.toml` file. Every value found in
+/// this file is treated as a secret.
+#[derive(Debug, Default, Deserialize)]
+struct SecretsFile {
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    /// The secrets of each service (`[services.<name>.env]`).
+    #[serde(default)]
+    services: BTreeMap<String, SecretsService>,
+}
+
+/// The secrets of a service in `Bifrost.secrets.toml`.
+#[derive(Debug, Default, Deserialize)]
+struct SecretsService {
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+impl SecretsFile {
+    /// Reads the secrets file from `cwd` if it exists; otherwise, returns an
+    /// empty `SecretsFile`.
+    fn from_cwd(cwd: &Path) -> BifrostResult<Self> {
+        let path = cwd.join(SECRETS_FILE);
+        if fs::metadata(&path).is_err() {
+            return Ok(SecretsFile::default());
+        }
+
+        match toml::from_str(&hofund::read(&path)?) {
+            Ok(s) => Ok(s),
+            Err(e) => failure::bail!("error: could not parse `{}` due to {}", SECRETS_FILE, e),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ContainerConfig {
     name: Option<String>,
+    /// The user commands are run as within the container. On Linux, bifrost
+    /// defaults to the invoking user's uid/gid (unless the daemon runs
+    /// rootless or with userns-remap) so that files written to the mounted
+    /// workspace are not owned by root; `user = "root"` opts out.
+    user: Option<String>,
+    /// The number of CPUs the container may use.
+    cpus: Option<f64>,
+    /// The memory limit of the container (e.g. `"2g"`).
+    memory: Option<String>,
+    /// The maximum number of processes within the container.
+    pids_limit: Option<u64>,
+    /// The networking mode of the container: `none`, `bridge`, or `host`.
+    network: Option<String>,
+    /// Ports published to the host (e.g. `["8080:80"]`).
+    ports: Option<Vec<String>>,
+    /// An existing image the realm's container is created from
+    /// (e.g. `"rust:1.70"`).
+    image: Option<String>,
+    /// A Dockerfile, relative to the realm, the realm's image is built from.
+    dockerfile: Option<String>,
+    /// The build context of the Dockerfile, relative to the realm (the
+    /// Dockerfile's directory by default).
+    context: Option<String>,
+    /// The absolute path the workspace is mounted at within the container
+    /// (e.g. `"/workspace"`). Defaults to `/bifrost/bifrost/<name>`.
+    mount_point: Option<String>,
+    /// The directory, relative to the workspace, commands are run from.
+    workdir: Option<String>,
+    /// Additional host bind mounts and named cache volumes.
+    mounts: Option<Vec<MountConfig>>,
+    /// The `--cpus` argument, parsed when the limits are resolved.
+    #[serde(skip)]
+    cpus_arg: Option<String>,
+    /// The `--pids-limit` argument, parsed when the limits are resolved.
+    #[serde(skip)]
+    pids_limit_arg: Option<String>,
+}
+
+/// An entry in the `[[container.mounts]]` array of a Bifrost.toml manifest:
+///
+/// ```toml
+/// [[container.mounts]]
+/// type = "bind"
+/// source = "~/datasets"
+/// target = "/data"
+/// read_only = true
+///
+/// [[container.mounts]]
+/// type = "volume"
+/// name = "cargo-registry"
+/// target = "/usr/local/cargo/registry"
+/// ```
+///
+/// Named volumes are keyed by realm so that realms do not share caches. A new
+/// volume is owned by the user commands are run as.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum MountConfig {
+    Bind {
+        source: String,
+        target: String,
+        #[serde(default)]
+        read_only: bool,
+    },
+    Volume {
+        name: String,
+        target: String,
+        #[serde(default)]
+        read_only: bool,
+    },
+}
+
+impl ContainerConfig {
+    /// Replaces fields with values passed as `clap::ArgMatches` (if they exist).
+    ///
+    /// `cpus` and `pids-limit` are kept as passed and only parsed by
+    /// [`limits`](#method.limits), so that invalid values are reported as
+    /// errors.
+    fn combine_with(mut self, args: &ArgMatches) -> Self {
+        if let Some(cpus) = value_of("cpus", args) {
+            self.cpus_arg = Some(cpus);
+        }
+        if let Some(memory) = value_of("memory", args) {
+            self.memory = Some(memory);
+        }
+        if let Some(pids_limit) = value_of("pids-limit", args) {
+            self.pids_limit_arg = Some(pids_limit);
+        }
+        if let Some(network) = value_of("network", args) {
+            self.network = Some(network);
+        }
+        if let Some(mut ports) = values_of("publish", args) {
+            for port in self.ports.take().unwrap_or_default() {
+                if !ports.contains(&port) {
+                    ports.push(port);
+                }
+            }
+            self.ports = Some(ports);
+        }
+        self
+    }
+
+    /// Returns whether or not any container arguments were passed.
+    fn args_present(args: &ArgMatches) -> bool {
+        ["cpus", "memory", "pids-limit", "network", "publish"]
+            .iter()
+            .any(|a| args.is_present(a))
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|n| n.as_ref())
+    }
+
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_ref().map(|u| u.as_ref())
+    }
+
+    /// Returns the resource limits of the container, preferring any passed
+    /// `--cpus` and `--pids-limit` arguments.
+    ///
+    /// # Errors
+    ///
+    /// If `--cpus` or `--pids-limit` were passed but are not numbers.
+    pub fn limits(&self) -> BifrostResult<ResourceLimits> {
+        let cpus = match self.cpus_arg {
+            Some(ref cpus) => Some(parse_arg("cpus", cpus)?),
+            None => self.cpus,
+        };
+        let pids_limit = match self.pids_limit_arg {
+            Some(ref pids_limit) => Some(parse_arg("pids-limit", pids_limit)?),
+            None => self.pids_limit,
+        };
+        Ok(ResourceLimits {
+            cpus,
+            memory: self.memory.clone(),
+            pids_limit,
+        })
+    }
+
+    pub fn image(&self) -> Option<&str> {
+        self.image.as_ref().map(|i| i.as_ref())
+    }
+
+    pub fn dockerfile(&self) -> Option<&str> {
+        self.dockerfile.as_ref().map(|d| d.as_ref())
+    }
+
+    /// Returns the build context of the Dockerfile, relative to the realm.
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_ref().map(|c| c.as_ref())
+    }
+
+    /// Returns the path the workspace is mounted at within the container.
+    pub fn mount_point(&self) -> Option<&str> {
+        self.mount_point.as_ref().map(|m| m.as_ref())
+    }
+
+    /// Returns the directory, relative to the workspace, commands are run from.
+    pub fn workdir(&self) -> Option<&str> {
+        self.workdir.as_ref().map(|w| w.as_ref())
+    }
+
+    /// Returns the additional mounts of the container.
+    pub fn mounts(&self) -> &[MountConfig] {
+        self.mounts.as_ref().map_or(&[], |m| m.as_ref())
+    }
+
+    /// Returns the network options of the container.
+    pub fn network(&self) -> NetworkOptions {
+        NetworkOptions {
+            mode: self.network.clone(),
+            ports: self.ports.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Parses a command line argument or exits with an error message.
+fn parse_arg<T: std::str::FromStr>(arg: &str, value: &str) -> BifrostResult<T> {
+    match value.parse() {
+        Ok(v) => Ok(v),
+        Err(_) => failure::bail!("error: invalid value `{}` for `--{}`", value, arg),
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct WorkSpaceConfig {
     name: Option<String>,
     ignore: Option<Vec<String>>,
+    /// Patterns of the files to load (all of them, if it is `None`).
+    include: Option<Vec<String>>,
+    /// Whether or not `.gitignore` and `.git/info/exclude` files are honored
+    /// (they are unless this is `false`).
+    gitignore: Option<bool>,
+    /// How symbolic links are loaded (they are preserved unless this says
+    /// otherwise).
+    symlinks: Option<Symlinks>,
 }
 
 impl WorkSpaceConfig {
@@ -388,6 +916,9 @@ impl WorkSpaceConfig {
         WorkSpaceConfig {
             name: Some(String::from(name)),
             ignore: Some(ignore),
+            include: None,
+            gitignore: None,
+            symlinks: None,
         }
     }
 
@@ -398,6 +929,20 @@ impl WorkSpaceConfig {
     pub fn ignore(&self) -> Option<&Vec<String>> {
         self.ignore.as_ref().map(|i| i.as_ref())
     }
+
+    pub fn include(&self) -> Option<&Vec<String>> {
+        self.include.as_ref()
+    }
+
+    /// Returns whether or not `.gitignore` files should be honored.
+    pub fn gitignore(&self) -> bool {
+        self.gitignore.unwrap_or(true)
+    }
+
+    /// Returns how symbolic links should be loaded.
+    pub fn symlinks(&self) -> Symlinks {
+        self.symlinks.unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -545,4 +1090,201 @@ mod test {
                 .get_cmds()
         );
     }
+
+    #[test]
+    fn test_to_str_does_not_write_secrets() {
+        let manifest: BifrostManifest = toml::from_str(
+            r#"[env]
+RUST_LOG = "debug"
+API_TOKEN = { value = "hunter2", secret = true }
+"#,
+        )
+        .expect("BUG: `test_to_str_does_not_write_secrets` has invalid toml");
+
+        let s = manifest
+            .to_str()
+            .expect("BUG: `test_to_str_does_not_write_secrets` failed `to_str`");
+        assert!(!s.contains("hunter2"));
+        assert!(s.contains("RUST_LOG = \"debug\""));
+
+        // The entry is still marked secret when read back.
+        let manifest: BifrostManifest =
+            toml::from_str(&s).expect("BUG: `to_str` produced invalid toml");
+        let env = manifest.env.expect("BUG: `[env]` was not written");
+        assert!(env["API_TOKEN"].is_secret());
+        assert_eq!(None, env["API_TOKEN"].value());
+    }
+
+    #[test]
+    fn test_to_str_keeps_host_env_entries() {
+        let manifest: BifrostManifest = toml::from_str(
+            r#"[env]
+HOME_DIR = {}
+"#,
+        )
+        .expect("BUG: `test_to_str_keeps_host_env_entries` has invalid toml");
+
+        let s = manifest
+            .to_str()
+            .expect("BUG: `test_to_str_keeps_host_env_entries` failed `to_str`");
+        assert!(!s.contains("HOME_DIR = \"\""));
+
+        let manifest: BifrostManifest =
+            toml::from_str(&s).expect("BUG: `to_str` produced invalid toml");
+        let env = manifest.env.expect("BUG: `[env]` was not written");
+        assert!(!env["HOME_DIR"].is_secret());
+        assert_eq!(None, env["HOME_DIR"].value());
+    }
+
+    #[test]
+    fn test_services() {
+        let mut config = Config::default();
+        config.cwd = PathBuf::from("tests")
+            .join("test_user")
+            .join("test_app_dir");
+        config.manifest = Some(
+            toml::from_str(
+                r#"[services.db]
+image = "postgres:15"
+health_check = "pg_isready"
+[services.db.env]
+POSTGRES_USER = "test"
+POSTGRES_PASSWORD = { secret = true }
+"#,
+            )
+            .expect("BUG: `test_services` has invalid toml"),
+        );
+
+        let services = config
+            .services()
+            .expect("BUG: `test_services` failed to resolve services");
+        assert_eq!(1, services.len());
+        assert_eq!("db", services[0].name);
+        // The password comes from `[services.db.env]` of the secrets file.
+        assert_eq!(
+            vec![
+                EnvVar {
+                    name: String::from("POSTGRES_PASSWORD"),
+                    value: String::from("test-password"),
+                    secret: true,
+                },
+                EnvVar {
+                    name: String::from("POSTGRES_USER"),
+                    value: String::from("test"),
+                    secret: false,
+                },
+            ],
+            services[0].env
+        );
+        assert_eq!(
+            Duration::from_secs(DEFAULT_HEALTH_TIMEOUT),
+            services[0].health_timeout
+        );
+
+        // Services survive a round trip through `to_str` without their
+        // secrets.
+        let s = config
+            .manifest()
+            .expect("BUG: `test_services` has no manifest")
+            .to_str()
+            .expect("BUG: `test_services` failed `to_str`");
+        assert!(!s.contains("test-password"));
+        config.manifest = Some(toml::from_str(&s).expect("BUG: `to_str` produced invalid toml"));
+        assert_eq!(
+            services,
+            config
+                .services()
+                .expect("BUG: `test_services` failed to resolve services")
+        );
+    }
+
+    #[test]
+    fn test_from_devcontainer() {
+        let devcontainer = DevContainer::from_str(
+            r#"{ "image": "rust:1.70", "containerEnv": { "TOKEN": "${localEnv:TOKEN}" } }"#,
+            Path::new(".devcontainer"),
+        )
+        .expect("BUG: `test_from_devcontainer` has invalid json");
+
+        let manifest = BifrostManifest::from_devcontainer("app", devcontainer);
+        let s = manifest
+            .to_str()
+            .expect("BUG: `test_from_devcontainer` failed `to_str`");
+        let manifest: BifrostManifest =
+            toml::from_str(&s).expect("BUG: `to_str` produced invalid toml");
+
+        let container = manifest.get_container_config().expect("no `[container]`");
+        assert_eq!(Some("rust:1.70"), container.image());
+        // Entries taken from the host environment keep having no value.
+        let env = manifest.env.expect("BUG: `[env]` was not written");
+        assert_eq!(None, env["TOKEN"].value());
+        assert!(!env["TOKEN"].is_secret());
+    }
+
+    #[test]
+    fn test_env_vars_from_secrets_file() {
+        let mut config = Config::default();
+        config.cwd = PathBuf::from("tests")
+            .join("test_user")
+            .join("test_app_dir");
+        config.manifest = Some(
+            toml::from_str(
+                r#"[env]
+RUST_LOG = "debug"
+API_TOKEN = { secret = true }
+"#,
+            )
+            .expect("BUG: `test_env_vars_from_secrets_file` has invalid toml"),
+        );
+
+        let vars = config
+            .env_vars()
+            .expect("BUG: `test_env_vars_from_secrets_file` failed to resolve env");
+
+        let left = vec![
+            EnvVar {
+                name: String::from("API_TOKEN"),
+                value: String::from("test-token"),
+                secret: true,
+            },
+            EnvVar {
+                name: String::from("EXTRA_SECRET"),
+                value: String::from("only-in-secrets-file"),
+                secret: true,
+            },
+            EnvVar {
+                name: String::from("RUST_LOG"),
+                value: String::from("debug"),
+                secret: false,
+            },
+        ];
+        assert_eq!(left, vars);
+    }
+
+    #[test]
+    fn test_limits_with_invalid_args() {
+        let mut container = ContainerConfig {
+            cpus: Some(2.0),
+            pids_limit: Some(256),
+            ..ContainerConfig::default()
+        };
+        let limits = container
+            .limits()
+            .expect("BUG: `test_limits_with_invalid_args` failed to resolve limits");
+        assert_eq!(Some(2.0), limits.cpus);
+        assert_eq!(Some(256), limits.pids_limit);
+
+        container.cpus_arg = Some(String::from("0.5"));
+        let limits = container
+            .limits()
+            .expect("BUG: `test_limits_with_invalid_args` failed to parse `--cpus`");
+        assert_eq!(Some(0.5), limits.cpus);
+
+        container.cpus_arg = Some(String::from("two"));
+        assert!(container.limits().is_err());
+
+        container.cpus_arg = None;
+        container.pids_limit_arg = Some(String::from("-1"));
+        assert!(container.limits().is_err());
+    }
 }
@@ -0,0 +1,545 @@
+//! Translation of a `devcontainer.json` into the sections of a Bifrost.toml
+//! manifest for `bifrost init --from-devcontainer`.
+//!
+//! The image (or Dockerfile), `containerEnv`, `mounts`, `forwardPorts` and
+//! `postCreateCommand` have counterparts in a manifest; so do
+//! `containerUser` and `workspaceFolder`. Everything else, and anything the
+//! above contain that bifrost cannot express (e.g. most `${...}` variables), is
+//! reported as unmapped.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::core::config::{EnvValue, MountConfig};
+use crate::util::BifrostResult;
+
+/// Where `devcontainer.json` is looked for (relative to the realm) when no
+/// path is given.
+pub const DEFAULT_PATHS: &[&str] = &[".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+/// The parts of a `devcontainer.json` that map onto a Bifrost.toml manifest.
+#[derive(Debug, Default)]
+pub struct DevContainer {
+    /// `image`.
+    pub image: Option<String>,
+    /// `build.dockerfile` (or `dockerFile`) relative to the realm.
+    pub dockerfile: Option<String>,
+    /// `build.context` relative to the realm, if it is not the directory of
+    /// the Dockerfile.
+    pub context: Option<String>,
+    /// `containerUser`.
+    pub user: Option<String>,
+    /// `workspaceFolder`.
+    pub mount_point: Option<String>,
+    /// `forwardPorts` as published ports.
+    pub ports: Vec<String>,
+    /// `mounts`.
+    pub mounts: Vec<MountConfig>,
+    /// `containerEnv`.
+    pub env: BTreeMap<String, EnvValue>,
+    /// `postCreateCommand` as commands to run.
+    pub cmds: Vec<String>,
+    /// Descriptions of what could not be mapped.
+    pub unmapped: Vec<String>,
+}
+
+impl DevContainer {
+    /// Finds the `devcontainer.json` of the realm at `cwd`.
+    ///
+    /// # Errors
+    ///
+    /// If none of `DEFAULT_PATHS` exist, then this function returns an error.
+    pub fn find(cwd: &Path) -> BifrostResult<PathBuf> {
+        match DEFAULT_PATHS
+            .iter()
+            .map(|p| cwd.join(p))
+            .find(|p| p.is_file())
+        {
+            Some(path) => Ok(path),
+            None => failure::bail!(
+                "error: could not find a devcontainer.json, looked for `{}`",
+                DEFAULT_PATHS.join("`, `")
+            ),
+        }
+    }
+
+    /// Reads and translates the `devcontainer.json` at `path` for the realm at
+    /// `cwd`.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or is not a JSON (with comments) object,
+    /// then this function returns an error.
+    pub fn from_path(path: &Path, cwd: &Path) -> BifrostResult<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => failure::bail!("error: could not read `{}` due to {}", path.display(), e),
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let dir = dir.strip_prefix(cwd).unwrap_or(dir);
+        DevContainer::from_str(&contents, dir)
+    }
+
+    /// Translates the contents of a `devcontainer.json` that lives in `dir`
+    /// (relative to the realm).
+    ///
+    /// # Errors
+    ///
+    /// If `contents` is not a JSON (with comments) object, then this function
+    /// returns an error.
+    pub fn from_str(contents: &str, dir: &Path) -> BifrostResult<Self> {
+        let json: Map<String, Value> = match serde_json::from_str(&strip_jsonc(contents)) {
+            Ok(json) => json,
+            Err(e) => failure::bail!("error: could not parse devcontainer.json due to {}", e),
+        };
+
+        let mut dc = DevContainer::default();
+        // The build context defaults to the directory of devcontainer.json.
+        let mut context = Value::from(".");
+        for (key, value) in json {
+            match key.as_ref() {
+                "image" => dc.image = dc.string(&key, &value),
+                "dockerFile" => dc.dockerfile(dir, &value),
+                "build" => {
+                    match value.get("dockerfile").or_else(|| value.get("dockerFile")) {
+                        Some(dockerfile) => dc.dockerfile(dir, dockerfile),
+                        None => dc.unmap("build", "it does not name a `dockerfile`"),
+                    }
+                    if let Some(c) = value.get("context") {
+                        context = c.clone();
+                    }
+                    if let Some(build) = value.as_object() {
+                        for field in build.keys() {
+                            match field.as_ref() {
+                                "dockerfile" | "dockerFile" | "context" => {}
+                                _ => dc.unmap(&format!("build.{}", field), "it is not supported"),
+                            }
+                        }
+                    }
+                }
+                "context" => context = value,
+                "containerUser" => dc.user = dc.string(&key, &value),
+                "workspaceFolder" => dc.mount_point = dc.string(&key, &value),
+                "containerEnv" => dc.env(&value),
+                "mounts" => dc.mounts(&value),
+                "forwardPorts" => dc.ports(&value),
+                "postCreateCommand" => dc.cmds(&value),
+                // The display name of the container has no counterpart.
+                "name" | "$schema" => {}
+                _ => dc.unmap(&key, "bifrost has no equivalent"),
+            }
+        }
+
+        if dc.image.is_some() && dc.dockerfile.is_some() {
+            dc.dockerfile = None;
+            dc.unmap(
+                "build",
+                "`image` is used instead (bifrost needs one or the other)",
+            );
+        }
+        if dc.dockerfile.is_some() {
+            dc.context(dir, &context);
+        }
+        Ok(dc)
+    }
+
+    fn unmap(&mut self, field: &str, reason: &str) {
+        self.unmapped.push(format!("`{}`: {}", field, reason));
+    }
+
+    fn string(&mut self, field: &str, value: &Value) -> Option<String> {
+        match value.as_str() {
+            Some(s) if !has_variables(s) => Some(String::from(s)),
+            Some(_) => {
+                self.unmap(field, "it uses a variable");
+                None
+            }
+            None => {
+                self.unmap(field, "it is not a string");
+                None
+            }
+        }
+    }
+
+    fn dockerfile(&mut self, dir: &Path, value: &Value) {
+        if let Some(dockerfile) = self.string("build.dockerfile", value) {
+            self.dockerfile = Some(to_slash(&normalize(&dir.join(dockerfile))));
+        }
+    }
+
+    /// Bifrost builds from the directory of the Dockerfile unless the
+    /// context is set.
+    fn context(&mut self, dir: &Path, value: &Value) {
+        let context = match self.string("build.context", value) {
+            Some(context) => normalize(&dir.join(context)),
+            None => return,
+        };
+        let dockerfile = self.dockerfile.as_ref().map(PathBuf::from);
+        if dockerfile.as_ref().and_then(|d| d.parent()) == Some(context.as_path()) {
+            return;
+        }
+        self.context = Some(if context.as_os_str().is_empty() {
+            String::from(".")
+        } else {
+            to_slash(&context)
+        });
+    }
+
+    fn env(&mut self, value: &Value) {
+        let env = match value.as_object() {
+            Some(env) => env,
+            None => return self.unmap("containerEnv", "it is not an object"),
+        };
+
+        for (name, value) in env {
+            let field = format!("containerEnv.{}", name);
+            let value = match value.as_str() {
+                Some(value) => value,
+                None => {
+                    self.unmap(&field, "it is not a string");
+                    continue;
+                }
+            };
+
+            // A variable taken from the host's environment as-is is an entry
+            // without a value.
+            if value == format!("${{localEnv:{}}}", name) {
+                self.env.insert(
+                    name.clone(),
+                    EnvValue::Detailed {
+                        value: None,
+                        secret: false,
+                    },
+                );
+            } else if has_variables(value) {
+                self.unmap(&field, "it uses a variable");
+            } else {
+                self.env
+                    .insert(name.clone(), EnvValue::Plain(String::from(value)));
+            }
+        }
+    }
+
+    fn mounts(&mut self, value: &Value) {
+        let mounts = match value.as_array() {
+            Some(mounts) => mounts,
+            None => return self.unmap("mounts", "it is not an array"),
+        };
+
+        for (i, mount) in mounts.iter().enumerate() {
+            let field = format!("mounts[{}]", i);
+            let options: BTreeMap<String, String> = match mount {
+                // `source=...,target=...,type=bind[,readonly]`
+                Value::String(s) => s
+                    .split(',')
+                    .map(|option| {
+                        let mut kv = option.splitn(2, '=');
+                        let key = kv.next().unwrap_or("").trim().to_lowercase();
+                        let value = kv.next().unwrap_or("true").trim();
+                        (key, String::from(value))
+                    })
+                    .collect(),
+                Value::Object(o) => o
+                    .iter()
+                    .map(|(k, v)| {
+                        let v = v
+                            .as_str()
+                            .map(String::from)
+                            .unwrap_or_else(|| v.to_string());
+                        (k.to_lowercase(), v)
+                    })
+                    .collect(),
+                _ => {
+                    self.unmap(&field, "it is not a string or an object");
+                    continue;
+                }
+            };
+
+            let get = |keys: &[&str]| keys.iter().find_map(|k| options.get(*k).cloned());
+            let source = get(&["source", "src"]);
+            let target = get(&["target", "destination", "dst"]);
+            let read_only = get(&["readonly", "ro"]).is_some_and(|v| v != "false");
+            let kind = get(&["type"]).unwrap_or_else(|| String::from("volume"));
+
+            let (source, target) = match (source, target) {
+                (Some(source), Some(target)) => (source, target),
+                _ => {
+                    self.unmap(&field, "it needs both a source and a target");
+                    continue;
+                }
+            };
+            if has_variables(&target) {
+                self.unmap(&field, "its target uses a variable");
+                continue;
+            }
+
+            match kind.as_ref() {
+                "bind" => match host_path(&source) {
+                    Some(source) => self.mounts.push(MountConfig::Bind {
+                        source,
+                        target,
+                        read_only,
+                    }),
+                    None => self.unmap(&field, "its source uses a variable"),
+                },
+                "volume" if !has_variables(&source) => self.mounts.push(MountConfig::Volume {
+                    name: source,
+                    target,
+                    read_only,
+                }),
+                "volume" => self.unmap(&field, "its source uses a variable"),
+                _ => self.unmap(
+                    &field,
+                    &format!("mounts of type `{}` are not supported", kind),
+                ),
+            }
+        }
+    }
+
+    fn ports(&mut self, value: &Value) {
+        let ports = match value.as_array() {
+            Some(ports) => ports,
+            None => return self.unmap("forwardPorts", "it is not an array"),
+        };
+
+        for port in ports {
+            let port = match port {
+                Value::Number(n) => n.to_string(),
+                Value::String(s) if s.parse::<u16>().is_ok() => s.clone(),
+                _ => {
+                    // e.g. `db:5432`, a port of another container.
+                    self.unmap(
+                        &format!("forwardPorts {}", port),
+                        "only ports of the container itself can be published",
+                    );
+                    continue;
+                }
+            };
+            self.ports.push(format!("{}:{}", port, port));
+        }
+    }
+
+    fn cmds(&mut self, value: &Value) {
+        match value {
+            Value::String(cmd) => self.cmds.push(cmd.clone()),
+            // The exec form is run through the shell like any other command.
+            Value::Array(args) => {
+                let args: Vec<String> = args
+                    .iter()
+                    .map(|a| shell_quote(a.as_str().map_or_else(|| a.to_string(), String::from)))
+                    .collect();
+                self.cmds.push(args.join(" "));
+            }
+            // Named commands (run in parallel by devcontainers) run in order.
+            Value::Object(cmds) => {
+                for (_, cmd) in cmds {
+                    self.cmds(cmd);
+                }
+            }
+            _ => self.unmap("postCreateCommand", "it is not a string, array or object"),
+        }
+    }
+}
+
+/// Returns whether or not `s` contains a devcontainer `${...}` variable.
+fn has_variables(s: &str) -> bool {
+    s.contains("${")
+}
+
+/// Translates the source of a bind mount to a path bifrost resolves the same
+/// way: relative to the realm or, with `~/`, to the home directory.
+fn host_path(source: &str) -> Option<String> {
+    let replacements = [
+        ("${localWorkspaceFolder}", "."),
+        ("${localEnv:HOME}", "~"),
+        ("${localEnv:USERPROFILE}", "~"),
+    ];
+    for (variable, replacement) in replacements.iter() {
+        if let Some(rest) = source.strip_prefix(variable) {
+            let path = format!("{}{}", replacement, rest);
+            return if has_variables(&path) {
+                None
+            } else {
+                Some(path)
+            };
+        }
+    }
+
+    if has_variables(source) {
+        None
+    } else {
+        Some(String::from(source))
+    }
+}
+
+/// Quotes `arg` for a POSIX shell if it needs it.
+fn shell_quote(arg: String) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Removes `.` components and resolves `..` components lexically.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+fn to_slash(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Strips the comments and trailing commas JSON with comments (as used by
+/// `devcontainer.json`) allows.
+fn strip_jsonc(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut chars = contents.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            (']', _) | ('}', _) => {
+                // Drop a trailing comma (and keep what followed it).
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.remove(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DEVCONTAINER: &str = r#"{
+    // The name is only shown by editors.
+    "name": "app",
+    "build": {
+        "dockerfile": "Dockerfile",
+        "context": "..",
+        "args": { "VARIANT": "bookworm" },
+    },
+    "containerEnv": {
+        "RUST_LOG": "debug", /* inline */
+        "GITHUB_TOKEN": "${localEnv:GITHUB_TOKEN}",
+        "WORKSPACE": "${containerWorkspaceFolder}",
+    },
+    "mounts": [
+        "source=${localWorkspaceFolder}/data,target=/data,type=bind,readonly",
+        { "source": "cargo-registry", "target": "/usr/local/cargo/registry", "type": "volume" },
+    ],
+    "forwardPorts": [3000, "db:5432"],
+    "postCreateCommand": ["cargo", "build", "--features", "a b"],
+    "customizations": { "vscode": { "extensions": ["rust-lang.rust-analyzer"] } },
+}"#;
+
+    #[test]
+    fn test_from_str() -> BifrostResult<()> {
+        let dc = DevContainer::from_str(DEVCONTAINER, Path::new(".devcontainer"))?;
+
+        assert_eq!(
+            Some(String::from(".devcontainer/Dockerfile")),
+            dc.dockerfile
+        );
+        assert_eq!(Some(String::from(".")), dc.context);
+        assert_eq!(vec![String::from("3000:3000")], dc.ports);
+        assert_eq!(vec![String::from("cargo build --features 'a b'")], dc.cmds);
+
+        assert_eq!(Some("debug"), dc.env["RUST_LOG"].value());
+        assert_eq!(None, dc.env["GITHUB_TOKEN"].value());
+        assert!(!dc.env.contains_key("WORKSPACE"));
+
+        assert_eq!(2, dc.mounts.len());
+        match dc.mounts[0] {
+            MountConfig::Bind {
+                ref source,
+                read_only,
+                ..
+            } => {
+                assert_eq!("./data", source);
+                assert!(read_only);
+            }
+            _ => panic!("expected a bind mount"),
+        }
+
+        let unmapped = dc.unmapped.join("\n");
+        for field in &[
+            "build.args",
+            "containerEnv.WORKSPACE",
+            "forwardPorts \"db:5432\"",
+            "customizations",
+        ] {
+            assert!(unmapped.contains(field), "`{}` was not reported", field);
+        }
+        assert_eq!(4, dc.unmapped.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_strip_jsonc() {
+        assert_eq!(
+            "{\"url\": \"http://a/*b*/\", \"x\": [1, 2 ]\n}",
+            strip_jsonc("{\"url\": \"http://a/*b*/\", /* c */\"x\": [1, 2, ]// d\n}")
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! The rules that decide which paths of a realm are walked (and loaded).
+//!
+//! Paths are excluded by, in order of precedence:
+//!
+//! * the patterns of `[workspace] ignore`,
+//! * the patterns of the `.bifrostignore` (and, unless turned off, `.gitignore`
+//!   and `.git/info/exclude`) files in the directories above them, the deepest
+//!   ones first, up to the root of the git repository the realm is in (or the
+//!   realm root, outside of one),
+//! * not being tracked by git (for `bifrost load --git`), and
+//! * (files only) not matching any pattern of `[workspace] include`.
+//!
+//! Walks also skip special files and, depending on `[workspace] symlinks`,
+//! symbolic links (see the `Rule`s for which `Rule::is_skipped` is true).
+//!
+//! All patterns use gitignore syntax: patterns without a slash match names at
+//! any depth, patterns with one (e.g. `/target` or `docs/build/`) are anchored
+//! to the directory they are relative to (the realm root for the manifest's
+//! patterns), `**` matches any number of directories and `!` re-includes a
+//! path.
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::util::BifrostResult;
+
+/// The name of the files whose patterns exclude paths from being walked,
+/// whether or not `.gitignore` files are honored.
+pub const BIFROST_IGNORE: &str = ".bifrostignore";
+
+/// The rule that excluded a path.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rule {
+    /// A pattern of `[workspace] ignore`.
+    Manifest(String),
+    /// A pattern of the given ignore file.
+    File { pattern: String, source: PathBuf },
+    /// The path is not (and has no files) tracked by git.
+    Untracked,
+    /// The path is a file that matches none of the patterns of
+    /// `[workspace] include`.
+    NotIncluded,
+    /// The path is a symbolic link and symbolic links are skipped.
+    Symlink,
+    /// The path is a symbolic link to the given target outside of the realm.
+    EscapingLink(PathBuf),
+    /// The path is a symbolic link that cannot be followed (it dangles or
+    /// forms a loop).
+    BrokenLink,
+    /// The path is not a regular file, directory or symbolic link (e.g. a
+    /// socket or named pipe).
+    Special,
+}
+
+impl Rule {
+    /// Returns whether or not the path was excluded because of what it is
+    /// rather than by a pattern (and so a load should report it).
+    pub fn is_skipped(&self) -> bool {
+        match self {
+            Rule::Symlink | Rule::EscapingLink(_) | Rule::BrokenLink | Rule::Special => true,
+            Rule::Manifest(_) | Rule::File { .. } | Rule::Untracked | Rule::NotIncluded => false,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::Manifest(pattern) => write!(f, "`{}` in [workspace] ignore", pattern),
+            Rule::File { pattern, source } => write!(f, "`{}` in {}", pattern, source.display()),
+            Rule::Untracked => write!(f, "not tracked by git"),
+            Rule::NotIncluded => write!(f, "not matched by [workspace] include"),
+            Rule::Symlink => write!(f, "symbolic link, [workspace] symlinks = \"skip\""),
+            Rule::EscapingLink(target) => write!(
+                f,
+                "symbolic link to `{}`, outside of the realm",
+                target.display()
+            ),
+            Rule::BrokenLink => write!(f, "symbolic link that cannot be followed"),
+            Rule::Special => write!(f, "not a regular file, directory or symbolic link"),
+        }
+    }
+}
+
+/// A path that was excluded and the rule that excluded it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Exclusion {
+    pub path: PathBuf,
+    pub rule: Rule,
+}
+
+/// The files git tracks and the directories that lead to them.
+#[derive(Debug, Default)]
+pub struct Tracked {
+    files: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+}
+
+impl Tracked {
+    /// Constructs a `Tracked` from the absolute paths of the tracked files.
+    pub fn new(files: HashSet<PathBuf>) -> Self {
+        let mut dirs = HashSet::new();
+        for file in &files {
+            for dir in file.ancestors().skip(1) {
+                if !dirs.insert(dir.to_path_buf()) {
+                    break;
+                }
+            }
+        }
+        Tracked { files, dirs }
+    }
+
+    fn contains(&self, path: &Path, is_dir: bool) -> bool {
+        if is_dir {
+            self.dirs.contains(path)
+        } else {
+            self.files.contains(path)
+        }
+    }
+}
+
+/// Decides which paths are excluded; see the module documentation.
+#[derive(Debug)]
+pub struct Filter {
+    /// The directory the manifest's patterns are relative to.
+    anchor: PathBuf,
+    /// The highest directory whose ignore files are read.
+    top: PathBuf,
+    ignore: Gitignore,
+    include: Option<Gitignore>,
+    /// Whether or not `.gitignore` and `.git/info/exclude` files are honored.
+    gitignore: bool,
+    /// The only files that are not excluded (if any).
+    tracked: Option<Rc<Tracked>>,
+    /// The patterns of the ignore files of each directory seen so far.
+    files: HashMap<PathBuf, Gitignore>,
+}
+
+impl Filter {
+    /// Constructs a `Filter` from the manifest's `ignore` and `include`
+    /// patterns, which are relative to `anchor`. An empty `include` list
+    /// includes everything.
+    ///
+    /// # Errors
+    ///
+    /// If a pattern is not a valid glob, then this function returns an error.
+    pub fn new<S>(
+        anchor: &Path,
+        ignore: &[S],
+        include: &[S],
+        gitignore: bool,
+    ) -> BifrostResult<Self>
+    where
+        S: AsRef<str>,
+    {
+        let include = if include.is_empty() {
+            None
+        } else {
+            Some(patterns(anchor, include, "include")?)
+        };
+
+        // As git does, ignore files above the repository are not read.
+        let top = anchor
+            .ancestors()
+            .find(|dir| dir.join(".git").exists())
+            .unwrap_or(anchor);
+
+        Ok(Filter {
+            anchor: anchor.to_path_buf(),
+            top: top.to_path_buf(),
+            ignore: patterns(anchor, ignore, "ignore")?,
+            include,
+            gitignore,
+            tracked: None,
+            files: HashMap::new(),
+        })
+    }
+
+    /// Excludes everything but the `tracked` files (and the directories that
+    /// lead to them).
+    pub fn tracked(mut self, tracked: Option<Rc<Tracked>>) -> Self {
+        self.tracked = tracked;
+        self
+    }
+
+    /// Returns the rule that excludes `path` (if any), assuming that the
+    /// directories above it are not excluded.
+    pub fn excludes(&mut self, path: &Path, is_dir: bool) -> Option<Rule> {
+        let mut whitelisted = false;
+        if path.starts_with(&self.anchor) {
+            match self.ignore.matched(path, is_dir) {
+                Match::Ignore(glob) => return Some(Rule::Manifest(glob.original().to_string())),
+                // Ignore files cannot exclude what the manifest whitelists,
+                // but the rest of the rules still apply.
+                Match::Whitelist(_) => whitelisted = true,
+                Match::None => {}
+            }
+        }
+
+        let dirs: Vec<PathBuf> = if whitelisted {
+            vec![]
+        } else {
+            path.ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(&self.top))
+                .map(Path::to_path_buf)
+                .collect()
+        };
+        for dir in dirs {
+            match self.ignore_files(&dir).matched(path, is_dir) {
+                Match::Ignore(glob) => {
+                    return Some(Rule::File {
+                        pattern: glob.original().to_string(),
+                        source: glob.from().map(Path::to_path_buf).unwrap_or_default(),
+                    });
+                }
+                Match::Whitelist(_) => break,
+                Match::None => {}
+            }
+        }
+
+        // Whitelists only undo ignores: what git does not track is never
+        // loaded from the index.
+        if let Some(ref tracked) = self.tracked {
+            if !tracked.contains(path, is_dir) {
+                return Some(Rule::Untracked);
+            }
+        }
+
+        match self.include {
+            Some(ref include) if !is_dir && !is_included(include, &self.anchor, path, false) => {
+                Some(Rule::NotIncluded)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the rule that excludes `path`, or one of the directories
+    /// between `root` and `path`, from a walk of `root` (if any).
+    pub fn excludes_below(&mut self, root: &Path, path: &Path) -> Option<Rule> {
+        let relative = path.strip_prefix(root).ok()?;
+        let mut current = root.to_path_buf();
+        for component in relative.components() {
+            current.push(component);
+            let is_dir = current != path || path.is_dir();
+            if let Some(rule) = self.excludes(&current, is_dir) {
+                return Some(rule);
+            }
+        }
+        None
+    }
+
+    /// Returns whether or not a directory should be kept even though none of
+    /// the files in it are: directories are kept unless there is an include
+    /// list that they do not match.
+    pub fn keeps_dir(&self, dir: &Path) -> bool {
+        match self.include {
+            Some(ref include) => is_included(include, &self.anchor, dir, true),
+            None => true,
+        }
+    }
+
+    /// Returns the patterns of the ignore files in `dir`.
+    fn ignore_files(&mut self, dir: &Path) -> &Gitignore {
+        let gitignore = self.gitignore;
+        self.files.entry(dir.to_path_buf()).or_insert_with(|| {
+            let mut builder = GitignoreBuilder::new(dir);
+            // Later files take precedence.
+            let mut files = vec![];
+            if gitignore {
+                files.push(dir.join(".git").join("info").join("exclude"));
+                files.push(dir.join(".gitignore"));
+            }
+            files.push(dir.join(BIFROST_IGNORE));
+            for file in files.iter().filter(|f| f.is_file()) {
+                // Malformed patterns are skipped (as git does).
+                let _ = builder.add(file);
+            }
+            builder.build().unwrap_or_else(|_| Gitignore::empty())
+        })
+    }
+}
+
+/// Builds a matcher of the manifest's `patterns` (of `[workspace] <key>`).
+fn patterns<S>(anchor: &Path, patterns: &[S], key: &str) -> BifrostResult<Gitignore>
+where
+    S: AsRef<str>,
+{
+    let mut builder = GitignoreBuilder::new(anchor);
+    for pattern in patterns {
+        // Some versions of the glob parser take an unclosed `[` literally.
+        if has_unclosed_class(pattern.as_ref()) {
+            failure::bail!(
+                "error: invalid pattern `{}` in [workspace] {}: unclosed character class",
+                pattern.as_ref(),
+                key
+            );
+        }
+        if let Err(e) = builder.add_line(None, pattern.as_ref()) {
+            failure::bail!(
+                "error: invalid pattern `{}` in [workspace] {}: {}",
+                pattern.as_ref(),
+                key,
+                e
+            );
+        }
+    }
+    Ok(builder.build()?)
+}
+
+/// Returns whether or not a `[` of `pattern` starts a character class that is
+/// never closed (a `]` right after the `[`, or after its `!`, is part of the
+/// class).
+fn has_unclosed_class(pattern: &str) -> bool {
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                if let Some('!') | Some('^') = chars.peek() {
+                    chars.next();
+                }
+                if let Some(']') = chars.peek() {
+                    chars.next();
+                }
+                if !chars.any(|c| c == ']') {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Returns whether or not `path` (or a directory above it) matches one of the
+/// `include` patterns.
+fn is_included(include: &Gitignore, anchor: &Path, path: &Path, is_dir: bool) -> bool {
+    path.starts_with(anchor)
+        && path != anchor
+        && include
+            .matched_path_or_any_parents(path, is_dir)
+            .is_ignore()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_manifest_patterns() -> BifrostResult<()> {
+        let root = Path::new("/realm");
+        let ignore = vec!["/target", "**/*.o", "docs/build/", "Bifrost.secrets.toml"];
+        let mut filter = Filter::new(root, &ignore, &[], false)?;
+        let excludes = |filter: &mut Filter, path: &str, is_dir: bool| {
+            filter.excludes(&root.join(path), is_dir)
+        };
+
+        assert_eq!(
+            Some(Rule::Manifest(String::from("/target"))),
+            excludes(&mut filter, "target", true)
+        );
+        // Anchored patterns do not match names elsewhere, and names are not
+        // prefixes.
+        assert_eq!(None, excludes(&mut filter, "src/target", true));
+        assert_eq!(None, excludes(&mut filter, "targets.rs", false));
+        assert!(excludes(&mut filter, "src/lib/main.o", false).is_some());
+        assert!(excludes(&mut filter, "docs/build", true).is_some());
+        assert_eq!(None, excludes(&mut filter, "build", true));
+        assert_eq!(None, excludes(&mut filter, "docs/build", false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_patterns() -> BifrostResult<()> {
+        let root = Path::new("/realm");
+        let include = vec!["src/", "Cargo.*"];
+        let mut filter = Filter::new(root, &["*.tmp"], &include, false)?;
+
+        assert_eq!(None, filter.excludes(&root.join("src/a/main.rs"), false));
+        assert_eq!(None, filter.excludes(&root.join("Cargo.toml"), false));
+        assert_eq!(
+            Some(Rule::NotIncluded),
+            filter.excludes(&root.join("README.md"), false)
+        );
+        // Ignore patterns take precedence.
+        assert!(filter.excludes(&root.join("src/a.tmp"), false).is_some());
+        // Directories are walked, but only kept if they are included.
+        assert_eq!(None, filter.excludes(&root.join("docs"), true));
+        assert!(!filter.keeps_dir(&root.join("docs")));
+        assert!(filter.keeps_dir(&root.join("src")));
+        assert!(filter.keeps_dir(&root.join("src/a")));
+
+        // Whitelisted files still have to be included.
+        let mut filter = Filter::new(root, &["*.md", "!README.md"], &include, false)?;
+        assert_eq!(
+            Some(Rule::NotIncluded),
+            filter.excludes(&root.join("README.md"), false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tracked() -> BifrostResult<()> {
+        let root = Path::new("/realm");
+        let files = vec![root.join("src/main.rs"), root.join("Cargo.toml")];
+        let tracked = Rc::new(Tracked::new(files.into_iter().collect()));
+        let ignore = vec!["Cargo.*", "*.rs", "!scratch.rs"];
+        let mut filter = Filter::new(root, &ignore, &[], true)?.tracked(Some(tracked.clone()));
+
+        assert_eq!(None, filter.excludes(&root.join("src"), true));
+        assert_eq!(
+            Some(Rule::Manifest(String::from("*.rs"))),
+            filter.excludes(&root.join("src/main.rs"), false)
+        );
+        assert_eq!(
+            Some(Rule::Untracked),
+            filter.excludes(&root.join("target"), true)
+        );
+        // Whitelisted, but not tracked.
+        assert_eq!(
+            Some(Rule::Untracked),
+            filter.excludes(&root.join("src/scratch.rs"), false)
+        );
+        // Tracked files can still be ignored.
+        assert_eq!(
+            Some(Rule::Manifest(String::from("Cargo.*"))),
+            filter.excludes(&root.join("Cargo.toml"), false)
+        );
+
+        // Whitelisted files are loaded only if they are tracked.
+        let mut filter = Filter::new(root, &["*.rs", "!*.rs"], &[], false)?.tracked(Some(tracked));
+        assert_eq!(None, filter.excludes(&root.join("src/main.rs"), false));
+        assert_eq!(
+            Some(Rule::Untracked),
+            filter.excludes(&root.join("src/scratch.rs"), false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_files_above_the_realm() -> BifrostResult<()> {
+        let tmp = std::env::temp_dir().join(format!("bifrost-filter-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&tmp);
+        let realm = tmp.join("home").join("realm");
+        std::fs::create_dir_all(&realm)?;
+        std::fs::write(tmp.join("home").join(".gitignore"), "*\n")?;
+        std::fs::write(realm.join(".gitignore"), "*.log\n")?;
+        let excludes = |path: &str| -> BifrostResult<Option<Rule>> {
+            Ok(Filter::new(&realm, &[] as &[&str], &[], true)?.excludes(&realm.join(path), false))
+        };
+
+        // Outside of a repository, only the realm's ignore files are read.
+        assert_eq!(None, excludes("main.rs")?);
+        assert!(excludes("run.log")?.is_some());
+
+        // Within one, those of the repository are read as well.
+        std::fs::create_dir_all(tmp.join("home").join(".git"))?;
+        assert!(excludes("main.rs")?.is_some());
+
+        std::fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(Filter::new(Path::new("/realm"), &["src/[a"], &[], true).is_err());
+        assert!(Filter::new(Path::new("/realm"), &[], &["[]a"], true).is_err());
+        assert!(Filter::new(Path::new("/realm"), &["src/[ab]", "[]]", "\\[a"], &[], true).is_ok());
+    }
+}
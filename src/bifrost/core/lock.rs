@@ -0,0 +1,180 @@
+//! The `Bifrost.lock` file that pins the image a realm runs in.
+//!
+//! On the first `bifrost run` of a realm, what the image it runs in is made of
+//! is written next to its Bifrost.toml. Image ids differ between machines for
+//! images built locally, so a pulled image is pinned by its registry digest
+//! and an image bifrost builds by the hash of its Dockerfile and build context
+//! along with the registry digest of its base image:
+//!
+//! ```toml
+//! [image]
+//! tag = "bifrost:0.1"
+//! context = "0123..."
+//! base = "debian:bookworm"
+//! base_digest = "debian@sha256:..."
+//! ```
+//!
+//! Committing the lock file makes sure everyone running the realm uses the same
+//! image; `bifrost run` refuses to run in any other image unless it is given
+//! `--update-lock`.
+use std::fs;
+use std::path::Path;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::core::hofund;
+use crate::util::BifrostResult;
+
+pub const LOCK_FILE: &str = "Bifrost.lock";
+
+const HEADER: &str = "\
+# This file is generated by bifrost. It pins the image `bifrost run` uses;
+# run `bifrost run --update-lock` to move to a different one.
+";
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Lock {
+    pub image: LockedImage,
+}
+
+/// An image pinned by its registry digest or by what it was built from.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct LockedImage {
+    /// The tag the image was run by.
+    pub tag: String,
+    /// The registry digest (e.g. `postgres@sha256:...`) of a pulled image.
+    pub digest: Option<String>,
+    /// The hash of the Dockerfile and build context of an image bifrost
+    /// builds.
+    pub context: Option<String>,
+    /// The base image the image was built from (if bifrost built it).
+    pub base: Option<String>,
+    /// The registry digest of the base image.
+    pub base_digest: Option<String>,
+}
+
+impl LockedImage {
+    /// Returns what pins the image: its registry digest or the hash it was
+    /// built from.
+    pub fn pin(&self) -> &str {
+        self.digest
+            .as_ref()
+            .or(self.context.as_ref())
+            .map_or("unknown", |p| p.as_str())
+    }
+}
+
+impl Lock {
+    /// Loads the lock file of the realm at `cwd` if it has one.
+    ///
+    /// # Errors
+    ///
+    /// If the lock file exists but cannot be read or parsed, then this
+    /// function returns an error.
+    pub fn load(cwd: &Path) -> BifrostResult<Option<Lock>> {
+        let path = cwd.join(LOCK_FILE);
+        if fs::metadata(&path).is_err() {
+            return Ok(None);
+        }
+
+        match toml::from_str(&hofund::read(&path)?) {
+            Ok(lock) => Ok(Some(lock)),
+            Err(e) => failure::bail!("error: could not parse `{}` due to {}", path.display(), e),
+        }
+    }
+
+    /// Writes the lock file of the realm at `cwd`.
+    pub fn write(&self, cwd: &Path) -> BifrostResult<()> {
+        let contents = format!("{}\n{}", HEADER, toml::to_string(self)?);
+        hofund::write(&cwd.join(LOCK_FILE), contents.as_bytes())
+    }
+
+    /// Checks that `image` is the pinned image.
+    ///
+    /// # Errors
+    ///
+    /// If the digests or build context hashes differ, then this method
+    /// returns an error explaining how to move the lock to `image`.
+    pub fn check(&self, image: &LockedImage) -> BifrostResult<()> {
+        let pinned = &self.image;
+        if pinned.digest != image.digest
+            || pinned.context != image.context
+            || pinned.base_digest != image.base_digest
+        {
+            failure::bail!(
+                "error: image `{}` ({}) does not match the image pinned in {} (`{}`, {})\n\
+                 hint: run with `--update-lock` to pin the new image",
+                image.tag,
+                image.pin(),
+                LOCK_FILE,
+                pinned.tag,
+                pinned.pin()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn image(context: &str) -> LockedImage {
+        LockedImage {
+            tag: String::from("bifrost:0.1"),
+            digest: None,
+            context: Some(String::from(context)),
+            base: Some(String::from("debian:bookworm")),
+            base_digest: Some(String::from("debian@sha256:b")),
+        }
+    }
+
+    #[test]
+    fn test_lock_round_trip() -> BifrostResult<()> {
+        let cwd = env::temp_dir().join(format!("bifrost-lock-{}", process::id()));
+        let _ = fs::remove_dir_all(&cwd);
+        fs::create_dir_all(&cwd)?;
+        assert_eq!(None, Lock::load(&cwd)?);
+
+        let lock = Lock {
+            image: image("0123"),
+        };
+        lock.write(&cwd)?;
+        let loaded = Lock::load(&cwd);
+        fs::remove_dir_all(&cwd)?;
+        assert_eq!(Some(lock), loaded?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_check() {
+        let lock = Lock {
+            image: image("0123"),
+        };
+        assert!(lock.check(&image("0123")).is_ok());
+        assert!(lock.check(&image("4567")).is_err());
+
+        // A new base image is a different image even if nothing else changed.
+        let rebased = LockedImage {
+            base_digest: Some(String::from("debian@sha256:c")),
+            ..image("0123")
+        };
+        assert!(lock.check(&rebased).is_err());
+
+        let pulled = |digest: &str| LockedImage {
+            tag: String::from("postgres:15"),
+            digest: Some(String::from(digest)),
+            context: None,
+            base: None,
+            base_digest: None,
+        };
+        let lock = Lock {
+            image: pulled("postgres@sha256:a"),
+        };
+        assert_eq!("postgres@sha256:a", lock.image.pin());
+        assert!(lock.check(&pulled("postgres@sha256:a")).is_ok());
+        assert!(lock.check(&pulled("postgres@sha256:c")).is_err());
+    }
+}
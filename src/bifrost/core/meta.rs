@@ -0,0 +1,140 @@
+//! Metadata about loaded workspaces, kept outside of the workspaces (in
+//! `~/.bifrost/meta/<name>.toml`) so that it is never part of what is run.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::util::BifrostResult;
+
+const DOT_BIFROST: &str = ".bifrost";
+const META: &str = "meta";
+
+/// What a workspace was loaded from.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// The files of the realm (e.g. `bifrost load`).
+    #[default]
+    Worktree,
+    /// The files git tracks in the realm (`bifrost load --git`).
+    Index,
+    /// A commit of the realm (`bifrost load --rev <commit>`).
+    Revision,
+}
+
+/// The metadata of a loaded workspace.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct WorkSpaceMeta {
+    pub source: Source,
+    /// The id of the commit that was loaded (or that the loaded index was at).
+    pub revision: Option<String>,
+    /// The roots of what was loaded, relative to the realm (an empty path is
+    /// the realm itself).
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
+    /// The SHA-256 hash of each loaded file, by its path in the workspace
+    /// (see `bifrost verify`).
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl WorkSpaceMeta {
+    /// Returns the path of the metadata of the workspace `name`.
+    pub fn path(home_path: &Path, name: &str) -> PathBuf {
+        home_path
+            .join(DOT_BIFROST)
+            .join(META)
+            .join(format!("{}.toml", name))
+    }
+
+    /// Reads the metadata of the workspace `name` (if there is any).
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be read or parsed, then this function returns
+    /// an error.
+    pub fn load(home_path: &Path, name: &str) -> BifrostResult<Option<WorkSpaceMeta>> {
+        let path = WorkSpaceMeta::path(home_path, name);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match toml::from_str(&contents) {
+            Ok(meta) => Ok(Some(meta)),
+            Err(e) => failure::bail!("error: could not parse `{}`: {}", path.display(), e),
+        }
+    }
+
+    /// Writes the metadata of the workspace `name`.
+    ///
+    /// # Errors
+    ///
+    /// If the metadata cannot be written, then this method returns an error.
+    pub fn save(&self, home_path: &Path, name: &str) -> BifrostResult<()> {
+        let path = WorkSpaceMeta::path(home_path, name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Removes the metadata of the workspace `name` (if there is any).
+    ///
+    /// # Errors
+    ///
+    /// If the metadata exists but cannot be removed, then this function
+    /// returns an error.
+    pub fn remove(home_path: &Path, name: &str) -> BifrostResult<()> {
+        match fs::remove_file(WorkSpaceMeta::path(home_path, name)) {
+            Err(ref e) if e.kind() != ErrorKind::NotFound => {
+                failure::bail!("error: could not remove the workspace's metadata: {}", e)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Describes what was loaded, e.g. for `bifrost show`.
+    pub fn describe(&self) -> Option<String> {
+        match (self.source, &self.revision) {
+            (Source::Revision, Some(revision)) => Some(format!("loaded revision `{}`", revision)),
+            (Source::Index, Some(revision)) => {
+                Some(format!("loaded the files git tracks (at `{}`)", revision))
+            }
+            (Source::Index, None) => Some(String::from("loaded the files git tracks")),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_meta_toml() -> BifrostResult<()> {
+        let meta = WorkSpaceMeta {
+            source: Source::Revision,
+            revision: Some(String::from("4f697ee")),
+            roots: vec![PathBuf::new()],
+            files: vec![(String::from("realm/src/main.rs"), String::from("e3b0c442"))]
+                .into_iter()
+                .collect(),
+        };
+        let contents = toml::to_string(&meta)?;
+        assert_eq!(
+            "source = \"revision\"\nrevision = \"4f697ee\"\nroots = [\"\"]\n\n[files]\n\"realm/src/main.rs\" = \"e3b0c442\"\n",
+            contents
+        );
+        assert_eq!(meta, toml::from_str(&contents)?);
+        assert_eq!(
+            Some(String::from("loaded revision `4f697ee`")),
+            meta.describe()
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,12 @@
 pub mod app;
 pub mod config;
+pub mod devcontainer;
+pub mod filter;
 pub mod hofund;
+pub mod lock;
+pub mod meta;
+pub mod plan;
+pub mod sync;
+pub mod watch;
 pub mod workingdir;
 pub mod workspace;
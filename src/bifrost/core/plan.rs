@@ -0,0 +1,228 @@
+//! The plan of a load (`bifrost load --dry-run`): the files a load would copy
+//! and what the realm's rules exclude from it.
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_derive::Serialize;
+
+use crate::core::workingdir::WorkingDir;
+use crate::util::inventory::format_size;
+use crate::util::BifrostResult;
+
+/// The number of files listed as the largest.
+pub const LARGEST: usize = 10;
+
+/// A file that would be loaded.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlannedFile {
+    /// The path relative to the loaded workspace.
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// A symbolic link that would be recreated.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlannedLink {
+    /// The path relative to the loaded workspace.
+    pub path: PathBuf,
+    /// What the link would link to.
+    pub target: PathBuf,
+}
+
+/// A path that would not be loaded and the rule that excludes it.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PlannedExclusion {
+    /// The path relative to the loaded workspace.
+    pub path: PathBuf,
+    pub is_dir: bool,
+    pub rule: String,
+}
+
+/// What a load of a realm would do.
+#[derive(Debug, Serialize)]
+pub struct LoadPlan {
+    /// The name of the workspace.
+    pub name: String,
+    /// Whether or not the workspace is already loaded (and so a plain `load`
+    /// would refuse to overwrite it).
+    pub loaded: bool,
+    /// The total size of the files.
+    pub bytes: u64,
+    /// The files, sorted by path.
+    pub files: Vec<PlannedFile>,
+    /// The largest files, largest first.
+    pub largest: Vec<PlannedFile>,
+    /// The symbolic links, sorted by path.
+    pub links: Vec<PlannedLink>,
+    /// The excluded paths (directories as a whole), sorted by path.
+    pub excluded: Vec<PlannedExclusion>,
+}
+
+impl LoadPlan {
+    /// Constructs the plan of loading the walked `contents` as the workspace
+    /// `name`.
+    ///
+    /// # Errors
+    ///
+    /// If a walked file can no longer be inspected, then this function
+    /// returns an error.
+    pub fn new(name: &str, loaded: bool, contents: &[WorkingDir]) -> BifrostResult<Self> {
+        let mut files = vec![];
+        let mut links = vec![];
+        let mut excluded = vec![];
+
+        for wd in contents {
+            let parent = wd.parent().map(|p| p.as_path()).unwrap_or(Path::new(""));
+            let relative = |p: &Path| p.strip_prefix(parent).unwrap_or(p).to_path_buf();
+
+            for file in wd.files() {
+                files.push(PlannedFile {
+                    path: relative(file),
+                    bytes: fs::metadata(file)?.len(),
+                });
+            }
+            for link in wd.links() {
+                links.push(PlannedLink {
+                    path: relative(&link.path),
+                    target: link.target.clone(),
+                });
+            }
+            for exclusion in wd.excluded() {
+                excluded.push(PlannedExclusion {
+                    path: relative(&exclusion.path),
+                    is_dir: exclusion.path.is_dir(),
+                    rule: exclusion.rule.to_string(),
+                });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        excluded.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut largest = files.clone();
+        largest.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        largest.truncate(LARGEST);
+
+        Ok(LoadPlan {
+            name: name.to_string(),
+            loaded,
+            bytes: files.iter().map(|f| f.bytes).sum(),
+            files,
+            largest,
+            links,
+            excluded,
+        })
+    }
+
+    /// Returns the plan as (pretty-printed) JSON.
+    ///
+    /// # Errors
+    ///
+    /// If the plan cannot be serialized, then this method returns an error.
+    pub fn to_json(&self) -> BifrostResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Lists the files, the largest files, and the exclusions, followed by a
+/// summary.
+impl fmt::Display for LoadPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for file in &self.files {
+            writeln!(
+                f,
+                "load: {}  ({})",
+                file.path.display(),
+                format_size(file.bytes)
+            )?;
+        }
+        for link in &self.links {
+            writeln!(
+                f,
+                "link: {} -> {}",
+                link.path.display(),
+                link.target.display()
+            )?;
+        }
+        for exclusion in &self.excluded {
+            let slash = if exclusion.is_dir { "/" } else { "" };
+            writeln!(
+                f,
+                "excluded: {}{}  ({})",
+                exclusion.path.display(),
+                slash,
+                exclusion.rule
+            )?;
+        }
+        if !self.largest.is_empty() {
+            writeln!(f, "largest files:")?;
+            for file in &self.largest {
+                writeln!(
+                    f,
+                    "  {:>10}  {}",
+                    format_size(file.bytes),
+                    file.path.display()
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "total: {} files ({}), {} links, {} excluded",
+            self.files.len(),
+            format_size(self.bytes),
+            self.links.len(),
+            self.excluded.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_load_plan() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-plan-{}", process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let realm = tmp.join("realm");
+        fs::create_dir_all(realm.join("src"))?;
+        fs::create_dir_all(realm.join("target"))?;
+        fs::write(realm.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(realm.join("README.md"), "# realm")?;
+        fs::write(realm.join("target").join("realm"), "binary")?;
+
+        let wd = WorkingDir::new(&realm).ignore(&vec!["target"]).walk()?;
+        let plan = LoadPlan::new("realm", false, &[wd])?;
+
+        assert_eq!(19, plan.bytes);
+        assert_eq!(
+            vec![
+                PathBuf::from("realm/README.md"),
+                PathBuf::from("realm/src/main.rs")
+            ],
+            plan.files
+                .iter()
+                .map(|f| f.path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(PathBuf::from("realm/src/main.rs"), plan.largest[0].path);
+        assert_eq!(
+            vec![PlannedExclusion {
+                path: PathBuf::from("realm/target"),
+                is_dir: true,
+                rule: String::from("`target` in [workspace] ignore"),
+            }],
+            plan.excluded
+        );
+        assert!(plan
+            .to_string()
+            .contains("excluded: realm/target/  (`target` in [workspace] ignore)\n"));
+        assert!(plan.to_json()?.contains("\"bytes\": 19"));
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+}
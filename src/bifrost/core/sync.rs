@@ -0,0 +1,572 @@
+//! Comparison of a realm's walked `WorkingDir`s with their loaded copy in the
+//! Bifrost container, and incremental updates of that copy.
+//!
+//! Files are compared by size and modification time first; files of the same
+//! size whose modification times differ are compared by content hash. Copied
+//! files take the modification time of their source so that later comparisons
+//! stay cheap. Symbolic links are compared by what they link to.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use filetime::{self, FileTime};
+use walkdir::WalkDir;
+
+use crate::core::filter::Rule;
+use crate::core::workingdir::{self, Link, WorkingDir};
+use crate::util::inventory::format_size;
+use crate::util::{hash, BifrostResult};
+
+/// A file that differs between the realm and its loaded copy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileChange {
+    /// The path relative to the loaded workspace.
+    pub path: PathBuf,
+    /// The absolute path of the file in the realm.
+    pub source: PathBuf,
+}
+
+/// A symbolic link that differs between the realm and its loaded copy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LinkChange {
+    /// The path relative to the loaded workspace.
+    pub path: PathBuf,
+    /// The link in the realm.
+    pub link: Link,
+    /// Whether or not something else is loaded at `path`.
+    pub existed: bool,
+}
+
+/// The differences between a realm's `WorkingDir`s and their loaded copy.
+#[derive(Debug, Default)]
+pub struct Delta {
+    /// The loaded workspace.
+    target: PathBuf,
+    /// Files that only exist in the realm.
+    pub added: Vec<FileChange>,
+    /// Files whose contents differ.
+    pub modified: Vec<FileChange>,
+    /// Symbolic links that are new or link to something else.
+    pub links: Vec<LinkChange>,
+    /// Files (relative to the loaded workspace) that no longer exist in the
+    /// realm.
+    pub removed: Vec<PathBuf>,
+    /// Directories (relative to the loaded workspace) that only exist in the
+    /// realm, along with their absolute path in the realm.
+    dirs_added: Vec<(PathBuf, PathBuf)>,
+    /// Directories that no longer exist in the realm.
+    dirs_removed: Vec<PathBuf>,
+    /// Unchanged files whose loaded copy has a different modification time.
+    touched: Vec<(PathBuf, FileTime)>,
+}
+
+/// The outcome of applying a `Delta`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SyncSummary {
+    pub added: usize,
+    pub modified: usize,
+    pub removed: usize,
+    /// The number of bytes copied.
+    pub bytes: u64,
+}
+
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} added, {} changed, {} removed ({} copied)",
+            self.added,
+            self.modified,
+            self.removed,
+            format_size(self.bytes)
+        )
+    }
+}
+
+impl Delta {
+    /// Compares walked `WorkingDir`s with their copy loaded at `target`. Only
+    /// the parts of `target` the `WorkingDir`s were loaded to are compared.
+    ///
+    /// # Errors
+    ///
+    /// If a file cannot be inspected or read, then this function returns an
+    /// error.
+    pub fn between(contents: &[WorkingDir], target: &Path) -> BifrostResult<Delta> {
+        let mut delta = Delta {
+            target: target.to_path_buf(),
+            ..Default::default()
+        };
+
+        for wd in contents {
+            let parent = wd.parent().map(|p| p.as_path()).unwrap_or(Path::new(""));
+            let relative = |p: &Path| p.strip_prefix(parent).map(Path::to_path_buf);
+
+            let mut files: BTreeMap<PathBuf, &PathBuf> = BTreeMap::new();
+            for file in wd.files() {
+                files.insert(relative(file)?, file);
+            }
+            let mut links: BTreeMap<PathBuf, &Link> = BTreeMap::new();
+            for link in wd.links() {
+                links.insert(relative(&link.path)?, link);
+            }
+            let mut dirs: BTreeMap<PathBuf, &Path> = BTreeMap::new();
+            for dir in wd.dir_paths() {
+                dirs.insert(relative(dir)?, dir);
+            }
+
+            // What is loaded but no longer in the realm. What the realm's rules
+            // exclude is left alone (e.g. build output that runs left in an
+            // ignored `target/`), except for what git no longer tracks, along
+            // with the directories that lead to it.
+            let mut filter = wd.filter()?;
+            let mut kept: BTreeSet<PathBuf> = BTreeSet::new();
+            let mut dirs_removed = vec![];
+            let root = relative(wd.root())?;
+            let mut walker = WalkDir::new(target.join(&root)).into_iter();
+            while let Some(entry) = walker.next() {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    // Nothing of this `WorkingDir` has been loaded yet.
+                    Err(ref e) if e.depth() == 0 => break,
+                    Err(e) => return Err(e.into()),
+                };
+                let path = entry.path().strip_prefix(target)?.to_path_buf();
+                let is_dir = entry.file_type().is_dir();
+                let in_realm = if is_dir {
+                    dirs.contains_key(&path)
+                } else {
+                    files.contains_key(&path) || links.contains_key(&path)
+                };
+                if in_realm {
+                    continue;
+                }
+
+                match filter.excludes(&parent.join(&path), is_dir) {
+                    None | Some(Rule::Untracked) => {}
+                    Some(_) => {
+                        if is_dir {
+                            walker.skip_current_dir();
+                        }
+                        kept.extend(path.ancestors().skip(1).map(Path::to_path_buf));
+                        continue;
+                    }
+                }
+                if is_dir {
+                    dirs_removed.push(path);
+                } else {
+                    delta.removed.push(path);
+                }
+            }
+            dirs_removed.retain(|d| !kept.contains(d));
+            delta.dirs_removed.extend(dirs_removed);
+
+            for (dir, source) in dirs {
+                if !is_dir(&target.join(&dir)) {
+                    delta.dirs_added.push((dir, source.to_path_buf()));
+                }
+            }
+
+            for (path, link) in links {
+                let loaded = target.join(&path);
+                if fs::read_link(&loaded).ok().as_ref() != Some(&link.target) {
+                    delta.links.push(LinkChange {
+                        path,
+                        link: link.clone(),
+                        existed: fs::symlink_metadata(&loaded).is_ok(),
+                    });
+                }
+            }
+
+            for (path, source) in files {
+                let change = FileChange {
+                    path,
+                    source: source.clone(),
+                };
+                match compare(&change.source, &target.join(&change.path))? {
+                    Comparison::Missing => delta.added.push(change),
+                    Comparison::Modified => delta.modified.push(change),
+                    Comparison::Unchanged => {}
+                    Comparison::Touched(mtime) => {
+                        delta.touched.push((target.join(&change.path), mtime))
+                    }
+                }
+            }
+        }
+
+        Ok(delta)
+    }
+
+    /// Returns the path of the loaded workspace.
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// Returns whether or not the realm and its loaded copy are the same.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.modified.is_empty()
+            && self.links.is_empty()
+            && self.removed.is_empty()
+            && self.dirs_added.is_empty()
+            && self.dirs_removed.is_empty()
+    }
+
+    /// Updates the loaded copy: removes files and directories that no longer
+    /// exist in the realm, copies added and modified files, and recreates
+    /// symbolic links. Added directories take the permissions and
+    /// modification times of their source, as they do on `load`. The `checksums` of the loaded files (by their path in
+    /// the loaded workspace) are updated accordingly.
+    ///
+    /// # Errors
+    ///
+    /// If a file cannot be copied or removed, then this method returns an error
+    /// naming it.
+    pub fn apply(&self, checksums: &mut BTreeMap<String, String>) -> BifrostResult<SyncSummary> {
+        let existed = self.links.iter().filter(|c| c.existed).count();
+        let mut summary = SyncSummary {
+            added: self.added.len() + self.links.len() - existed,
+            modified: self.modified.len() + existed,
+            removed: self.removed.len(),
+            bytes: 0,
+        };
+
+        // What is removed may be in the way of what is added (e.g. a
+        // directory that is now a link).
+        for path in &self.removed {
+            checksums.remove(&path.to_string_lossy().into_owned());
+            let path = self.target.join(path);
+            if let Err(e) = fs::remove_file(&path) {
+                failure::bail!("error: could not remove `{}` due to {}", path.display(), e);
+            }
+        }
+
+        // Children before their parents.
+        let mut dirs: Vec<&PathBuf> = self.dirs_removed.iter().collect();
+        dirs.sort_by_key(|d| std::cmp::Reverse(d.components().count()));
+        for dir in dirs {
+            let path = self.target.join(dir);
+            if let Err(e) = fs::remove_dir(&path) {
+                failure::bail!("error: could not remove `{}` due to {}", path.display(), e);
+            }
+        }
+
+        for (dir, _) in &self.dirs_added {
+            fs::create_dir_all(self.target.join(dir))?;
+        }
+
+        for change in self.added.iter().chain(self.modified.iter()) {
+            let (n, hash) = self.copy(change)?;
+            summary.bytes += n;
+            checksums.insert(change.path.to_string_lossy().into_owned(), hash);
+        }
+
+        for change in &self.links {
+            checksums.remove(&change.path.to_string_lossy().into_owned());
+            workingdir::load_link(&change.link, &self.target.join(&change.path))?;
+        }
+
+        for (path, mtime) in &self.touched {
+            filetime::set_file_mtime(path, *mtime)?;
+        }
+
+        // Only once their contents are in place, since adding to a directory
+        // changes its modification time.
+        for (dir, source) in &self.dirs_added {
+            workingdir::load_dir_metadata(source, &self.target.join(dir))?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Copies a file into the loaded copy and returns the number of bytes
+    /// copied and the hash of the copy.
+    fn copy(&self, change: &FileChange) -> BifrostResult<(u64, String)> {
+        let to = self.target.join(&change.path);
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Copying to a link would overwrite what it links to.
+        if fs::symlink_metadata(&to).is_ok_and(|m| m.file_type().is_symlink()) {
+            fs::remove_file(&to)?;
+        }
+
+        let (n, hash) = match hash::copy_sha256(&change.source, &to) {
+            Ok(copied) => copied,
+            Err(e) => failure::bail!(
+                "error: could not copy `{}` due to {}",
+                change.source.display(),
+                e
+            ),
+        };
+        let mtime = FileTime::from_last_modification_time(&fs::metadata(&change.source)?);
+        filetime::set_file_mtime(&to, mtime)?;
+        Ok((n, hash))
+    }
+}
+
+/// How a file of the realm compares to its loaded copy.
+enum Comparison {
+    Missing,
+    Modified,
+    Unchanged,
+    /// The contents are the same but the loaded copy should take the given
+    /// modification time.
+    Touched(FileTime),
+}
+
+/// Returns whether or not `path` is a directory (rather than, e.g., a link to
+/// one).
+fn is_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|m| m.is_dir())
+}
+
+fn compare(source: &Path, target: &Path) -> BifrostResult<Comparison> {
+    let target_meta = match fs::symlink_metadata(target) {
+        Ok(meta) => meta,
+        Err(_) => return Ok(Comparison::Missing),
+    };
+    if !target_meta.is_file() {
+        return Ok(Comparison::Modified);
+    }
+
+    let source_meta = fs::metadata(source)?;
+    if source_meta.len() != target_meta.len() {
+        return Ok(Comparison::Modified);
+    }
+
+    let mtime = FileTime::from_last_modification_time(&source_meta);
+    if mtime == FileTime::from_last_modification_time(&target_meta) {
+        return Ok(Comparison::Unchanged);
+    }
+
+    if hash::file_sha256(source)? == hash::file_sha256(target)? {
+        Ok(Comparison::Touched(mtime))
+    } else {
+        Ok(Comparison::Modified)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_delta_apply() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-sync-{}", process::id()));
+        let realm = tmp.join("realm");
+        let target = tmp.join("container");
+        let _ = fs::remove_dir_all(&tmp);
+
+        write(&realm.join("same.txt"), "same");
+        write(&realm.join("changed.txt"), "new");
+        write(&realm.join("src").join("added.rs"), "fn main() {}");
+        write(&target.join("realm").join("same.txt"), "same");
+        write(&target.join("realm").join("changed.txt"), "old");
+        write(&target.join("realm").join("old").join("removed.rs"), "");
+        let dir_mtime = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(realm.join("src"), dir_mtime)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(realm.join("src"), fs::Permissions::from_mode(0o550))?;
+        }
+
+        let walk =
+            || -> BifrostResult<Vec<WorkingDir>> { Ok(vec![WorkingDir::new(&realm).walk()?]) };
+        let delta = Delta::between(&walk()?, &target)?;
+        assert_eq!(
+            vec![PathBuf::from("realm/src/added.rs")],
+            delta
+                .added
+                .iter()
+                .map(|c| c.path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![PathBuf::from("realm/changed.txt")],
+            delta
+                .modified
+                .iter()
+                .map(|c| c.path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![PathBuf::from("realm/old/removed.rs")], delta.removed);
+
+        let mut checksums = BTreeMap::new();
+        checksums.insert(String::from("realm/old/removed.rs"), String::new());
+        let summary = delta.apply(&mut checksums)?;
+        assert_eq!(
+            SyncSummary {
+                added: 1,
+                modified: 1,
+                removed: 1,
+                bytes: 15,
+            },
+            summary
+        );
+        assert_eq!("new", fs::read_to_string(target.join("realm/changed.txt"))?);
+        assert_eq!(
+            vec!["realm/changed.txt", "realm/src/added.rs"],
+            checksums.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(hash::sha256_hex(b"new"), checksums["realm/changed.txt"]);
+        assert!(!target.join("realm").join("old").exists());
+        // The added directory takes the metadata of its source, but stays
+        // writable.
+        let meta = fs::metadata(target.join("realm").join("src"))?;
+        assert_eq!(dir_mtime, FileTime::from_last_modification_time(&meta));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(0o750, meta.permissions().mode() & 0o777);
+        }
+
+        // Everything, including the unchanged file's modification time, is
+        // now in sync.
+        let delta = Delta::between(&walk()?, &target)?;
+        assert!(delta.is_empty());
+        assert!(delta.touched.is_empty());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(realm.join("src"), fs::Permissions::from_mode(0o750))?;
+        }
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_delta_keeps_ignored() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-sync-ignored-{}", process::id()));
+        let realm = tmp.join("realm");
+        let target = tmp.join("container");
+        let _ = fs::remove_dir_all(&tmp);
+
+        write(&realm.join("src").join("main.rs"), "fn main() {}");
+        write(&realm.join(".gitignore"), "build/\n");
+        write(
+            &target.join("realm").join("src").join("main.rs"),
+            "fn main() {}",
+        );
+        write(&target.join("realm").join(".gitignore"), "build/\n");
+        // Build output of earlier runs, including within a directory that is
+        // gone from the realm.
+        write(
+            &target
+                .join("realm")
+                .join("target")
+                .join("debug")
+                .join("realm"),
+            "",
+        );
+        write(&target.join("realm").join("build").join("out.o"), "");
+        write(
+            &target.join("realm").join("old").join("target").join("out"),
+            "",
+        );
+        write(&target.join("realm").join("old").join("removed.rs"), "");
+
+        let walk = || -> BifrostResult<Vec<WorkingDir>> {
+            Ok(vec![WorkingDir::new(&realm)
+                .ignore(&vec!["target"])
+                .walk()?])
+        };
+        let delta = Delta::between(&walk()?, &target)?;
+        assert_eq!(vec![PathBuf::from("realm/old/removed.rs")], delta.removed);
+        assert!(delta.dirs_removed.is_empty());
+
+        delta.apply(&mut BTreeMap::new())?;
+        assert!(target.join("realm/target/debug/realm").exists());
+        assert!(target.join("realm/build/out.o").exists());
+        assert!(target.join("realm/old/target/out").exists());
+        assert!(!target.join("realm/old/removed.rs").exists());
+        assert!(Delta::between(&walk()?, &target)?.is_empty());
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_delta_links() -> BifrostResult<()> {
+        use std::os::unix::fs::symlink;
+
+        let tmp = env::temp_dir().join(format!("bifrost-sync-links-{}", process::id()));
+        let realm = tmp.join("realm");
+        let target = tmp.join("container");
+        let _ = fs::remove_dir_all(&tmp);
+
+        write(&realm.join("a.txt"), "a");
+        write(&realm.join("b.txt"), "b");
+        symlink("a.txt", realm.join("current"))?;
+        write(&target.join("realm").join("a.txt"), "a");
+        write(&target.join("realm").join("b.txt"), "b");
+        symlink("b.txt", target.join("realm").join("current"))?;
+
+        let walk =
+            || -> BifrostResult<Vec<WorkingDir>> { Ok(vec![WorkingDir::new(&realm).walk()?]) };
+        let delta = Delta::between(&walk()?, &target)?;
+        assert!(delta.removed.is_empty());
+        assert_eq!(1, delta.links.len());
+        assert!(delta.links[0].existed);
+
+        assert_eq!(1, delta.apply(&mut BTreeMap::new())?.modified);
+        assert_eq!(
+            PathBuf::from("a.txt"),
+            fs::read_link(target.join("realm").join("current"))?
+        );
+        assert!(Delta::between(&walk()?, &target)?.is_empty());
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_delta_below() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-sync-below-{}", process::id()));
+        let realm = tmp.join("realm");
+        let target = tmp.join("container");
+        let _ = fs::remove_dir_all(&tmp);
+
+        write(&realm.join("changed.txt"), "new");
+        write(&realm.join("src").join("main.rs"), "fn main() {}");
+        write(&target.join("realm").join("changed.txt"), "old");
+        write(&target.join("realm").join("gone").join("removed.rs"), "");
+
+        // Only what is below the walked paths is compared, and it is compared
+        // with where the whole realm was loaded.
+        let wd = WorkingDir::new(&realm);
+        let contents = vec![
+            wd.below(realm.join("src")).walk()?,
+            wd.below(realm.join("gone")).walk()?,
+        ];
+        let delta = Delta::between(&contents, &target)?;
+        assert_eq!(
+            vec![PathBuf::from("realm/src/main.rs")],
+            delta
+                .added
+                .iter()
+                .map(|c| c.path.clone())
+                .collect::<Vec<_>>()
+        );
+        assert!(delta.modified.is_empty());
+        assert_eq!(vec![PathBuf::from("realm/gone/removed.rs")], delta.removed);
+
+        delta.apply(&mut BTreeMap::new())?;
+        assert!(target.join("realm/src/main.rs").is_file());
+        assert!(!target.join("realm/gone").exists());
+        assert_eq!("old", fs::read_to_string(target.join("realm/changed.txt"))?);
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+//! Watching a realm's `WorkingDir`s for changes (inotify on Linux) until
+//! Ctrl-C is pressed.
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+
+use crate::core::workingdir::WorkingDir;
+use crate::util::BifrostResult;
+
+/// How long the realm has to be quiet before a burst of changes is handled.
+pub const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// How often to check whether Ctrl-C was pressed while waiting for changes.
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Watches the roots of `contents` and calls `on_change` with the changed
+/// paths that are not ignored by any of them, once per burst of changes
+/// (i.e. after the realm has been quiet for `debounce`). Returns once Ctrl-C
+/// is pressed or `on_change` returns an error.
+///
+/// # Errors
+///
+/// If the roots cannot be watched, or `on_change` returns an error, then this
+/// function returns an error.
+pub fn watch<F>(contents: &[WorkingDir], debounce: Duration, mut on_change: F) -> BifrostResult<()>
+where
+    F: FnMut(&BTreeSet<PathBuf>) -> BifrostResult<()>,
+{
+    // Ctrl-C stops watching instead of killing bifrost mid-update.
+    let _guard = InterruptGuard::new();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::watcher(tx, debounce)?;
+    for wd in contents {
+        watcher.watch(wd.root(), RecursiveMode::Recursive)?;
+    }
+
+    while !INTERRUPTED.load(Ordering::SeqCst) {
+        let mut changed = BTreeSet::new();
+        match rx.recv_timeout(INTERRUPT_POLL) {
+            Ok(event) => collect(event, &mut changed),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                failure::bail!("error: stopped receiving changes to the realm")
+            }
+        }
+        // Take in the rest of the burst.
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            collect(event, &mut changed);
+        }
+
+        retain_relevant(contents, &mut changed);
+        if !changed.is_empty() {
+            on_change(&changed)?;
+        }
+    }
+    Ok(())
+}
+
+/// Installs the Ctrl-C handler of `watch` and restores the previous one when
+/// dropped (i.e. when watching ends, however it ends).
+struct InterruptGuard {
+    previous: libc::sighandler_t,
+}
+
+impl InterruptGuard {
+    fn new() -> Self {
+        INTERRUPTED.store(false, Ordering::SeqCst);
+        let handler = on_interrupt as extern "C" fn(libc::c_int);
+        let previous = unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+        InterruptGuard { previous }
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::signal(libc::SIGINT, self.previous);
+        }
+    }
+}
+
+/// Returns unwalked `WorkingDir`s that cover the `changed` paths of
+/// `contents`: one below each changed path that is not ignored (what is below
+/// a changed directory is covered by it), or the whole `WorkingDir` if events
+/// were missed or its root changed.
+pub fn affected(contents: &[WorkingDir], changed: &BTreeSet<PathBuf>) -> Vec<WorkingDir> {
+    let mut affected = vec![];
+    for wd in contents {
+        if changed.contains(Path::new("")) || changed.contains(wd.root()) {
+            affected.push(wd.unwalked());
+            continue;
+        }
+        // Directories sort before what is below them.
+        let mut paths: Vec<&PathBuf> = vec![];
+        for path in changed {
+            if wd.is_ignored(path) || paths.iter().any(|p| path.starts_with(p)) {
+                continue;
+            }
+            paths.push(path);
+        }
+        affected.extend(paths.into_iter().map(|p| wd.below(p)));
+    }
+    affected
+}
+
+/// Drops the `changed` paths that are ignored by all of `contents` (an empty
+/// path, i.e. a rescan, is kept).
+fn retain_relevant(contents: &[WorkingDir], changed: &mut BTreeSet<PathBuf>) {
+    changed.retain(|p| p.as_os_str().is_empty() || contents.iter().any(|wd| !wd.is_ignored(p)));
+}
+
+/// Adds the paths an event is about to `changed`.
+fn collect(event: DebouncedEvent, changed: &mut BTreeSet<PathBuf>) {
+    match event {
+        DebouncedEvent::Create(path)
+        | DebouncedEvent::Write(path)
+        | DebouncedEvent::Chmod(path)
+        | DebouncedEvent::Remove(path) => {
+            changed.insert(path);
+        }
+        DebouncedEvent::Rename(from, to) => {
+            changed.insert(from);
+            changed.insert(to);
+        }
+        // Events were missed; everything may have changed (an empty path is
+        // never ignored).
+        DebouncedEvent::Rescan => {
+            changed.insert(PathBuf::new());
+        }
+        DebouncedEvent::Error(_, Some(path)) => {
+            changed.insert(path);
+        }
+        DebouncedEvent::NoticeWrite(_)
+        | DebouncedEvent::NoticeRemove(_)
+        | DebouncedEvent::Error(_, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_collect() {
+        let mut changed = BTreeSet::new();
+        collect(
+            DebouncedEvent::Create(PathBuf::from("/realm/new")),
+            &mut changed,
+        );
+        collect(
+            DebouncedEvent::Write(PathBuf::from("/realm/src/main.rs")),
+            &mut changed,
+        );
+        collect(
+            DebouncedEvent::Rename(PathBuf::from("/realm/a"), PathBuf::from("/realm/b")),
+            &mut changed,
+        );
+        collect(
+            DebouncedEvent::NoticeWrite(PathBuf::from("/realm/c")),
+            &mut changed,
+        );
+        collect(
+            DebouncedEvent::NoticeRemove(PathBuf::from("/realm/d")),
+            &mut changed,
+        );
+        assert_eq!(
+            vec!["/realm/a", "/realm/b", "/realm/new", "/realm/src/main.rs"],
+            changed
+                .iter()
+                .map(|p| p.to_str().unwrap())
+                .collect::<Vec<_>>()
+        );
+
+        collect(DebouncedEvent::Rescan, &mut changed);
+        assert!(changed.contains(Path::new("")));
+    }
+
+    #[test]
+    fn test_ignored_changes() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-watch-{}", process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let realm = tmp.join("realm");
+        fs::create_dir_all(realm.join("src"))?;
+        fs::create_dir_all(realm.join("target"))?;
+        let wd = WorkingDir::new(&realm).ignore(&vec!["target"]);
+        let contents = [wd];
+
+        let mut changed: BTreeSet<PathBuf> = vec![
+            realm.join("src"),
+            realm.join("src").join("main.rs"),
+            realm.join("target").join("debug"),
+            tmp.join("elsewhere"),
+        ]
+        .into_iter()
+        .collect();
+        retain_relevant(&contents, &mut changed);
+        assert_eq!(
+            vec![realm.join("src"), realm.join("src").join("main.rs")],
+            changed.iter().cloned().collect::<Vec<_>>()
+        );
+
+        // Only the changed directory is walked again.
+        let walked = affected(&contents, &changed);
+        assert_eq!(1, walked.len());
+        assert_eq!(realm.join("src"), walked[0].root());
+        assert_eq!(Some(&tmp), walked[0].parent());
+
+        // A rescan keeps everything and walks the whole realm.
+        let mut changed: BTreeSet<PathBuf> = vec![PathBuf::new()].into_iter().collect();
+        retain_relevant(&contents, &mut changed);
+        assert_eq!(1, changed.len());
+        let walked = affected(&contents, &changed);
+        assert_eq!(realm, walked[0].root());
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+}
@@ -4,16 +4,44 @@ use std::collections::BinaryHeap;
 use std::collections::HashSet;
 use std::env;
 use std::fmt;
-use std::fs::{self, File};
-use std::path::{Path, PathBuf};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
 
-use crate::util::{BifrostPath, BifrostResult, OperationInfo};
+use crate::core::filter::{Exclusion, Filter, Rule, Tracked};
+use crate::util::{hash, BifrostPath, BifrostResult, OperationInfo};
 
 extern crate dirs;
 extern crate walkdir;
 
+use filetime::{self, FileTime};
+use serde_derive::{Deserialize, Serialize};
 use walkdir::{DirEntry, WalkDir};
 
+/// How symbolic links are walked (and loaded), see `[workspace] symlinks`.
+/// Links that lead outside of the realm are never loaded.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Symlinks {
+    /// Links are recreated (links to absolute paths in the realm become
+    /// relative).
+    #[default]
+    Preserve,
+    /// Links are replaced by what they link to.
+    Follow,
+    /// Links are not loaded.
+    Skip,
+}
+
+/// A symbolic link found by `walk`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    /// The absolute path of the link.
+    pub path: PathBuf,
+    /// What the link is recreated to link to.
+    pub target: PathBuf,
+}
+
 /// The primary data structure for walking and structuring working-directory information.
 pub struct WorkingDir {
     /// The `absolute path to the root`'s parent directory.
@@ -25,9 +53,28 @@ pub struct WorkingDir {
     dirs: BinaryHeap<DirEntryExt>,
     /// Absolute paths to this working directory's files.
     files: Vec<PathBuf>,
-    /// Names of files and/or directories to `ignore` when
+    /// This working directory's (preserved) symbolic links.
+    links: Vec<Link>,
+    /// The SHA-256 hash of each file that `load` copied, by its path relative
+    /// to the loaded workspace.
+    checksums: Vec<(PathBuf, String)>,
+    /// Patterns of files and/or directories to `ignore` when
     /// `walk`ing this working directory.
-    ignore_list: HashSet<String>,
+    ignore_list: Vec<String>,
+    /// Patterns of the files to walk (all of them, if it is empty).
+    include_list: Vec<String>,
+    /// The directory that the patterns are relative to (the `root`, if it is
+    /// `None`).
+    anchor: Option<PathBuf>,
+    /// Whether or not `.gitignore` and `.git/info/exclude` files are honored
+    /// when `walk`ing this working directory.
+    gitignore: bool,
+    /// The only files to walk (if any).
+    tracked: Option<Rc<Tracked>>,
+    /// How symbolic links are walked.
+    symlinks: Symlinks,
+    /// What `walk` excluded.
+    excluded: Vec<Exclusion>,
     /// The size of all files in this `WorkingDir`.
     size: u64,
 }
@@ -43,7 +90,15 @@ impl Default for WorkingDir {
             root,
             dirs: BinaryHeap::new(),
             files: vec![],
-            ignore_list: HashSet::new(),
+            links: vec![],
+            checksums: vec![],
+            ignore_list: vec![],
+            include_list: vec![],
+            anchor: None,
+            gitignore: true,
+            tracked: None,
+            symlinks: Symlinks::default(),
+            excluded: vec![],
             size: 0,
         }
     }
@@ -56,7 +111,14 @@ impl fmt::Debug for WorkingDir {
             .field("root", &self.root)
             .field("dirs", &self.dirs)
             .field("files", &self.files)
+            .field("links", &self.links)
             .field("ignore_list", &self.ignore_list)
+            .field("include_list", &self.include_list)
+            .field("anchor", &self.anchor)
+            .field("gitignore", &self.gitignore)
+            .field("tracked", &self.tracked.is_some())
+            .field("symlinks", &self.symlinks)
+            .field("excluded", &self.excluded)
             .finish()
     }
 }
@@ -77,23 +139,63 @@ impl WorkingDir {
         }
     }
 
-    /// Stores a list of names to ignore for subsequent access. The actual filtering
+    /// Stores a list of patterns to ignore for subsequent access. The actual filtering
     /// does not occur until [`walk`](struct.WorkingDir.html#method.walk) is called.
     pub fn ignore<S>(mut self, list: &Vec<S>) -> Self
     where
         S: AsRef<str>,
     {
         for path in list {
-            self.ignore_list.insert(path.as_ref().to_string());
+            self.ignore_list.push(path.as_ref().to_string());
+        }
+        self
+    }
+
+    /// Stores a list of patterns that files must match to be walked (if it is
+    /// not empty).
+    pub fn include<S>(mut self, list: &Vec<S>) -> Self
+    where
+        S: AsRef<str>,
+    {
+        for path in list {
+            self.include_list.push(path.as_ref().to_string());
         }
         self
     }
 
+    /// Sets the directory that the ignore and include patterns are relative to
+    /// (the realm root). It is the `root` by default.
+    pub fn anchor<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.anchor = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets whether or not `.gitignore` and `.git/info/exclude` files are
+    /// honored (they are by default). `.bifrostignore` files are always honored.
+    pub fn gitignore(mut self, yes: bool) -> Self {
+        self.gitignore = yes;
+        self
+    }
+
+    /// Restricts the walk to the `tracked` files (e.g. the ones git tracks).
+    pub fn tracked(mut self, tracked: Rc<Tracked>) -> Self {
+        self.tracked = Some(tracked);
+        self
+    }
+
+    /// Sets how symbolic links are walked (they are preserved by default).
+    pub fn symlinks(mut self, symlinks: Symlinks) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
     /// Walks a directory (or file) beginning from the `root`. Utilizes the
-    /// `WalkDir` iterator and `self.ignore_list` so that walks will not descend
-    /// into unwanted directories (or dir-entries).
+    /// `WalkDir` iterator and a [`Filter`](../filter/struct.Filter.html) of
+    /// the ignore and include patterns so that walks will not descend into
+    /// unwanted directories (or dir-entries). What is excluded, and by which
+    /// rule, is recorded (see [`excluded`](#method.excluded)).
     ///
-    /// The entries are handled in two different ways:
+    /// The entries are handled in different ways:
     ///
     /// ## Directories
     ///
@@ -106,35 +208,245 @@ impl WorkingDir {
     /// ## Files
     ///
     /// Absolute paths, in the form of `PathBuf`s are pushed onto a vector.
+    ///
+    /// ## Symbolic links
+    ///
+    /// Depending on the [`Symlinks`](enum.Symlinks.html) policy, links are
+    /// pushed onto a vector of `Link`s, walked as what they link to, or
+    /// excluded. Links that lead outside of the realm (the anchor), and
+    /// special files such as sockets, are always excluded.
+    ///
+    /// # Errors
+    ///
+    /// If an ignore or include pattern is invalid, then this method returns an
+    /// error.
     pub fn walk(mut self) -> BifrostResult<Self> {
-        let root = &self.root;
-        let ignore_list = &self.ignore_list;
-        let walkdir = WalkDir::new(root)
-            .into_iter()
-            .filter_entry(|e| !ignorable(e, ignore_list));
-
-        for entry in walkdir {
-            if let Ok(entry) = entry {
-                if entry.path().is_dir() {
-                    self.dirs.push(DirEntryExt(entry));
-                } else if entry.path().is_file() {
-                    // Record the 'incoming' file sizes.
-                    self.size += fs::metadata(entry.path())
-                        .expect("error: `WorkingDir::walk` failed to unwrap `fs::metadata`")
-                        .len();
-
-                    self.files.push(entry.into_path());
+        let mut filter = self.filter()?;
+        let mut dirs: Vec<DirEntry> = vec![];
+        let realm = normalize(self.anchor.as_ref().unwrap_or(&self.root));
+
+        let mut walkdir = WalkDir::new(&self.root)
+            .follow_links(self.symlinks == Symlinks::Follow)
+            .into_iter();
+        while let Some(entry) = walkdir.next() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    // Links that cannot be followed are reported; anything
+                    // else that cannot be read is passed over.
+                    if let Some(path) = e.path() {
+                        if e.loop_ancestor().is_some() || is_symlink(path) {
+                            self.excluded.push(Exclusion {
+                                path: path.to_path_buf(),
+                                rule: Rule::BrokenLink,
+                            });
+                        }
+                    }
+                    continue;
                 }
+            };
+            let file_type = entry.file_type();
+            let is_dir = file_type.is_dir();
+            if entry.depth() > 0 {
+                if let Some(rule) = filter.excludes(entry.path(), is_dir) {
+                    if is_dir {
+                        walkdir.skip_current_dir();
+                    }
+                    self.excluded.push(Exclusion {
+                        path: entry.into_path(),
+                        rule,
+                    });
+                    continue;
+                }
+            }
+
+            if entry.path_is_symlink() {
+                match self.link(entry.path(), &realm) {
+                    Ok(Some(link)) => {
+                        self.links.push(link);
+                        continue;
+                    }
+                    // A followed link is walked as what it links to.
+                    Ok(None) => {}
+                    Err(rule) => {
+                        if is_dir {
+                            walkdir.skip_current_dir();
+                        }
+                        self.excluded.push(Exclusion {
+                            path: entry.into_path(),
+                            rule,
+                        });
+                        continue;
+                    }
+                }
+            }
+
+            if is_dir {
+                dirs.push(entry);
+            } else if file_type.is_file() {
+                // Record the 'incoming' file sizes.
+                self.size += fs::metadata(entry.path())
+                    .expect("error: `WorkingDir::walk` failed to unwrap `fs::metadata`")
+                    .len();
+
+                self.files.push(entry.into_path());
+            } else {
+                self.excluded.push(Exclusion {
+                    path: entry.into_path(),
+                    rule: Rule::Special,
+                });
+            }
+        }
+
+        // Only the directories that lead to walked files are kept (unless they
+        // are included themselves).
+        let mut leading: HashSet<&Path> = HashSet::new();
+        for path in self.files.iter().chain(self.links.iter().map(|l| &l.path)) {
+            leading.extend(path.ancestors().skip(1));
+        }
+        for entry in dirs {
+            if entry.depth() == 0
+                || leading.contains(entry.path())
+                || filter.keeps_dir(entry.path())
+            {
+                self.dirs.push(DirEntryExt(entry));
             }
         }
         Ok(self)
     }
 
+    /// Applies the `Symlinks` policy to the link at `path`: returns the
+    /// `Link` to recreate, `None` if the link is followed, or the rule that
+    /// excludes it (e.g. because it leads outside of the `realm`).
+    fn link(&self, path: &Path, realm: &Path) -> Result<Option<Link>, Rule> {
+        let target = fs::read_link(path).map_err(|_| Rule::BrokenLink)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let resolved = normalize(&dir.join(&target));
+        if !resolved.starts_with(realm) {
+            return Err(Rule::EscapingLink(target));
+        }
+
+        match self.symlinks {
+            Symlinks::Skip => Err(Rule::Symlink),
+            Symlinks::Follow => {
+                // Links in the realm may still lead out of it through other
+                // links.
+                match (fs::canonicalize(path), fs::canonicalize(realm)) {
+                    (Ok(linked), Ok(realm)) if linked.starts_with(&realm) => Ok(None),
+                    (Ok(_), Ok(_)) => Err(Rule::EscapingLink(target)),
+                    _ => Err(Rule::BrokenLink),
+                }
+            }
+            Symlinks::Preserve => {
+                let target = if target.is_absolute() {
+                    relative_to(&resolved, &normalize(dir))
+                } else {
+                    target
+                };
+                Ok(Some(Link {
+                    path: path.to_path_buf(),
+                    target,
+                }))
+            }
+        }
+    }
+
+    /// Returns a `WorkingDir` with the same `root` and patterns that has not
+    /// been walked (e.g. to walk it again).
+    pub fn unwalked(&self) -> WorkingDir {
+        WorkingDir {
+            ignore_list: self.ignore_list.clone(),
+            include_list: self.include_list.clone(),
+            anchor: self.anchor.clone(),
+            gitignore: self.gitignore,
+            tracked: self.tracked.clone(),
+            symlinks: self.symlinks,
+            ..WorkingDir::new(&self.root)
+        }
+    }
+
+    /// Returns an unwalked `WorkingDir` with the same patterns, anchor, and
+    /// `parent` whose `root` is `path` (e.g. to walk only what changed below
+    /// the `root`). Walks of it are loaded to the same place.
+    pub fn below<P: AsRef<Path>>(&self, path: P) -> WorkingDir {
+        WorkingDir {
+            parent: self.parent.clone(),
+            anchor: Some(self.anchor.clone().unwrap_or_else(|| self.root.clone())),
+            ..WorkingDir {
+                root: path.as_ref().to_path_buf(),
+                ..self.unwalked()
+            }
+        }
+    }
+
+    /// Returns an unwalked `WorkingDir` with the same patterns whose `root`
+    /// (and anchor) are moved from `from` to `to`, e.g. to walk a checkout of
+    /// the realm instead of the realm.
+    ///
+    /// # Errors
+    ///
+    /// If the `root` is not below `from`, then this method returns an error.
+    pub fn relocated(&self, from: &Path, to: &Path) -> BifrostResult<WorkingDir> {
+        let anchor = match self.anchor {
+            Some(ref anchor) => Some(to.join(anchor.strip_prefix(from)?)),
+            None => None,
+        };
+        Ok(WorkingDir {
+            anchor,
+            ..WorkingDir::new(to.join(self.root.strip_prefix(from)?))
+                .ignore(&self.ignore_list)
+                .include(&self.include_list)
+                .gitignore(self.gitignore)
+                .symlinks(self.symlinks)
+        })
+    }
+
+    /// Returns whether or not `path` is outside of this working directory or
+    /// would be excluded by `walk`.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        if !path.starts_with(&self.root) {
+            return true;
+        }
+        match self.filter() {
+            Ok(mut filter) => filter.excludes_below(&self.root, path).is_some(),
+            // Nothing can be walked.
+            Err(_) => true,
+        }
+    }
+
+    /// Returns what the last `walk` excluded (directories are excluded as a
+    /// whole).
+    pub fn excluded(&self) -> &[Exclusion] {
+        &self.excluded
+    }
+
+    /// Returns the `Filter` that decides what `walk` excludes.
+    ///
+    /// # Errors
+    ///
+    /// If a pattern is invalid, then this method returns an error.
+    pub fn filter(&self) -> BifrostResult<Filter> {
+        let anchor = self.anchor.as_ref().unwrap_or(&self.root);
+        let filter = Filter::new(
+            anchor,
+            &self.ignore_list,
+            &self.include_list,
+            self.gitignore,
+        )?;
+        Ok(filter.tracked(self.tracked.clone()))
+    }
+
     /// Returns the `root` of this working directory.
     pub fn root(&self) -> &Path {
         self.root.as_path()
     }
 
+    /// Returns the directory that the patterns are relative to (the realm
+    /// root).
+    pub fn realm(&self) -> &Path {
+        self.anchor.as_ref().unwrap_or(&self.root)
+    }
+
     /// Returns the optional `parent` of this work directory.
     pub fn parent(&self) -> Option<&PathBuf> {
         self.parent.as_ref()
@@ -145,6 +457,29 @@ impl WorkingDir {
         self.size
     }
 
+    /// Returns the absolute paths of the files found by `walk`.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Returns the symbolic links found by `walk` (which are recreated rather
+    /// than copied).
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    /// Returns the SHA-256 hash of each file copied by `load`, by its path
+    /// relative to the loaded workspace.
+    pub fn checksums(&self) -> &[(PathBuf, String)] {
+        &self.checksums
+    }
+
+    /// Returns the absolute paths of the directories found by `walk` (until
+    /// they are consumed by `load`).
+    pub fn dir_paths(&self) -> Vec<&Path> {
+        self.dirs.iter().map(|d| d.0.path()).collect()
+    }
+
     /// Returns a mutable reference to this `WorkingDir`'s underlying `dirs`.
     /// This allows the `load` operation to avoid manual heap-traversal.
     pub fn dirs_as_mut(&mut self) -> &mut BinaryHeap<DirEntryExt> {
@@ -158,15 +493,6 @@ impl WorkingDir {
     }
 }
 
-/// Filter function used to filter entries in the construction of the `WalkDir` iterator.
-fn ignorable(entry: &DirEntry, ignore_list: &HashSet<String>) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map(|s| ignore_list.iter().any(|a| s.starts_with(a)))
-        .unwrap_or(false)
-}
-
 /// Light-ish wrapper around a `walkdir::DirEntry`. It is used to get access
 /// to an entry's depth. Since Rust's `BinaryHeap` is a max-heap, the ordering
 /// on this type needs to be inverted to get min-heap behavior out of this collection.
@@ -213,8 +539,10 @@ fn try_load(wd: &mut WorkingDir, bifrost_path: BifrostPath) -> BifrostResult<Ope
 /// Loads the specified `WorkingDir` to the target destination specified by the `BifrostPath` by:
 ///
 /// * loading the top-level-directory, then
-/// * loading the remaining directories, and then
-/// * loading the files
+/// * loading the remaining directories, then
+/// * loading the files and symbolic links, and then
+/// * giving the directories the permissions and modification times of their
+///   sources (once nothing is written to them anymore)
 ///
 /// Returns a `BifrostResult` containing the number of bytes written (or errors).
 fn _load(wd: &mut WorkingDir, to: &BifrostPath) -> BifrostResult<u64> {
@@ -227,18 +555,28 @@ fn _load(wd: &mut WorkingDir, to: &BifrostPath) -> BifrostResult<u64> {
     );
 
     // `dirs` will be empty after this if block.
+    let mut loaded: Vec<DirEntryExt> = vec![];
     if let Some(from) = wd.dirs_as_mut().pop() {
         _load_top_level_dir(&parent, &from, &to)?;
+        loaded.push(from);
 
         while let Some(from) = wd.dirs_as_mut().pop() {
             _load_dir(&parent, &from, &to)?;
+            loaded.push(from);
         }
     } else {
         // There are no directories to load, so create the target `to` path
         // to begin loading the file(s).
         fs::create_dir_all(&to.path)?;
     }
-    return _load_files(&parent, &wd, &to);
+    let nbytes = _load_files(parent, wd, to)?;
+    _load_links(parent, wd, to)?;
+
+    // Children before their parents.
+    for from in loaded.iter().rev() {
+        _load_dir_metadata(parent, from, to)?;
+    }
+    Ok(nbytes)
 }
 
 /// Creates the top-level directory at its target destination. To construct the appropriate
@@ -280,24 +618,188 @@ fn _load_dir(parent: &str, from: &DirEntryExt, to: &BifrostPath) -> BifrostResul
     Ok(())
 }
 
-/// Creates files within the Bifrost container and copies the contents from the
-/// workspace source files to the destination files created in the Bifrost container.
-fn _load_files(parent: &str, wd: &WorkingDir, to: &BifrostPath) -> BifrostResult<u64> {
+/// Gives a loaded directory the permissions and modification time of its
+/// source.
+fn _load_dir_metadata(parent: &str, from: &DirEntryExt, to: &BifrostPath) -> BifrostResult<()> {
+    if let Ok(to_dir) = from.0.path().strip_prefix(parent) {
+        load_dir_metadata(from.0.path(), &to.path.join(to_dir))?;
+    }
+    Ok(())
+}
+
+/// Gives the loaded directory `to` the permissions and modification time of
+/// the directory `from`. Loaded directories stay writable by their owner (see
+/// `writable`) so that they can be updated and removed (e.g. by `sync` and
+/// `reload`).
+///
+/// # Errors
+///
+/// If the metadata of `from` cannot be read or cannot be set on `to`, then
+/// this function returns an error.
+pub fn load_dir_metadata(from: &Path, to: &Path) -> BifrostResult<()> {
+    let meta = fs::metadata(from)?;
+    fs::set_permissions(to, writable(meta.permissions()))?;
+    filetime::set_file_mtime(to, FileTime::from_last_modification_time(&meta))?;
+    Ok(())
+}
+
+/// Copies the contents (and permissions) from the workspace source files to
+/// the destination files created in the Bifrost container, which take the
+/// modification times of their sources. The hash of each copied file is
+/// recorded (see [`checksums`](struct.WorkingDir.html#method.checksums)).
+///
+/// # Errors
+///
+/// If any file cannot be copied, then the remaining files are still copied
+/// and this function returns an error naming each file that failed.
+fn _load_files(parent: &str, wd: &mut WorkingDir, to: &BifrostPath) -> BifrostResult<u64> {
     let mut nbytes = 0;
+    let mut failed: Vec<String> = vec![];
     for entry in &wd.files {
-        if let Ok(to) = entry.strip_prefix(parent).map(|e| to.path.join(e)) {
-            File::create(&to)?;
-            if let Ok(n) = fs::copy(entry, to) {
-                nbytes += n;
+        if let Ok(relative) = entry.strip_prefix(parent) {
+            let to = to.path.join(relative);
+            let copied = hash::copy_sha256(entry, &to).and_then(|(n, hash)| {
+                let mtime = FileTime::from_last_modification_time(&fs::metadata(entry)?);
+                filetime::set_file_mtime(&to, mtime)?;
+                Ok((n, hash))
+            });
+            match copied {
+                Ok((n, hash)) => {
+                    nbytes += n;
+                    wd.checksums.push((relative.to_path_buf(), hash));
+                }
+                Err(e) => failed.push(format!("  {}: {}", entry.display(), e)),
             }
         }
     }
+
+    if !failed.is_empty() {
+        failure::bail!(
+            "error: could not load {} of {} files:\n{}",
+            failed.len(),
+            wd.files.len(),
+            failed.join("\n")
+        );
+    }
     Ok(nbytes)
 }
 
+/// Recreates the workspace's symbolic links within the Bifrost container.
+fn _load_links(parent: &str, wd: &WorkingDir, to: &BifrostPath) -> BifrostResult<()> {
+    for link in &wd.links {
+        if let Ok(to) = link.path.strip_prefix(parent).map(|e| to.path.join(e)) {
+            load_link(link, &to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Creates `link` at `to` (replacing whatever is there), with the
+/// modification time of the original link.
+///
+/// # Errors
+///
+/// If the link cannot be created, then this function returns an error naming
+/// it.
+pub fn load_link(link: &Link, to: &Path) -> BifrostResult<()> {
+    if let Ok(meta) = fs::symlink_metadata(to) {
+        if meta.is_dir() {
+            fs::remove_dir_all(to)?;
+        } else {
+            fs::remove_file(to)?;
+        }
+    }
+    if let Err(e) = symlink(&link.target, to) {
+        failure::bail!(
+            "error: could not create the symbolic link `{}` due to {}",
+            link.path.display(),
+            e
+        );
+    }
+    let meta = fs::symlink_metadata(&link.path)?;
+    let mtime = FileTime::from_last_modification_time(&meta);
+    filetime::set_symlink_file_times(to, FileTime::from_last_access_time(&meta), mtime)?;
+    Ok(())
+}
+
+/// Returns `permissions` with owner-write forced on. Loaded directories take
+/// these rather than the exact permissions of their source, since bifrost
+/// could not otherwise add, update or remove their contents (e.g. those of a
+/// read-only source directory).
+#[cfg(unix)]
+fn writable(permissions: fs::Permissions) -> fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    fs::Permissions::from_mode(permissions.mode() | 0o200)
+}
+
+/// Returns `permissions` without the read-only attribute (see the unix
+/// `writable`).
+#[cfg(windows)]
+fn writable(mut permissions: fs::Permissions) -> fs::Permissions {
+    permissions.set_readonly(false);
+    permissions
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path) -> std::io::Result<()> {
+    let dir = link.parent().unwrap_or(Path::new("")).join(target);
+    if dir.is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+/// Returns whether or not `path` is a symbolic link.
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|m| m.file_type().is_symlink())
+        .unwrap_or(false)
+}
+
+/// Removes the `.` and `..` components of `path` without consulting the file
+/// system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns the relative path from the directory `dir` to `path` (both of which
+/// are normalized and absolute).
+fn relative_to(path: &Path, dir: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in path.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::filter::Rule;
 
     #[test]
     fn test_default_working_dir() {
@@ -371,6 +873,193 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_walk_with_ignore_files() -> BifrostResult<()> {
+        let root = env::temp_dir().join(format!("bifrost-ignore-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+
+        write(".gitignore", "*.log\n!keep.log\nbuild/\n");
+        write(".git/info/exclude", "excluded.txt\n");
+        write("src/.bifrostignore", "secret/\n!debug.log\n");
+        for file in &[
+            "main.c",
+            "out.log",
+            "keep.log",
+            "excluded.txt",
+            "build/main.o",
+            "src/lib.c",
+            "src/debug.log",
+            "src/trace.log",
+            "src/secret/key",
+        ] {
+            write(file, "");
+        }
+
+        let walked = |wd: WorkingDir| -> BifrostResult<Vec<String>> {
+            let wd = wd.walk()?;
+            let mut files: Vec<String> = wd
+                .files()
+                .iter()
+                .map(|f| f.strip_prefix(&root).unwrap().display().to_string())
+                .collect();
+            files.sort();
+
+            // `is_ignored` agrees with `walk`.
+            for file in wd.files() {
+                assert!(!wd.is_ignored(file), "{}", file.display());
+            }
+            Ok(files)
+        };
+
+        let wd = WorkingDir::new(&root).ignore(&vec![".git"]);
+        assert_eq!(
+            vec![
+                ".gitignore",
+                "keep.log",
+                "main.c",
+                "src/.bifrostignore",
+                "src/debug.log",
+                "src/lib.c"
+            ],
+            walked(wd.unwalked())?
+        );
+        for ignored in &["out.log", "excluded.txt", "build/main.o", "src/secret/key"] {
+            assert!(wd.is_ignored(&root.join(ignored)), "{}", ignored);
+        }
+
+        let wd = wd.walk()?;
+        assert_eq!(
+            Some(&Rule::File {
+                pattern: String::from("build/"),
+                source: root.join(".gitignore"),
+            }),
+            wd.excluded()
+                .iter()
+                .find(|e| e.path == root.join("build"))
+                .map(|e| &e.rule)
+        );
+
+        // Only `.bifrostignore` files are honored without gitignore integration.
+        let files = walked(
+            WorkingDir::new(&root)
+                .ignore(&vec![".git"])
+                .gitignore(false),
+        )?;
+        assert!(files.contains(&String::from("out.log")));
+        assert!(files.contains(&String::from("build/main.o")));
+        assert!(!files.contains(&String::from("src/secret/key")));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_metadata_and_links() -> BifrostResult<()> {
+        use std::ffi::CString;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let tmp = env::temp_dir().join(format!("bifrost-links-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let realm = tmp.join("realm");
+        fs::create_dir_all(realm.join("bin"))?;
+        fs::write(realm.join("bin").join("run.sh"), "#!/bin/sh\n")?;
+        fs::set_permissions(
+            realm.join("bin").join("run.sh"),
+            fs::Permissions::from_mode(0o750),
+        )?;
+        symlink("bin/run.sh", realm.join("latest"))?;
+        symlink(
+            realm.join("bin").join("run.sh"),
+            realm.join("bin").join("absolute"),
+        )?;
+        symlink("/etc/hostname", realm.join("escaping"))?;
+        let fifo = CString::new(realm.join("fifo").as_os_str().as_bytes())?;
+        assert_eq!(0, unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) });
+        let old = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(realm.join("bin").join("run.sh"), old)?;
+        fs::set_permissions(realm.join("bin"), fs::Permissions::from_mode(0o700))?;
+        filetime::set_file_mtime(realm.join("bin"), old)?;
+        fs::create_dir_all(realm.join("docs"))?;
+        fs::write(realm.join("docs").join("README.md"), "# docs\n")?;
+        fs::set_permissions(realm.join("docs"), fs::Permissions::from_mode(0o555))?;
+
+        let rule = |wd: &WorkingDir, name: &str| {
+            wd.excluded()
+                .iter()
+                .find(|e| e.path == realm.join(name))
+                .map(|e| e.rule.clone())
+        };
+
+        let mut wd = WorkingDir::new(&realm).walk()?;
+        assert_eq!(2, wd.links().len());
+        assert_eq!(
+            Some(Rule::EscapingLink(PathBuf::from("/etc/hostname"))),
+            rule(&wd, "escaping")
+        );
+        assert_eq!(Some(Rule::Special), rule(&wd, "fifo"));
+
+        let to = BifrostPath {
+            path: tmp.join("container"),
+        };
+        wd.load(to.clone())?;
+        let loaded = to.path.join("realm");
+        assert_eq!(
+            PathBuf::from("bin/run.sh"),
+            fs::read_link(loaded.join("latest"))?
+        );
+        assert_eq!(
+            PathBuf::from("run.sh"),
+            fs::read_link(loaded.join("bin").join("absolute"))?
+        );
+        assert!(fs::symlink_metadata(loaded.join("escaping")).is_err());
+        assert!(fs::symlink_metadata(loaded.join("fifo")).is_err());
+
+        let meta = fs::metadata(loaded.join("bin").join("run.sh"))?;
+        assert_eq!(0o750, meta.permissions().mode() & 0o777);
+        assert_eq!(old, FileTime::from_last_modification_time(&meta));
+        let meta = fs::metadata(loaded.join("bin"))?;
+        assert_eq!(0o700, meta.permissions().mode() & 0o777);
+        assert_eq!(old, FileTime::from_last_modification_time(&meta));
+        // Read-only directories are loaded writable by their owner.
+        let meta = fs::metadata(loaded.join("docs"))?;
+        assert_eq!(0o755, meta.permissions().mode() & 0o777);
+
+        let wd = WorkingDir::new(&realm).symlinks(Symlinks::Skip).walk()?;
+        assert!(wd.links().is_empty());
+        assert_eq!(Some(Rule::Symlink), rule(&wd, "latest"));
+
+        let wd = WorkingDir::new(&realm).symlinks(Symlinks::Follow).walk()?;
+        assert!(wd.links().is_empty());
+        assert!(wd.files().contains(&realm.join("latest")));
+        assert_eq!(
+            Some(Rule::EscapingLink(PathBuf::from("/etc/hostname"))),
+            rule(&wd, "escaping")
+        );
+
+        fs::set_permissions(realm.join("docs"), fs::Permissions::from_mode(0o755))?;
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_and_relative_to() {
+        assert_eq!(
+            PathBuf::from("/realm/lib"),
+            normalize(Path::new("/realm/src/./../lib"))
+        );
+        assert_eq!(
+            PathBuf::from("../lib/a.so"),
+            relative_to(Path::new("/realm/lib/a.so"), Path::new("/realm/src"))
+        );
+    }
+
     #[test]
     fn test_propose_target_suffix() -> BifrostResult<()> {
         Ok(())
@@ -1,17 +1,30 @@
 //! Primary structures, mehtods, and functions that facilitate `bifrost::ops`.
-use crate::core::config::{self, CommandConfig, Config};
-use crate::core::workingdir::WorkingDir;
-use crate::util::{BifrostOptions, BifrostPath, BifrostResult, OperationInfo};
-
+use crate::core::config::{self, CommandConfig, Config, EnvVar, MountConfig};
+use crate::core::filter::Tracked;
+use crate::core::lock::{self, Lock, LockedImage};
+use crate::core::meta::{Source, WorkSpaceMeta};
+use crate::core::plan::LoadPlan;
+use crate::core::sync::Delta;
+use crate::core::watch;
+use crate::core::workingdir::{Symlinks, WorkingDir};
+use crate::util::docker::{
+    self, ContainerRunner, Mount, NetworkOptions, RealmImage, ResourceLimits, WorkspaceMount,
+};
+use crate::util::services::{self, Service, ServiceStack};
+use crate::util::{
+    bifrost_path, diff, git, hash, BifrostOptions, BifrostPath, BifrostResult, OperationInfo,
+    Secrets,
+};
+
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process;
+use std::rc::Rc;
 
 use crate::ArgMatches;
-use spinner::SpinnerBuilder;
-use subprocess::{self, Popen, PopenConfig, Redirection};
 
 /// Primary structure which `bifrost::ops operate upon.
 ///
@@ -75,6 +88,15 @@ impl WorkSpace {
         WorkSpaceArgs::parse_load(config, &args).to_load_space()
     }
 
+    /// Constructs a `LoadSpace` that reloads (i.e. replaces) the workspace.
+    pub fn to_reload_space(config: Config, args: &ArgMatches) -> LoadSpace {
+        WorkSpaceArgs {
+            mode: Mode::Reload,
+            ..WorkSpaceArgs::parse_load(config, args)
+        }
+        .to_load_space()
+    }
+
     /// Constructs a `ShowSpace`.
     pub fn to_show_space(config: Config, args: &ArgMatches) -> ShowSpace {
         WorkSpaceArgs::parse_show(config, &args).to_show_space()
@@ -85,6 +107,11 @@ impl WorkSpace {
         WorkSpaceArgs::parse_unload(config, args).to_unload_space()
     }
 
+    /// Constructs a `VerifySpace`.
+    pub fn to_verify_space(config: Config, args: &ArgMatches) -> VerifySpace {
+        WorkSpaceArgs::parse_verify(config, args).to_verify_space()
+    }
+
     /// Constructs a `RunSpace`.
     pub fn to_run_space(config: Config, args: &ArgMatches) -> RunSpace {
         WorkSpaceArgs::parse_run(config, &args).to_run_space()
@@ -106,6 +133,7 @@ pub enum Mode {
     Auto,
     Modified,
     Normal,
+    Reload,
 }
 
 /// This structure is similar to `WorkSpace`; however, there are a few differences.
@@ -130,6 +158,9 @@ struct WorkSpaceArgs {
     config: Config,
     contents: Option<Vec<String>>,
     ignore_list: Vec<String>,
+    include_list: Vec<String>,
+    gitignore: bool,
+    symlinks: Symlinks,
     opts: Option<BifrostOptions>,
 }
 
@@ -140,6 +171,9 @@ impl WorkSpaceArgs {
         let ws_mode = WorkSpaceBuilder::get_mode(&args);
         let ws_name = WorkSpaceBuilder::get_name(&config);
         let ws_ignore_list = WorkSpaceBuilder::get_ignore_list(&config);
+        let ws_include_list = WorkSpaceBuilder::get_include_list(&config);
+        let ws_gitignore = WorkSpaceBuilder::get_gitignore(&config);
+        let ws_symlinks = WorkSpaceBuilder::get_symlinks(&config);
         let ws_contents = config::values_of("contents", &args).map_or(vec![], |v| v);
 
         WorkSpaceArgs {
@@ -148,7 +182,15 @@ impl WorkSpaceArgs {
             config,
             contents: Some(ws_contents),
             ignore_list: ws_ignore_list,
-            opts: None,
+            include_list: ws_include_list,
+            gitignore: ws_gitignore,
+            symlinks: ws_symlinks,
+            opts: Some(BifrostOptions {
+                git: args.is_present("git"),
+                rev: config::value_of("rev", args),
+                dry_run: args.is_present("dry-run"),
+                ..Default::default()
+            }),
         }
     }
 
@@ -157,12 +199,19 @@ impl WorkSpaceArgs {
         let ws_mode = WorkSpaceBuilder::get_mode(&args);
         let ws_opts = WorkSpaceBuilder::get_opts(&args);
         let ws_name = WorkSpaceBuilder::get_name(&config);
+        let ws_ignore_list = WorkSpaceBuilder::get_ignore_list(&config);
+        let ws_include_list = WorkSpaceBuilder::get_include_list(&config);
+        let ws_gitignore = WorkSpaceBuilder::get_gitignore(&config);
+        let ws_symlinks = WorkSpaceBuilder::get_symlinks(&config);
         WorkSpaceArgs {
             name: Some(ws_name),
             mode: ws_mode,
             config,
             contents: None,
-            ignore_list: vec![],
+            ignore_list: ws_ignore_list,
+            include_list: ws_include_list,
+            gitignore: ws_gitignore,
+            symlinks: ws_symlinks,
             opts: ws_opts,
         }
     }
@@ -176,12 +225,31 @@ impl WorkSpaceArgs {
             config,
             contents: None,
             ignore_list: vec![],
+            include_list: vec![],
+            gitignore: true,
+            symlinks: Symlinks::default(),
+            opts: Some(BifrostOptions::default()),
+        }
+    }
+
+    /// Constructs `WorkSpaceArgs` from the current workspace name.
+    fn parse_verify(config: Config, _args: &ArgMatches) -> Self {
+        let ws_name = WorkSpaceBuilder::get_name(&config);
+        WorkSpaceArgs {
+            name: Some(ws_name),
+            mode: Mode::Normal,
+            config,
+            contents: None,
+            ignore_list: vec![],
+            include_list: vec![],
+            gitignore: true,
+            symlinks: Symlinks::default(),
             opts: Some(BifrostOptions::default()),
         }
     }
 
     /// Constructs `WorkSpaceArgs` from the workspace name and associated commands.
-    fn parse_run(config: Config, _args: &ArgMatches) -> Self {
+    fn parse_run(config: Config, args: &ArgMatches) -> Self {
         let ws_name = WorkSpaceBuilder::get_name(&config);
 
         WorkSpaceArgs {
@@ -190,10 +258,27 @@ impl WorkSpaceArgs {
             config,
             contents: None,
             ignore_list: vec![],
-            opts: None,
+            include_list: vec![],
+            gitignore: true,
+            symlinks: Symlinks::default(),
+            opts: Some(BifrostOptions {
+                update_lock: args.is_present("update-lock"),
+                ..Default::default()
+            }),
         }
     }
 
+    /// Constructs a `WorkingDir` of `path` that honors the realm's ignore and
+    /// include patterns.
+    fn working_dir(&self, path: &Path) -> WorkingDir {
+        WorkingDir::new(path)
+            .ignore(&self.ignore_list)
+            .include(&self.include_list)
+            .anchor(self.config.cwd())
+            .gitignore(self.gitignore)
+            .symlinks(self.symlinks)
+    }
+
     /// Constructs a `LoadSpace` for `WorkSpaceArgs`. `WorkSpaceArgs` has all the
     /// information needed to construct a `WorkSpace`. The _proposed_ `contents`
     /// are passed off to `WorkSpaceBuilder::args_to_paths` for some _basic_
@@ -204,11 +289,11 @@ impl WorkSpaceArgs {
     ///
     /// Otherwise, a new `WorkSpace` is constructed with new `contents` of type `Vec<WorkingDir>`
     /// and a `LoadSpace` is returned.
-    fn to_load_space(self) -> LoadSpace {
+    fn to_load_space(mut self) -> LoadSpace {
         let mut working_dirs: Vec<WorkingDir> = Vec::new();
-        if let Some(contents) = self.contents {
+        if let Some(contents) = self.contents.take() {
             if contents.is_empty() {
-                working_dirs.push(WorkingDir::new(self.config.cwd()).ignore(&self.ignore_list));
+                working_dirs.push(self.working_dir(self.config.cwd()));
             } else {
                 let paths = WorkSpaceBuilder::args_to_paths(&self.config, contents, &is_loadable);
                 if paths.is_empty() {
@@ -220,11 +305,18 @@ impl WorkSpaceArgs {
                 }
 
                 for path in paths {
-                    working_dirs.push(WorkingDir::new(path).ignore(&self.ignore_list));
+                    working_dirs.push(self.working_dir(&path));
                 }
             }
         }
 
+        let opts = self.opts.unwrap_or_default();
+        let source = match opts.rev {
+            Some(_) => Source::Revision,
+            None if opts.git => Source::Index,
+            None => Source::Worktree,
+        };
+
         LoadSpace {
             workspace: WorkSpace {
                 name: self.name,
@@ -234,6 +326,11 @@ impl WorkSpaceArgs {
                 size: 0u64,
             },
             target: None,
+            source,
+            revision: opts.rev,
+            checkout: None,
+            dry_run: opts.dry_run,
+            checksums: BTreeMap::new(),
         }
     }
 
@@ -249,6 +346,11 @@ impl WorkSpaceArgs {
             },
             target: None,
             opts: self.opts,
+            ignore_list: self.ignore_list,
+            include_list: self.include_list,
+            gitignore: self.gitignore,
+            symlinks: self.symlinks,
+            delta: None,
         }
     }
 
@@ -266,6 +368,20 @@ impl WorkSpaceArgs {
         }
     }
 
+    /// Translates `WorkSpaceArgs` into a `VerifySpace`.
+    fn to_verify_space(self) -> VerifySpace {
+        VerifySpace {
+            workspace: WorkSpace {
+                name: self.name,
+                mode: self.mode,
+                config: self.config,
+                contents: None,
+                size: 0u64,
+            },
+            target: None,
+        }
+    }
+
     fn to_run_space(self) -> RunSpace {
         RunSpace {
             workspace: WorkSpace {
@@ -277,6 +393,15 @@ impl WorkSpaceArgs {
             },
             target: None,
             cmd: None,
+            env: vec![],
+            user: None,
+            limits: ResourceLimits::default(),
+            network: NetworkOptions::default(),
+            image: RealmImage::default(),
+            update_lock: self.opts.is_some_and(|o| o.update_lock),
+            mount: None,
+            mounts: vec![],
+            services: vec![],
         }
     }
 }
@@ -306,6 +431,17 @@ pub struct LoadSpace {
     workspace: WorkSpace,
     /// The target path to `load` the `WorkSpace` to.
     target: Option<BifrostPath>,
+    /// What is loaded: the realm, the files git tracks, or a revision.
+    source: Source,
+    /// The revision to load (or, once prepared, the id of the commit that is
+    /// loaded).
+    revision: Option<String>,
+    /// The temporary checkout of the revision that is loaded.
+    checkout: Option<PathBuf>,
+    /// Whether or not the load is only planned, see [`plan`](#method.plan).
+    dry_run: bool,
+    /// The SHA-256 hash of each loaded file, by its path in the workspace.
+    checksums: BTreeMap<String, String>,
 }
 
 /// A `LoadSpace`'s primary goal is to `load` contents into the Bifrost container.
@@ -338,6 +474,231 @@ impl LoadSpace {
         self.workspace.as_mut()
     }
 
+    /// Updates the workspace loaded at the target `BifrostPath`: only new or
+    /// changed files are copied, and files removed from the realm are removed
+    /// from the workspace. The summary of the update is the `text` of the
+    /// returned `OperationInfo`.
+    pub fn sync(&mut self) -> BifrostResult<OperationInfo> {
+        let path = self
+            .target
+            .clone()
+            .expect("BUG: `BifrostPath` should not be `None` here");
+
+        let name = self
+            .name()
+            .expect("BUG: `LoadSpace::sync` failed to unwrap `WorkSpace::name`")
+            .clone();
+        // Only what changes is hashed, so the checksums of the rest are kept
+        // (and have to have been recorded when the workspace was loaded).
+        if self.checksums.is_empty() {
+            match WorkSpaceMeta::load(self.home_path(), &name)? {
+                Some(meta) if !meta.files.is_empty() || !meta.roots.is_empty() => {
+                    self.checksums = meta.files;
+                }
+                _ if fs::read_dir(&path.path)?.next().is_some() => failure::bail!(
+                    "error: the workspace of realm `{}` has no checksums, \
+                     run `bifrost reload` to record them",
+                    name
+                ),
+                // Nothing has been loaded yet.
+                _ => {}
+            }
+        }
+
+        let contents = self.workspace.contents.as_ref().map_or(&[][..], |c| &c[..]);
+        let summary = Delta::between(contents, &path.path)?.apply(&mut self.checksums)?;
+
+        Ok(OperationInfo {
+            bytes: Some(summary.bytes),
+            name,
+            text: Some(summary.to_string().into_bytes()),
+            stderr: self.skipped(),
+            ..Default::default()
+        })
+    }
+
+    /// Watches the realm and `sync`s the workspace after each burst of changes
+    /// until Ctrl-C is pressed. The outcome of each `sync` is passed to
+    /// `on_sync`; watching stops if `on_sync` returns an error.
+    ///
+    /// # Errors
+    ///
+    /// If the realm cannot be watched, or `on_sync` returns an error, then this
+    /// method returns an error.
+    pub fn watch<F>(&mut self, mut on_sync: F) -> BifrostResult<()>
+    where
+        F: FnMut(BifrostResult<OperationInfo>) -> BifrostResult<()>,
+    {
+        let roots: Vec<WorkingDir> = self
+            .workspace
+            .contents
+            .iter()
+            .flatten()
+            .map(WorkingDir::unwalked)
+            .collect();
+
+        watch::watch(&roots, watch::DEBOUNCE, |changed| {
+            // Only what changed is walked (and synced) again.
+            let walked: BifrostResult<Vec<WorkingDir>> = watch::affected(&roots, changed)
+                .into_iter()
+                .map(WorkingDir::walk)
+                .collect();
+            let result = match walked {
+                Ok(walked) => {
+                    self.workspace.contents = Some(walked);
+                    let result = self.sync();
+                    // The roots, rather than what changed below them, are
+                    // recorded.
+                    self.workspace.contents =
+                        Some(roots.iter().map(WorkingDir::unwalked).collect());
+                    result.and_then(|op_info| {
+                        self.save_meta()?;
+                        Ok(op_info)
+                    })
+                }
+                Err(e) => Err(e),
+            };
+            on_sync(result)
+        })
+    }
+
+    /// Prepares the contents according to the `source`: restricts them to the
+    /// files git tracks or moves them to a checkout of the revision.
+    fn prep_source(&mut self) -> BifrostResult<()> {
+        let cwd = self.workspace.config().cwd().clone();
+        let contents = match self.workspace.contents.take() {
+            Some(contents) => contents,
+            None => return Ok(()),
+        };
+
+        let contents = match self.source {
+            Source::Worktree => contents,
+            Source::Index => {
+                let tracked = Rc::new(Tracked::new(git::tracked_files(&cwd)?));
+                self.revision = git::head(&cwd);
+                // The index, rather than `.gitignore` files, decides what is
+                // loaded.
+                contents
+                    .iter()
+                    .map(|wd| wd.unwalked().gitignore(false).tracked(tracked.clone()))
+                    .collect()
+            }
+            Source::Revision => {
+                let rev = self
+                    .revision
+                    .clone()
+                    .expect("BUG: `LoadSpace::revision` should not be `None` here");
+                let commit = git::resolve(&cwd, &rev)?;
+
+                let name = self
+                    .name()
+                    .expect("BUG: `LoadSpace::prep_source` failed to unwrap `WorkSpace::name`");
+                let checkout = env::temp_dir().join(format!("bifrost-{}-{}", name, process::id()));
+                let realm = checkout.join(cwd.file_name().unwrap_or_else(|| "realm".as_ref()));
+                fs::create_dir_all(&realm)?;
+                self.checkout = Some(checkout);
+                git::archive(&cwd, &commit, &realm)?;
+
+                let mut relocated = vec![];
+                for wd in &contents {
+                    let wd = wd.relocated(&cwd, &realm)?;
+                    if fs::symlink_metadata(wd.root()).is_err() {
+                        failure::bail!(
+                            "error: `{}` does not exist at revision `{}`",
+                            wd.root().strip_prefix(&realm)?.display(),
+                            rev
+                        );
+                    }
+                    relocated.push(wd);
+                }
+                self.revision = Some(commit);
+                relocated
+            }
+        };
+        self.workspace.contents = Some(contents);
+        Ok(())
+    }
+
+    /// Returns what loading this space's (walked) `WorkSpace` would do, without
+    /// loading anything (`bifrost load --dry-run`).
+    ///
+    /// # Errors
+    ///
+    /// If a walked file can no longer be inspected, then this method returns
+    /// an error.
+    pub fn plan(&self) -> BifrostResult<LoadPlan> {
+        let name = self
+            .name()
+            .expect("BUG: `LoadSpace::plan` failed to unwrap `WorkSpace::name`");
+        let loaded = BifrostPath::from_existing(self.home_path(), Some(name)).is_ok();
+        let contents = self.workspace.contents.as_ref().map_or(&[][..], |c| &c[..]);
+        LoadPlan::new(name, loaded, contents)
+    }
+
+    /// Lists what the walk skipped because of what it is (e.g. a special file
+    /// or a symbolic link that leads outside of the realm), one warning per
+    /// line.
+    fn skipped(&self) -> String {
+        let mut text = String::new();
+        for wd in self.workspace.contents.iter().flatten() {
+            let parent = wd.parent().map(|p| p.as_path()).unwrap_or(Path::new(""));
+            for exclusion in wd.excluded().iter().filter(|e| e.rule.is_skipped()) {
+                let path = exclusion
+                    .path
+                    .strip_prefix(parent)
+                    .unwrap_or(&exclusion.path);
+                text.push_str(&format!(
+                    "warn: skipped {}  ({})\n",
+                    path.display(),
+                    exclusion.rule
+                ));
+            }
+        }
+        text
+    }
+
+    /// Records what was loaded in the workspace's metadata.
+    fn save_meta(&self) -> BifrostResult<()> {
+        let roots = self
+            .workspace
+            .contents
+            .iter()
+            .flatten()
+            .filter_map(|wd| wd.root().strip_prefix(wd.realm()).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        let meta = WorkSpaceMeta {
+            source: self.source,
+            revision: self.revision.clone(),
+            roots,
+            files: self.checksums.clone(),
+        };
+        let name = self
+            .name()
+            .expect("BUG: `LoadSpace::save_meta` failed to unwrap `WorkSpace::name`");
+        meta.save(self.home_path(), name)
+    }
+
+    /// Loads this space's `WorkSpace` into the staging `BifrostPath` and then
+    /// swaps it into place, replacing the loaded workspace (if any). A reload
+    /// that fails removes what it staged and leaves the workspace as it was.
+    pub fn reload(&mut self) -> BifrostResult<OperationInfo> {
+        let staging = self
+            .target
+            .clone()
+            .expect("BUG: `BifrostPath` should not be `None` here");
+
+        let op_info = match self.load() {
+            Ok(op_info) => op_info,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&staging.path);
+                return Err(e);
+            }
+        };
+        self.target = Some(staging.replace(self.name())?);
+        Ok(op_info)
+    }
+
     /// Loads this spaces's `WorkSpace` to the target `BifrostPath`.
     ///
     /// # Panics
@@ -379,6 +740,15 @@ impl LoadSpace {
             failure::bail!("error: could not `load` all contents");
         }
 
+        self.checksums = self
+            .workspace
+            .contents
+            .iter()
+            .flatten()
+            .flat_map(WorkingDir::checksums)
+            .map(|(path, hash)| (path.to_string_lossy().into_owned(), hash.clone()))
+            .collect();
+
         // The name really should not be `None` at this point.
         let name = self
             .name()
@@ -391,6 +761,7 @@ impl LoadSpace {
             bytes: Some(nbytes),
             name,
             text: None,
+            stderr: self.skipped(),
             ..Default::default()
         })
     }
@@ -427,7 +798,32 @@ impl BifrostOperable for LoadSpace {
         if self.target.is_some() {
             failure::bail!("error: a `BifrostPath` has already been prepared for this `LoadSpace`");
         }
-        self.target = Some(BifrostPath::new(self.home_path(), self.name())?);
+        self.prep_source()?;
+        // A planned load has no target.
+        if self.dry_run {
+            return Ok(self);
+        }
+        // A modified (or auto) load updates the existing workspace (if there
+        // is one).
+        let existing = match self.workspace.mode {
+            Mode::Modified | Mode::Auto => {
+                BifrostPath::from_existing(self.home_path(), self.name()).ok()
+            }
+            Mode::Reload => {
+                let staging = BifrostPath::staging(self.home_path(), self.name())?;
+                // Left behind by an interrupted reload.
+                if fs::symlink_metadata(&staging.path).is_ok() {
+                    fs::remove_dir_all(&staging.path)?;
+                }
+                self.target = Some(staging);
+                return Ok(self);
+            }
+            Mode::Normal => None,
+        };
+        self.target = match existing {
+            Some(path) => Some(path),
+            None => Some(BifrostPath::new(self.home_path(), self.name())?),
+        };
         Ok(self)
     }
 
@@ -450,9 +846,21 @@ impl BifrostOperable for LoadSpace {
         Ok(self)
     }
 
-    /// Executes a `LoadSpace`'s primary function: `load`.
+    /// Executes a `LoadSpace`'s primary function: `load` (or `sync` for a
+    /// modified or auto load, and `reload` for a reload).
     fn exec(&mut self) -> BifrostResult<OperationInfo> {
-        Ok(self.load()?)
+        if self.dry_run {
+            failure::bail!(
+                "error: a planned `LoadSpace` cannot be executed, see `LoadSpace::plan`"
+            );
+        }
+        let op_info = match self.workspace.mode {
+            Mode::Modified | Mode::Auto => self.sync()?,
+            Mode::Normal => self.load()?,
+            Mode::Reload => self.reload()?,
+        };
+        self.save_meta()?;
+        Ok(op_info)
     }
 
     /// Returns a `clone`d version of the target `BifrostPath` (or `None`).
@@ -464,6 +872,15 @@ impl BifrostOperable for LoadSpace {
     }
 }
 
+/// Removes the temporary checkout of a loaded revision.
+impl Drop for LoadSpace {
+    fn drop(&mut self) {
+        if let Some(ref checkout) = self.checkout {
+            let _ = fs::remove_dir_all(checkout);
+        }
+    }
+}
+
 /// Primary data structure used to `show` `WorkSpace`s that exist within the
 /// Bifrost container.
 #[derive(Debug)]
@@ -474,6 +891,19 @@ pub struct ShowSpace {
     target: Option<BifrostPath>,
     /// Options that control _how_ a `ShowSpace` is displayed.
     opts: Option<BifrostOptions>,
+    /// Names of files and/or directories to ignore when comparing the realm
+    /// with the workspace.
+    ignore_list: Vec<String>,
+    /// Patterns of the files to compare (all of them, if it is empty).
+    include_list: Vec<String>,
+    /// Whether or not `.gitignore` files are honored when comparing the realm
+    /// with the workspace.
+    gitignore: bool,
+    /// How symbolic links are walked when comparing the realm with the
+    /// workspace.
+    symlinks: Symlinks,
+    /// The differences between the realm and the workspace (for `--diff`).
+    delta: Option<Delta>,
 }
 
 /// A `ShowSpace`'s primary goal is to `show` the contents in the Bifrost container
@@ -498,15 +928,152 @@ impl ShowSpace {
     /// current bifrost realm.
     /// * `show_diff` - displays only files that have been modified in the current
     /// bifrost realm but have not be re-loaded into the bifrost container realm.
+    /// * `show_ignored` - displays what loading the current bifrost realm
+    /// excludes and why.
     pub fn show(&self) -> BifrostResult<OperationInfo> {
         if let Some(ref opts) = self.opts {
             if opts.verbose {
                 return Ok(self.show_all()?);
+            } else if opts.diff {
+                return Ok(self.show_diff(opts.patch)?);
+            } else if opts.ignored {
+                return Ok(self.show_ignored()?);
             }
         }
         return Ok(self.show_default()?);
     }
 
+    /// Lists the files that differ between the realm and the workspace (and,
+    /// with `patch`, how text files differ). The text is empty if they do not
+    /// differ.
+    fn show_diff(&self, patch: bool) -> BifrostResult<OperationInfo> {
+        let delta = self
+            .delta
+            .as_ref()
+            .expect("BUG: `ShowSpace::show_diff` expected `self.delta` to be `Some`");
+
+        let mut text = String::new();
+        for change in &delta.added {
+            text.push_str(&format!("added:    {}\n", change.path.display()));
+        }
+        for change in &delta.modified {
+            text.push_str(&format!("modified: {}\n", change.path.display()));
+        }
+        for change in &delta.links {
+            let status = if change.existed { "modified" } else { "added" };
+            text.push_str(&format!(
+                "{:<9} {} -> {}\n",
+                format!("{}:", status),
+                change.path.display(),
+                change.link.target.display()
+            ));
+        }
+        for path in &delta.removed {
+            text.push_str(&format!("deleted:  {}\n", path.display()));
+        }
+
+        if patch {
+            let loaded = |p: &Path| Some(delta.target().join(p));
+            for change in &delta.added {
+                text.push_str(&file_diff(&change.path, None, Some(change.source.clone()))?);
+            }
+            for change in &delta.modified {
+                let source = Some(change.source.clone());
+                text.push_str(&file_diff(&change.path, loaded(&change.path), source)?);
+            }
+            for path in &delta.removed {
+                text.push_str(&file_diff(path, loaded(path), None)?);
+            }
+        }
+
+        let mut op_info = OperationInfo::new();
+        op_info.text = Some(text.into_bytes());
+        op_info.name = self
+            .name()
+            .expect("BUG: `ShowSpace::show_diff` expected `self.name` to be `Some`")
+            .to_string();
+        Ok(op_info)
+    }
+
+    /// Walks the parts of the realm that were loaded into the workspace (the
+    /// roots recorded in its metadata).
+    ///
+    /// # Errors
+    ///
+    /// If the workspace's metadata does not record its roots (i.e. it was
+    /// loaded by an older bifrost), then this method returns an error.
+    fn loaded_contents(&self) -> BifrostResult<Vec<WorkingDir>> {
+        let name = self
+            .name()
+            .expect("BUG: `ShowSpace::loaded_contents` expected `self.name` to be `Some`");
+        let roots = WorkSpaceMeta::load(self.home_path(), name)?
+            .map(|meta| meta.roots)
+            .unwrap_or_default();
+        if roots.is_empty() {
+            failure::bail!(
+                "error: the workspace of realm `{}` does not record what was loaded, \
+                 reload it with `bifrost reload`",
+                name
+            );
+        }
+
+        let cwd = self.workspace.config().cwd();
+        roots
+            .iter()
+            .map(|root| {
+                if root.as_os_str().is_empty() {
+                    self.working_dir(cwd).walk()
+                } else {
+                    self.working_dir(&cwd.join(root)).walk()
+                }
+            })
+            .collect()
+    }
+
+    /// Lists what a load of the realm excludes (directories as a whole) and
+    /// the rule that excludes it.
+    fn show_ignored(&self) -> BifrostResult<OperationInfo> {
+        let cwd = self.workspace.config().cwd();
+        let wd = self.working_dir(cwd).walk()?;
+
+        let mut excluded: Vec<_> = wd.excluded().iter().collect();
+        excluded.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut text = String::new();
+        for exclusion in excluded {
+            let path = exclusion.path.strip_prefix(cwd).unwrap_or(&exclusion.path);
+            let slash = if exclusion.path.is_dir() { "/" } else { "" };
+            text.push_str(&format!(
+                "excluded: {}{}  ({})\n",
+                path.display(),
+                slash,
+                exclusion.rule
+            ));
+        }
+
+        let name = self
+            .name()
+            .expect("BUG: `ShowSpace::show_ignored` expected `self.name` to be `Some`")
+            .to_string();
+
+        Ok(OperationInfo {
+            name,
+            text: Some(text.into_bytes()),
+            ..Default::default()
+        })
+    }
+
+    /// Constructs a `WorkingDir` of `path` that honors the realm's ignore and
+    /// include patterns.
+    fn working_dir(&self, path: &Path) -> WorkingDir {
+        WorkingDir::new(path)
+            .ignore(&self.ignore_list)
+            .include(&self.include_list)
+            .anchor(self.workspace.config().cwd())
+            .gitignore(self.gitignore)
+            .symlinks(self.symlinks)
+    }
+
     fn show_default(&self) -> BifrostResult<OperationInfo> {
         let mut op_info = OperationInfo::new();
 
@@ -573,8 +1140,9 @@ impl BifrostOperable for ShowSpace {
             if opts.verbose {
                 return Ok(self);
             } else if opts.diff {
-                // [TODO] build the space for a --diff display
-                println!("building for --diff (gotta write that diff-er now...)");
+                let target = bifrost_path::get_path_or_empty(self.target.clone());
+                let contents = self.loaded_contents()?;
+                self.delta = Some(Delta::between(&contents, &target)?);
             }
         }
         Ok(self)
@@ -629,13 +1197,15 @@ impl UnloadSpace {
         if let Some(ref target) = self.target {
             match hofund::remove_dir_all(&target.path) {
                 Ok(_) => {
+                    let name = self
+                        .name()
+                        .expect("BUG: `UnloadSpace::unload` expected `name` to be `Some`")
+                        .to_string();
+                    WorkSpaceMeta::remove(self.home_path(), &name)?;
                     return Ok(OperationInfo {
-                        name: self
-                            .name()
-                            .expect("BUG: `UnloadSpace::unload` expected `name` to be `Some`")
-                            .to_string(),
+                        name,
                         ..Default::default()
-                    })
+                    });
                 }
                 Err(e) => {
                     io::stdout().write_fmt(format_args!(
@@ -685,6 +1255,111 @@ impl BifrostOperable for UnloadSpace {
     }
 }
 
+/// Primary data structure used to `verify` a `WorkSpace` that exists within the
+/// Bifrost container against the checksums recorded when it was loaded.
+#[derive(Debug)]
+pub struct VerifySpace {
+    /// The `WorkSpace` to be verified.
+    workspace: WorkSpace,
+    /// The target path the `WorkSpace` was loaded to.
+    target: Option<BifrostPath>,
+}
+
+/// A `VerifySpace`'s primary goal is to `verify` that the contents of the
+/// Bifrost container are what was `load`ed from the current workspace.
+impl VerifySpace {
+    /// Returns a reference to the underlying `home_path` `PathBuf` defined
+    /// upon configuration.
+    pub fn home_path(&self) -> &PathBuf {
+        self.workspace.config().home_path()
+    }
+
+    /// Returns a optional reference to the underlying `WorkSpace` name.
+    pub fn name(&self) -> Option<&String> {
+        self.workspace.name()
+    }
+
+    /// Hashes each loaded file and compares it with the checksum recorded when
+    /// it was loaded. Files that are missing or differ (or cannot be read) are
+    /// listed in the `text` of the returned `OperationInfo`, which is empty if
+    /// the workspace is intact; its `bytes` are the number of bytes verified.
+    /// Files the workspace gained since (e.g. build outputs) are not checked.
+    ///
+    /// # Errors
+    ///
+    /// If the workspace has no checksums, then this method returns an error.
+    pub fn verify(&self) -> BifrostResult<OperationInfo> {
+        let target = self
+            .target
+            .clone()
+            .expect("BUG: `BifrostPath` should not be `None` here");
+        let name = self
+            .name()
+            .expect("BUG: `VerifySpace::verify` expected `name` to be `Some`")
+            .to_string();
+
+        let checksums = WorkSpaceMeta::load(self.home_path(), &name)?
+            .map(|meta| meta.files)
+            .unwrap_or_default();
+        if checksums.is_empty() {
+            failure::bail!(
+                "error: the workspace of realm `{}` has no checksums, \
+                 run `bifrost reload` to record them",
+                name
+            );
+        }
+
+        let mut nbytes = 0u64;
+        let mut text = String::new();
+        for (path, checksum) in &checksums {
+            let loaded = target.path.join(path);
+            if fs::symlink_metadata(&loaded).is_err() {
+                text.push_str(&format!("missing:  {}\n", path));
+                continue;
+            }
+            match hash::file_sha256(&loaded) {
+                Ok(ref hash) if hash == checksum => nbytes += fs::metadata(&loaded)?.len(),
+                Ok(_) => text.push_str(&format!("modified: {}\n", path)),
+                Err(e) => text.push_str(&format!("failed:   {} ({})\n", path, e)),
+            }
+        }
+
+        Ok(OperationInfo {
+            name,
+            bytes: Some(nbytes),
+            text: Some(text.into_bytes()),
+            ..Default::default()
+        })
+    }
+}
+
+/// Implements `BifrostOperable` for `VerifySpace`.
+/// A `VerifySpace` is `prep`-able, `build`-able, and `exec`-utable.
+impl BifrostOperable for VerifySpace {
+    /// Prepares a `VerifySpace` by setting its `BifrostPath` from the current
+    /// existing workspace.
+    fn prep(&mut self) -> BifrostResult<&mut dyn BifrostOperable> {
+        let path = BifrostPath::try_from_existing(self.home_path(), self.name())?;
+        self.target = Some(path);
+        Ok(self)
+    }
+
+    /// Builds a `VerifySpace`.
+    fn build(&mut self) -> BifrostResult<&mut dyn BifrostOperable> {
+        Ok(self)
+    }
+
+    /// Executes a `VerifySpace`'s primary function: `verify`.
+    fn exec(&mut self) -> BifrostResult<OperationInfo> {
+        self.verify()
+    }
+
+    /// Returns a cloned version of the target `BifrostPath` (or None).
+    fn target(&self) -> Option<BifrostPath> {
+        self.target.clone()
+    }
+}
+
 /// Primary data structure used to `run` `WorkSpace`s that exist within the
 /// Bifrost container.
 #[derive(Debug)]
@@ -695,6 +1370,24 @@ pub struct RunSpace {
     target: Option<BifrostPath>,
     /// The commands to be executed within the bifrost container.
     cmd: Option<CommandConfig>,
+    /// The environment variables passed to the bifrost container.
+    env: Vec<EnvVar>,
+    /// The user (`name|uid[:gid]`) to run commands as within the container.
+    user: Option<String>,
+    /// The resource limits applied to the container.
+    limits: ResourceLimits,
+    /// The networking mode and published ports of the container.
+    network: NetworkOptions,
+    /// The image the container is created from.
+    image: RealmImage,
+    /// Whether or not the image pinned in `Bifrost.lock` may be replaced.
+    update_lock: bool,
+    /// The mount of the workspace into the container.
+    mount: Option<WorkspaceMount>,
+    /// Additional bind mounts and named cache volumes.
+    mounts: Vec<Mount>,
+    /// Sidecar services started on a private network for the run.
+    services: Vec<Service>,
 }
 
 /// An `RunSpace`'s primary goal is to `run` commands.
@@ -710,108 +1403,125 @@ impl RunSpace {
         self.workspace.name()
     }
 
-    pub fn run(&self) -> BifrostResult<OperationInfo> {
-        let home_path = match dirs::home_dir() {
-            Some(path) => path,
-            _ => {
-                io::stderr().write("error: failed to `run` target is `None`".as_bytes())?;
-                process::exit(1);
-            }
+    /// Pins the image in the realm's `Bifrost.lock` on the first run and
+    /// checks that later runs use the pinned image (unless the lock is being
+    /// updated).
+    fn check_lock(&self) -> BifrostResult<()> {
+        // Image ids are not the same on every machine, so pulled images are
+        // pinned by their registry digest and built images by what they are
+        // built from.
+        let tag = self.image.tag();
+        let (digest, context) = match self.image.builder("docker", self.home_path())? {
+            Some(builder) => (None, Some(builder.hash(self.home_path())?)),
+            None => match docker::repo_digest("docker", tag) {
+                Some(digest) => (Some(digest), None),
+                None => failure::bail!(
+                    "error: could not resolve the registry digest of image `{}`",
+                    tag
+                ),
+            },
+        };
+        let base = self.image.base(self.home_path());
+        let base_digest = base.as_ref().and_then(|b| docker::repo_digest("docker", b));
+
+        let image = LockedImage {
+            tag: String::from(tag),
+            digest,
+            context,
+            base,
+            base_digest,
         };
 
-        let path = home_path
-            .join(".bifrost")
-            .join("container:")
-            .join("bifrost");
+        let cwd = self.workspace.config.cwd();
+        match Lock::load(cwd)? {
+            Some(ref lock) if lock.image == image => Ok(()),
+            Some(ref lock) if !self.update_lock => lock.check(&image),
+            previous => {
+                let lock = Lock { image };
+                lock.write(cwd)?;
+                let verb = if previous.is_some() {
+                    "updated"
+                } else {
+                    "pinned"
+                };
+                io::stdout().write_fmt(format_args!(
+                    "bifrost: {} image `{}` ({}) in {}\n",
+                    verb,
+                    lock.image.tag,
+                    lock.image.pin(),
+                    lock::LOCK_FILE
+                ))?;
+                Ok(())
+            }
+        }
+    }
 
-        let path = path
-            .to_str()
-            .expect("error: could not unwrap path to `run`");
+    pub fn run(&self) -> BifrostResult<OperationInfo> {
+        let workspace = match self.mount {
+            Some(ref mount) => mount.clone(),
+            None => failure::bail!("error: failed to `run` workspace mount is `None`"),
+        };
 
         let target_dir = self
             .name()
             .expect("error: `run` expected name to be `Some`");
 
-        let mut output = match self.cmd {
-            Some(ref b) => RunSpace::_run(b, &path, &target_dir)?,
+        let cmds = match self.cmd.as_ref().map(|c| c.get_cmds()) {
+            Some(Some(cmds)) => cmds,
+            Some(None) => {
+                failure::bail!("bail for now  could not get cmds from command config... [FIX]")
+            }
             None => failure::bail!("error: failed to `run` `CommandConfig` is `None`"),
         };
 
-        output.name = self
-            .name()
-            .unwrap_or(&String::from("run-default"))
-            .to_owned();
+        let mut runner = ContainerRunner {
+            name: String::from("docker"),
+            image: String::from(self.image.tag()),
+            container: docker::container_name(target_dir),
+            workspace,
+            env: self.env.clone(),
+            user: self.user.clone(),
+            limits: self.limits.clone(),
+            network: self.network.clone(),
+            mounts: self.mounts.clone(),
+            // The container starts in the working directory, so the commands
+            // are run as-is.
+            command: cmds.join(" && "),
+        };
 
-        Ok(output)
-    }
+        // Services are started on a network private to this run, where the
+        // container reaches them by name, and are torn down afterwards.
+        let engine = services::Docker {
+            name: runner.name.clone(),
+        };
+        let stack = if self.services.is_empty() {
+            None
+        } else {
+            let network = format!("{}-net", runner.container);
+            let stack = ServiceStack::up(&engine, &network, &self.services)?;
+            runner.network.mode = Some(network);
+            Some(stack)
+        };
 
-    fn _run(cmd: &CommandConfig, path: &str, target_dir: &str) -> BifrostResult<OperationInfo> {
-        match cmd.get_cmds() {
-            Some(c) => Ok(RunSpace::_run_process(c, path, target_dir)?),
-            _ => failure::bail!("bail for now  could not get cmds from command config... [FIX]"),
+        let result = runner.run();
+        if let Some(Err(e)) = stack.map(|s| s.down()) {
+            // Leftover services do not invalidate the run's output.
+            io::stderr().write_fmt(format_args!("warn: {}\n", e))?;
         }
-    }
-
-    fn _run_process(
-        cmds: &Vec<String>,
-        path: &str,
-        target_dir: &str,
-    ) -> BifrostResult<OperationInfo> {
-        let mut process = Popen::create(
-            &[
-                "docker",
-                "run",
-                "--rm",
-                "-i",
-                "--volume",
-                path,
-                "bifrost:0.1",
-            ],
-            PopenConfig {
-                stdout: Redirection::Pipe,
-                stdin: Redirection::Pipe,
-                stderr: Redirection::Pipe,
-                ..Default::default()
-            },
-        )
-        .expect("Popen failure");
-
-        // [FIX ME] - this works right now, however, it is
-        // ugly and brittle.
-        let md_cmd = cmds.join(" && ");
-        let mounted_cmd = format!(
-            "bash -c \"cd /bifrost/bifrost; cd {}; {}; \"",
-            target_dir, md_cmd
-        );
+        let mut output = result?;
 
-        let _sp = SpinnerBuilder::new("Running...".into())
-            .spinner(vec![
-                "⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏",
-            ])
-            .start();
+        output.name = self
+            .name()
+            .unwrap_or(&String::from("run-default"))
+            .to_owned();
 
-        match process
-            .communicate(Some(&mounted_cmd))
-            .expect("communicate failure")
-        {
-            (Some(stdout), Some(stderr)) => Ok(OperationInfo {
-                stdout,
-                stderr,
-                ..Default::default()
-            }),
-            (Some(stdout), _) => Ok(OperationInfo {
-                stdout,
-                ..Default::default()
-            }),
-            (_, Some(stderr)) => Ok(OperationInfo {
-                stderr,
-                ..Default::default()
-            }),
-            (_, _) => Ok(OperationInfo {
-                // [FIX]
-                ..Default::default()
-            }),
+        // Secret values (including those of services) must never make it to
+        // the terminal.
+        let mut env = self.env.clone();
+        for service in &self.services {
+            env.extend(service.env.iter().cloned());
         }
+        Ok(output.redact(&Secrets::from_env(&env)))
     }
 }
 
@@ -826,11 +1536,75 @@ impl BifrostOperable for RunSpace {
         // Take the `command` from the `config`.
         self.cmd = WorkSpaceBuilder::take_command_config(&mut self.workspace.config);
 
+        // Resolve the environment (including secrets) to pass to the container.
+        self.env = self.workspace.config.env_vars()?;
+
+        // Resolve the user to run as so the workspace is not littered with
+        // root-owned files.
+        let user = self
+            .workspace
+            .config
+            .manifest()
+            .and_then(|m| m.get_container_config().and_then(|c| c.user()));
+        self.user = WorkSpaceBuilder::get_user(user, &|| docker::remaps_users("docker"));
+
+        // Resolve the resource limits so a runaway command cannot take down
+        // the host.
+        self.limits = WorkSpaceBuilder::get_limits(&self.workspace.config)?;
+        self.limits.validate()?;
+
+        // Resolve networking so hermetic runs can be forced offline and dev
+        // servers can be reached from the host.
+        self.network = WorkSpaceBuilder::get_network(&self.workspace.config);
+        self.network.validate()?;
+
+        // Resolve the image so realms do not have to share one.
+        let name = WorkSpaceBuilder::get_name(&self.workspace.config);
+        self.image = WorkSpaceBuilder::get_image(&self.workspace.config, &name)?;
+
+        // Resolve where the workspace is mounted and where commands run from.
+        let source = bifrost_path::get_path_or_empty(self.target.clone());
+        if source.as_os_str().is_empty() {
+            failure::bail!(
+                "error: failed to `run` {{{}}} are you sure you have called `bifrost load`?",
+                name
+            );
+        }
+        let mount = WorkSpaceBuilder::get_workspace_mount(&self.workspace.config, &name, source);
+        mount.validate()?;
+
+        // Resolve additional mounts; none of them may shadow the workspace.
+        self.mounts = WorkSpaceBuilder::get_mounts(&self.workspace.config, &name);
+        for m in &self.mounts {
+            m.validate(&mount.target)?;
+        }
+        self.mount = Some(mount);
+
+        // Resolve sidecar services; they share a network with the container,
+        // so the container cannot be offline or on the host's network.
+        self.services = self.workspace.config.services()?;
+        for service in &self.services {
+            service.validate()?;
+        }
+        if let (false, Some(mode)) = (self.services.is_empty(), self.network.mode.as_ref()) {
+            if mode != "bridge" {
+                failure::bail!(
+                    "error: services cannot be started when `network = \"{}\"`",
+                    mode
+                );
+            }
+        }
+
         Ok(self)
     }
 
-    /// Builds an `RunSpace`.
+    /// Builds an `RunSpace`, (re)building the realm's image if it is missing
+    /// or stale.
     fn build(&mut self) -> BifrostResult<&mut dyn BifrostOperable> {
+        let home_path = self.home_path().to_owned();
+        let name = WorkSpaceBuilder::get_name(&self.workspace.config);
+        self.image.ensure("docker", &home_path, &name)?;
+        self.check_lock()?;
         Ok(self)
     }
 
@@ -848,27 +1622,91 @@ impl BifrostOperable for RunSpace {
     }
 }
 
+/// Returns the unified diff of the file at `path` (relative to the workspace)
+/// between its `loaded` copy and its `source` in the realm; either may be
+/// missing.
+fn file_diff(
+    path: &Path,
+    loaded: Option<PathBuf>,
+    source: Option<PathBuf>,
+) -> BifrostResult<String> {
+    let read = |p: &Option<PathBuf>| -> BifrostResult<Vec<u8>> {
+        match p {
+            Some(p) => Ok(fs::read(p)?),
+            None => Ok(vec![]),
+        }
+    };
+    let (old, new) = (read(&loaded)?, read(&source)?);
+    let old_name = match loaded {
+        Some(_) => format!("a/{}", path.display()),
+        None => String::from("/dev/null"),
+    };
+    let new_name = match source {
+        Some(_) => format!("b/{}", path.display()),
+        None => String::from("/dev/null"),
+    };
+
+    if !diff::is_text(&old) || !diff::is_text(&new) {
+        return Ok(format!(
+            "Binary files {} and {} differ\n",
+            old_name, new_name
+        ));
+    }
+    Ok(diff::unified(
+        &String::from_utf8_lossy(&old),
+        &String::from_utf8_lossy(&new),
+        &old_name,
+        &new_name,
+    ))
+}
+
 /// `WorkSpaceBuilder` provides utility functions needed to construct `WorkSpaceArgs`.
 /// It is a no-field struct used to signal a division of labor/responsibility.
 pub struct WorkSpaceBuilder;
 
 impl WorkSpaceBuilder {
-    // Gets the ignore list from the manifest if it exists. Otherwise, an
-    // empty vector will be returned meaning that no files will be ignored in
-    // the `WorkSpace`.
+    // Gets the ignore list from the manifest if it exists. Otherwise, only
+    // the secrets file is ignored in the `WorkSpace`; secrets are passed to
+    // the container as environment variables and are never loaded.
     fn get_ignore_list(config: &Config) -> Vec<String> {
-        match config
+        let mut list = match config
             .manifest()
             .and_then(|m| m.get_workspace_config().and_then(|ws| ws.ignore()))
         {
             Some(list) => list.to_owned(),
             None => Vec::new(),
-        }
+        };
+        list.push(String::from(config::SECRETS_FILE));
+        list
+    }
+
+    // Returns the patterns of the files to load (all of them, if it is empty).
+    fn get_include_list(config: &Config) -> Vec<String> {
+        config
+            .manifest()
+            .and_then(|m| m.get_workspace_config().and_then(|ws| ws.include()))
+            .map_or(vec![], |list| list.to_owned())
+    }
+
+    // Returns whether or not the realm's `.gitignore` files are honored.
+    fn get_gitignore(config: &Config) -> bool {
+        config
+            .manifest()
+            .and_then(|m| m.get_workspace_config())
+            .is_none_or(|ws| ws.gitignore())
+    }
+
+    // Returns how the realm's symbolic links are loaded.
+    fn get_symlinks(config: &Config) -> Symlinks {
+        config
+            .manifest()
+            .and_then(|m| m.get_workspace_config())
+            .map_or(Symlinks::default(), |ws| ws.symlinks())
     }
 
     // Gets the name of the workspace if it exists; otherwise, the workspace
     // name is derived from the current working directory's top-level directory.
-    fn get_name(config: &Config) -> String {
+    pub fn get_name(config: &Config) -> String {
         const DEFAULT_TOML_NAME: &str = "workspace name";
 
         fn name_from_cwd(cwd: &PathBuf) -> String {
@@ -908,15 +1746,175 @@ impl WorkSpaceBuilder {
                 verbose: true,
                 ..Default::default()
             });
-        } else if args.is_present("diff") {
+        } else if args.is_present("diff") || args.is_present("patch") {
             return Some(BifrostOptions {
                 diff: true,
+                patch: args.is_present("patch"),
+                ..Default::default()
+            });
+        } else if args.is_present("ignored") {
+            return Some(BifrostOptions {
+                ignored: true,
                 ..Default::default()
             });
         }
         return None;
     }
 
+    /// Gets the user that commands are run as within the container.
+    ///
+    /// An explicit `[container] user` is used as-is, except `"root"` which
+    /// keeps the image's default user. Otherwise, on Linux, the invoking user's
+    /// `uid:gid` is used; docker accepts ids that have no entry in the image's
+    /// `/etc/passwd`, so no matching user has to be created. Elsewhere, or if
+    /// `remaps_users` finds the daemon runs rootless or with userns-remap
+    /// (where the invoking user's ids would map to a subordinate id on the
+    /// host), the container engine already maps file ownership and `None` is
+    /// returned.
+    fn get_user(user: Option<&str>, remaps_users: &dyn Fn() -> bool) -> Option<String> {
+        match user {
+            Some("root") => None,
+            Some(user) => Some(String::from(user)),
+            None if remaps_users() => None,
+            None => host_user(),
+        }
+    }
+
+    /// Gets the container resource limits from the manifest (and any command
+    /// line arguments it was combined with).
+    ///
+    /// # Errors
+    ///
+    /// If `--cpus` or `--pids-limit` were passed but are not numbers.
+    fn get_limits(config: &Config) -> BifrostResult<ResourceLimits> {
+        Ok(config
+            .manifest()
+            .and_then(|m| m.get_container_config())
+            .map(|c| c.limits())
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// Gets the container network options from the manifest (and any command
+    /// line arguments it was combined with).
+    fn get_network(config: &Config) -> NetworkOptions {
+        config
+            .manifest()
+            .and_then(|m| m.get_container_config())
+            .map(|c| c.network())
+            .unwrap_or_default()
+    }
+
+    /// Gets the image of the realm `name` from the manifest: either an
+    /// existing `image`, an image built from the realm's `dockerfile`, or the
+    /// default image built by `bifrost setup`.
+    ///
+    /// # Errors
+    ///
+    /// If both `image` and `dockerfile` are set, if the Dockerfile cannot be
+    /// read, or if the build context is not a directory, then this function
+    /// returns an error.
+    fn get_image(config: &Config, name: &str) -> BifrostResult<RealmImage> {
+        let container = match config.manifest().and_then(|m| m.get_container_config()) {
+            Some(container) => container,
+            None => return Ok(RealmImage::Default),
+        };
+
+        match (container.image(), container.dockerfile()) {
+            (Some(_), Some(_)) => failure::bail!(
+                "error: `[container]` can set either `image` or `dockerfile`, but not both"
+            ),
+            (Some(image), None) => Ok(RealmImage::Image(String::from(image))),
+            (None, Some(dockerfile)) => {
+                let path = config.cwd().join(dockerfile);
+                let contents = match fs::read(&path) {
+                    Ok(contents) => contents,
+                    Err(e) => failure::bail!(
+                        "error: could not read dockerfile `{}` due to {}",
+                        path.display(),
+                        e
+                    ),
+                };
+                // Only the Dockerfile's directory is sent to the engine,
+                // unless the manifest says otherwise.
+                let context = match container.context() {
+                    Some(context) => config.cwd().join(context),
+                    None => path.parent().unwrap_or(config.cwd()).to_path_buf(),
+                };
+                if !context.is_dir() {
+                    failure::bail!(
+                        "error: the build context `{}` is not a directory",
+                        context.display()
+                    );
+                }
+                Ok(RealmImage::Dockerfile {
+                    tag: docker::realm_image_tag(name, &contents),
+                    path,
+                    context,
+                })
+            }
+            (None, None) => Ok(RealmImage::Default),
+        }
+    }
+
+    /// Gets the mount of the workspace at `source` from the manifest. Unless
+    /// `[container] mount_point` is set, the workspace is mounted at
+    /// `/bifrost/bifrost/<name>`.
+    fn get_workspace_mount(config: &Config, name: &str, source: PathBuf) -> WorkspaceMount {
+        let container = config.manifest().and_then(|m| m.get_container_config());
+        let target = match container.and_then(|c| c.mount_point()) {
+            Some(mount_point) => String::from(mount_point),
+            None => format!("/bifrost/bifrost/{}", name),
+        };
+
+        WorkspaceMount {
+            source,
+            target,
+            workdir: container.and_then(|c| c.workdir()).map(String::from),
+        }
+    }
+
+    /// Gets the additional container mounts from the manifest. Bind sources
+    /// may start with `~` or be relative to the current working directory and
+    /// named volumes are keyed by the realm `name`.
+    fn get_mounts(config: &Config, name: &str) -> Vec<Mount> {
+        let mounts = match config.manifest().and_then(|m| m.get_container_config()) {
+            Some(container) => container.mounts(),
+            None => return Vec::new(),
+        };
+
+        mounts
+            .iter()
+            .map(|mount| match mount {
+                MountConfig::Bind {
+                    source,
+                    target,
+                    read_only,
+                } => {
+                    let source = match (source.strip_prefix("~/"), dirs::home_dir()) {
+                        (Some(rest), Some(home)) => home.join(rest),
+                        _ => config.cwd().join(source),
+                    };
+                    Mount::Bind {
+                        source,
+                        target: target.to_owned(),
+                        read_only: *read_only,
+                    }
+                }
+                MountConfig::Volume {
+                    name: volume,
+                    target,
+                    read_only,
+                } => Mount::Volume {
+                    name: docker::volume_name(name, volume),
+                    realm: String::from(name),
+                    target: target.to_owned(),
+                    read_only: *read_only,
+                },
+            })
+            .collect()
+    }
+
     /// `take`'s the `command_config` from the `Config`.
     fn take_command_config(config: &mut Config) -> Option<CommandConfig> {
         config.manifest_mut().and_then(|m| m.take_command_config())
@@ -947,6 +1945,19 @@ impl WorkSpaceBuilder {
     }
 }
 
+// Returns the invoking user's `uid:gid`.
+#[cfg(target_os = "linux")]
+fn host_user() -> Option<String> {
+    // These calls are always successful.
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    Some(format!("{}:{}", uid, gid))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn host_user() -> Option<String> {
+    None
+}
+
 // Collects `names` that can be considered valid paths for a given operation. If
 // the `BifrostResult` returned by `f` cannot be unwrapped then the value an `Ok`
 // result would have contained is considered to be false.
@@ -1096,6 +2107,89 @@ mod test {
         assert_eq!(left.len(), right.len());
     }
 
+    #[test]
+    fn test_get_user() {
+        let rootful = || false;
+        let remapped = || true;
+
+        // `root` keeps the image's default user.
+        assert_eq!(None, WorkSpaceBuilder::get_user(Some("root"), &rootful));
+        assert_eq!(None, WorkSpaceBuilder::get_user(Some("root"), &remapped));
+
+        // An explicit user is used as-is.
+        let user = Some(String::from("1000:1000"));
+        assert_eq!(
+            user,
+            WorkSpaceBuilder::get_user(Some("1000:1000"), &rootful)
+        );
+        assert_eq!(
+            user,
+            WorkSpaceBuilder::get_user(Some("1000:1000"), &remapped)
+        );
+
+        // The default is the invoking user, unless the daemon remaps users.
+        assert_eq!(host_user(), WorkSpaceBuilder::get_user(None, &rootful));
+        assert_eq!(None, WorkSpaceBuilder::get_user(None, &remapped));
+        #[cfg(target_os = "linux")]
+        {
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            assert_eq!(
+                Some(format!("{}:{}", uid, gid)),
+                WorkSpaceBuilder::get_user(None, &rootful)
+            );
+        }
+    }
+
+    #[test]
+    fn test_verify() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-verify-{}", process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let home = tmp.join("home");
+        let target = BifrostPath {
+            path: tmp.join("container"),
+        };
+        let loaded = target.path.join("realm");
+        fs::create_dir_all(&loaded)?;
+        fs::write(loaded.join("intact.txt"), "intact")?;
+        fs::write(loaded.join("changed.txt"), "changed")?;
+        fs::write(loaded.join("gone.txt"), "gone")?;
+
+        let mut meta = WorkSpaceMeta::default();
+        for name in &["intact.txt", "changed.txt", "gone.txt"] {
+            let checksum = hash::file_sha256(&loaded.join(name))?;
+            meta.files.insert(format!("realm/{}", name), checksum);
+        }
+        meta.save(&home, "realm")?;
+
+        let verify = VerifySpace {
+            workspace: WorkSpace {
+                name: Some(String::from("realm")),
+                config: Config::new(Some(home.clone()), Some(tmp.join("realm"))),
+                ..Default::default()
+            },
+            target: Some(target),
+        };
+        let op_info = verify.verify()?;
+        assert_eq!(Some(b"".to_vec()), op_info.text);
+        assert_eq!(Some(17), op_info.bytes);
+
+        fs::write(loaded.join("changed.txt"), "CHANGED")?;
+        fs::remove_file(loaded.join("gone.txt"))?;
+        let op_info = verify.verify()?;
+        assert_eq!(
+            Some(b"modified: realm/changed.txt\nmissing:  realm/gone.txt\n".to_vec()),
+            op_info.text
+        );
+        assert_eq!(Some(6), op_info.bytes);
+
+        // Workspaces loaded before checksums were recorded are not verified.
+        WorkSpaceMeta::default().save(&home, "realm")?;
+        assert!(verify.verify().is_err());
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
     // figure out how to run this test
     fn _test_is_loadable() -> BifrostResult<()> {
         let current_dir = std::env::current_dir()?;
@@ -0,0 +1,41 @@
+//! Implementation details of the `cache` subcommand.
+use std::io::{self, Write};
+
+use crate::core::config::Config;
+use crate::core::workspace::WorkSpaceBuilder;
+use crate::util::{docker, BifrostResult};
+use crate::ArgMatches;
+
+pub fn cache(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    match args.subcommand() {
+        ("clear", Some(clear_args)) => clear(config, clear_args),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the named cache volumes of the current realm (or of every realm if
+/// `--all` was passed).
+fn clear(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let realm = if args.is_present("all") {
+        None
+    } else {
+        Some(WorkSpaceBuilder::get_name(&config))
+    };
+
+    let volumes = docker::list_volumes("docker", realm.as_deref())?;
+    for volume in &volumes {
+        docker::remove_volume("docker", volume)?;
+        io::stdout().write_fmt(format_args!("bifrost: removed volume `{}`\n", volume))?;
+    }
+
+    let scope = match realm {
+        Some(name) => format!("realm `{}`", name),
+        None => String::from("all realms"),
+    };
+    io::stdout().write_fmt(format_args!(
+        "bifrost: cleared {} cache volume(s) of {}\n",
+        volumes.len(),
+        scope
+    ))?;
+    Ok(())
+}
@@ -0,0 +1,243 @@
+//! Implementation details of the `image` subcommand.
+use std::fs;
+use std::io::{self, Write};
+
+use crate::core::config::Config;
+use crate::core::hofund;
+use crate::util::docker;
+use crate::util::dockerfile::{self, Dockerfile};
+use crate::util::inventory::{self, Inventory};
+use crate::util::BifrostResult;
+use crate::ArgMatches;
+
+pub fn image(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    match args.subcommand() {
+        ("add", Some(add_args)) => add(config, add_args),
+        ("remove", Some(remove_args)) => remove(config, remove_args),
+        ("list", Some(_)) => list(config),
+        ("rebuild", Some(rebuild_args)) => rebuild(config, rebuild_args),
+        ("inventory", Some(_)) => list_images(config),
+        ("prune", Some(prune_args)) => prune(config, prune_args),
+        _ => Ok(()),
+    }
+}
+
+/// Adds packages to the bifrost Dockerfile and rebuilds the image if any were
+/// not already installed.
+fn add(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let packages = packages_of(args);
+    let mut dockerfile = read_dockerfile(&config)?;
+
+    let added = dockerfile.add(&packages)?;
+    if added.is_empty() {
+        io::stdout()
+            .write_all("bifrost: packages already installed; nothing to do\n".as_bytes())?;
+        return Ok(());
+    }
+
+    write_and_rebuild(&config, &dockerfile)?;
+    io::stdout().write_fmt(format_args!(
+        "bifrost: successfully added {}\n",
+        added.join(" ")
+    ))?;
+    Ok(())
+}
+
+/// Removes packages from the bifrost Dockerfile and rebuilds the image if any
+/// were installed.
+fn remove(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let packages = packages_of(args);
+    let mut dockerfile = read_dockerfile(&config)?;
+
+    let removed = dockerfile.remove(&packages)?;
+    if removed.is_empty() {
+        io::stdout().write_all("bifrost: packages not installed; nothing to do\n".as_bytes())?;
+        return Ok(());
+    }
+
+    write_and_rebuild(&config, &dockerfile)?;
+    io::stdout().write_fmt(format_args!(
+        "bifrost: successfully removed {}\n",
+        removed.join(" ")
+    ))?;
+    Ok(())
+}
+
+/// Lists the packages installed through `bifrost image add`.
+fn list(config: Config) -> BifrostResult<()> {
+    let dockerfile = read_dockerfile(&config)?;
+    for package in dockerfile.packages() {
+        io::stdout().write_fmt(format_args!("{}\n", package))?;
+    }
+    Ok(())
+}
+
+/// Rebuilds the bifrost image if its Dockerfile or build context changed since
+/// it was last built (or unconditionally with `--force`).
+fn rebuild(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let image = docker::default_image(config.home_path())?;
+    if image.rebuild(config.home_path(), args.is_present("force"))? {
+        io::stdout().write_fmt(format_args!(
+            "bifrost: successfully rebuilt image `{}`\n",
+            image.tag
+        ))?;
+    } else {
+        io::stdout().write_fmt(format_args!(
+            "bifrost: image `{}` is up to date\n",
+            image.tag
+        ))?;
+    }
+    Ok(())
+}
+
+/// Lists the images bifrost has built along with their sizes.
+fn list_images(config: Config) -> BifrostResult<()> {
+    let inventory = Inventory::load(config.home_path())?;
+
+    let mut stdout = io::stdout();
+    stdout.write_fmt(format_args!(
+        "{:<32} {:<14} {:>10}  {:<19}  {}\n",
+        "TAG", "DIGEST", "SIZE", "BUILT", "REALMS"
+    ))?;
+    for (tag, record) in inventory.images() {
+        let size = match docker::image_details("docker", tag) {
+            Some(details) => inventory::format_size(details.size),
+            None => String::from("missing"),
+        };
+        let digest = record.digest.trim_start_matches("sha256:");
+        stdout.write_fmt(format_args!(
+            "{:<32} {:<14} {:>10}  {:<19}  {}\n",
+            tag,
+            &digest[..digest.len().min(12)],
+            size,
+            inventory::format_timestamp(record.built),
+            record.realms.join(", ")
+        ))?;
+    }
+    Ok(())
+}
+
+/// Removes the images bifrost has built that no loaded realm uses. The image
+/// built by `bifrost setup` is always kept.
+fn prune(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let dry_run = args.is_present("dry-run");
+    let home_path = config.home_path();
+    let mut inventory = Inventory::load(home_path)?;
+
+    let workspaces = home_path.join(".bifrost").join("container").join("bifrost");
+    let is_live = |realm: &str| fs::metadata(workspaces.join(realm)).is_ok();
+    let tags = inventory.unreferenced(&[docker::DEFAULT_IMAGE], is_live);
+
+    let mut freed = 0;
+    let mut removed = 0;
+
+    // Rebuilds leave the (now untagged) images they replaced behind.
+    let superseded: Vec<(String, String)> = inventory
+        .images()
+        .iter()
+        .flat_map(|(tag, record)| {
+            record
+                .superseded
+                .iter()
+                .map(move |id| (tag.clone(), id.clone()))
+        })
+        .collect();
+    for (tag, id) in &superseded {
+        let details = docker::image_details("docker", id);
+        let size = details.as_ref().map_or(0, |d| d.size);
+        let short = id.trim_start_matches("sha256:");
+        let short = &short[..short.len().min(12)];
+
+        if dry_run {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: would remove image {} replaced by `{}` ({})\n",
+                short,
+                tag,
+                inventory::format_size(size)
+            ))?;
+        } else {
+            if details.is_some() {
+                if let Err(e) = docker::remove_image("docker", id) {
+                    io::stderr().write_fmt(format_args!("{}\n", e))?;
+                    continue;
+                }
+            }
+            inventory.forget_superseded(tag, id);
+            io::stdout().write_fmt(format_args!(
+                "bifrost: removed image {} replaced by `{}`\n",
+                short, tag
+            ))?;
+        }
+        freed += size;
+        removed += 1;
+    }
+
+    for tag in &tags {
+        let details = docker::image_details("docker", tag);
+        let size = details.as_ref().map_or(0, |d| d.size);
+
+        if dry_run {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: would remove image `{}` ({})\n",
+                tag,
+                inventory::format_size(size)
+            ))?;
+        } else {
+            // Images already gone from the engine only need to be forgotten.
+            if details.is_some() {
+                if let Err(e) = docker::remove_image("docker", tag) {
+                    io::stderr().write_fmt(format_args!("{}\n", e))?;
+                    continue;
+                }
+            }
+            inventory.remove(tag);
+            io::stdout().write_fmt(format_args!("bifrost: removed image `{}`\n", tag))?;
+        }
+        freed += size;
+        removed += 1;
+    }
+
+    if dry_run {
+        io::stdout().write_fmt(format_args!(
+            "bifrost: would remove {} image(s) freeing {}\n",
+            removed,
+            inventory::format_size(freed)
+        ))?;
+    } else {
+        inventory.save(home_path)?;
+        io::stdout().write_fmt(format_args!(
+            "bifrost: removed {} image(s) freeing {}\n",
+            removed,
+            inventory::format_size(freed)
+        ))?;
+    }
+    Ok(())
+}
+
+fn packages_of(args: &ArgMatches) -> Vec<String> {
+    args.values_of("packages")
+        .map(|values| values.map(String::from).collect())
+        .unwrap_or_default()
+}
+
+fn read_dockerfile(config: &Config) -> BifrostResult<Dockerfile> {
+    let path = dockerfile::global_path(config.home_path());
+    Ok(Dockerfile::new(hofund::read(&path)?))
+}
+
+/// Writes the edited Dockerfile and rebuilds the image from it. If the build
+/// fails, the previous Dockerfile is restored so that it keeps describing the
+/// image that actually exists.
+fn write_and_rebuild(config: &Config, dockerfile: &Dockerfile) -> BifrostResult<()> {
+    let path = dockerfile::global_path(config.home_path());
+    let previous = hofund::read(&path)?;
+    hofund::write(&path, dockerfile.contents().as_bytes())?;
+
+    let rebuilt = docker::default_image(config.home_path())
+        .and_then(|image| image.rebuild(config.home_path(), false));
+    if let Err(e) = rebuilt {
+        hofund::write(&path, previous.as_bytes())?;
+        return Err(e);
+    }
+    Ok(())
+}
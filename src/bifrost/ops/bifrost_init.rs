@@ -4,7 +4,8 @@ use std::io::{self, Write};
 use std::path::PathBuf;
 use std::process;
 
-use crate::core::config::Config;
+use crate::core::config::{self, BifrostManifest, Config};
+use crate::core::devcontainer::DevContainer;
 use crate::core::hofund;
 use crate::util::BifrostResult;
 use crate::ArgMatches;
@@ -62,6 +63,10 @@ pub fn init(config: Config, args: &ArgMatches) -> BifrostResult<()> {
         Ok(())
     };
 
+    if args.is_present("from-devcontainer") {
+        return init_from_devcontainer(config, args);
+    }
+
     if args.args.is_empty() {
         let name = name_work_space(config.cwd())?;
         let toml = create_toml_with(name);
@@ -89,6 +94,38 @@ pub fn init(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     Ok(())
 }
 
+/// Initializes a Bifrost realm from the realm's `devcontainer.json` (or the
+/// one passed with `--from-devcontainer=<path>`), reporting the fields that
+/// could not be translated.
+fn init_from_devcontainer(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let path = match config::value_of("from-devcontainer", args) {
+        Some(path) => config.cwd().join(path),
+        None => DevContainer::find(config.cwd())?,
+    };
+    let devcontainer = DevContainer::from_path(&path, config.cwd())?;
+
+    for field in &devcontainer.unmapped {
+        io::stderr().write_fmt(format_args!("warn: skipped {}\n", field))?;
+    }
+
+    let name = match config::value_of("workspace", args) {
+        Some(name) => name,
+        None => String::from(name_work_space(config.cwd())?),
+    };
+    let manifest = BifrostManifest::from_devcontainer(&name, devcontainer).combine_with(args);
+    hofund::write(
+        &config.cwd().join("Bifrost.toml"),
+        manifest.to_str()?.as_bytes(),
+    )?;
+
+    io::stdout().write_fmt(format_args!(
+        "initialized bifrost realm in {} from {}\n",
+        config.cwd().display(),
+        path.display()
+    ))?;
+    Ok(())
+}
+
 fn name_work_space(from_cwd: &PathBuf) -> BifrostResult<&str> {
     match from_cwd.file_name() {
         Some(os_str) => match os_str.to_str() {
@@ -3,6 +3,7 @@ use std::io::{self, Write};
 
 use crate::core::config::Config;
 use crate::core::workspace::{BifrostOperable, WorkSpace};
+use crate::ops::bifrost_run;
 use crate::util::{BifrostResult, OperationInfo};
 use crate::ArgMatches;
 
@@ -11,18 +12,82 @@ use crate::ArgMatches;
 /// by a `BifrostPath`. Here, **load** is synonymous with **copy**.
 pub fn load(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     let success = |op_info: &OperationInfo| -> BifrostResult<()> {
-        io::stdout().write_fmt(format_args!(
-            "bifrost: loaded {{{}}} bytes from realm {{{}}}\n",
-            op_info.bytes.unwrap(),
-            op_info.name,
-        ))?;
+        io::stderr().write_all(op_info.stderr.as_bytes())?;
+        match op_info.text {
+            // A modified load reports what it updated.
+            Some(ref summary) => io::stdout().write_fmt(format_args!(
+                "bifrost: reloaded realm {{{}}}: {}\n",
+                op_info.name,
+                String::from_utf8_lossy(summary),
+            ))?,
+            None => io::stdout().write_fmt(format_args!(
+                "bifrost: loaded {{{}}} bytes from realm {{{}}}\n",
+                op_info.bytes.unwrap(),
+                op_info.name,
+            ))?,
+        }
+        Ok(())
+    };
+
+    // Re-runs the realm's `[command]` (with `--run`) after an auto load.
+    let run = || -> BifrostResult<()> {
+        if args.is_present("run") {
+            if let Err(e) = bifrost_run::run(Config::default().config_manifest(args), args) {
+                io::stderr().write_fmt(format_args!("{}\n", e))?;
+            }
+        }
         Ok(())
     };
 
-    let op_info = WorkSpace::to_load_space(config, &args)
-        .prep()?
-        .build()?
-        .exec()?;
+    let mut space = WorkSpace::to_load_space(config, args);
+
+    // A dry run only walks the realm and reports the plan.
+    if args.is_present("dry-run") {
+        space.prep()?.build()?;
+        let plan = space.plan()?;
+        if args.is_present("json") {
+            io::stdout().write_fmt(format_args!("{}\n", plan.to_json()?))?;
+            return Ok(());
+        }
 
-    return Ok(success(&op_info)?);
+        io::stdout().write_fmt(format_args!(
+            "bifrost: dry run of loading realm {{{}}} (nothing is copied)\n{}",
+            plan.name, plan
+        ))?;
+        if plan.loaded {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: realm {{{}}} is already loaded (use `bifrost reload` to replace it)\n",
+                plan.name
+            ))?;
+        }
+        return Ok(());
+    }
+
+    let op_info = space.prep()?.build()?.exec()?;
+    success(&op_info)?;
+
+    if args.is_present("auto") {
+        run()?;
+        io::stdout().write_fmt(format_args!(
+            "bifrost: watching realm {{{}}} for changes (press Ctrl-C to stop)\n",
+            op_info.name
+        ))?;
+
+        // Failed reloads are reported but do not stop the watch.
+        space.watch(|result| {
+            match result {
+                Ok(op_info) => {
+                    success(&op_info)?;
+                    run()?;
+                }
+                Err(e) => io::stderr().write_fmt(format_args!("{}\n", e))?,
+            }
+            Ok(())
+        })?;
+        io::stdout().write_fmt(format_args!(
+            "bifrost: stopped watching realm {{{}}}\n",
+            op_info.name
+        ))?;
+    }
+    Ok(())
 }
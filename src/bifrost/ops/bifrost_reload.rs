@@ -0,0 +1,28 @@
+//! Implementation details of the `reload` subcommand.
+use std::io::{self, Write};
+
+use crate::core::config::Config;
+use crate::core::workspace::{BifrostOperable, WorkSpace};
+use crate::util::{BifrostResult, OperationInfo};
+use crate::ArgMatches;
+
+/// Reloads a Bifrost Workspace. The workspace is loaded into a staging
+/// directory next to the loaded workspace, which it then replaces. Unlike
+/// `load --modified`, everything is copied anew.
+pub fn reload(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let success = |op_info: OperationInfo| -> BifrostResult<()> {
+        io::stderr().write_all(op_info.stderr.as_bytes())?;
+        io::stdout().write_fmt(format_args!(
+            "bifrost: reloaded {{{}}} bytes from realm {{{}}}\n",
+            op_info.bytes.unwrap(),
+            op_info.name,
+        ))?;
+        Ok(())
+    };
+
+    let op_info = WorkSpace::to_reload_space(config, args)
+        .prep()?
+        .build()?
+        .exec()?;
+    success(op_info)
+}
@@ -1,19 +1,44 @@
 //! Implementation details of the `run` subcommand.
 use std::fs;
 use std::io::{self, Write};
-use std::process;
 
 use crate::core::config::Config;
 use crate::core::workspace::{BifrostOperable, WorkSpace};
+use crate::util::operation_info::RunStatus;
 use crate::util::{bifrost_path, BifrostResult, OperationInfo};
 use crate::ArgMatches;
 
-pub fn run(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+/// Runs the realm's commands in its container and reports their output.
+/// Returns the status of the run (if it ran), so that callers decide what a
+/// failed run means (e.g. `bifrost run` exits like it, while `bifrost load
+/// --auto --run` keeps watching).
+///
+/// # Errors
+///
+/// If the realm has not been loaded or its container cannot be run, then
+/// this function returns an error.
+pub fn run(config: Config, args: &ArgMatches) -> BifrostResult<Option<RunStatus>> {
     let success = |op_info: OperationInfo| -> BifrostResult<()> {
         io::stdout().write_fmt(format_args!(
             "bifrost: workspace realm {{{}}}\nstdout:\n{}\nstderr:\n{}",
             op_info.name, op_info.stdout, op_info.stderr,
         ))?;
+
+        // Only call out runs that did not succeed; limit exceeded terminations
+        // are reported distinctly.
+        match op_info.status {
+            Some(RunStatus::OutOfMemory) => {
+                io::stdout().write_all(
+                    "\nbifrost: run was killed: memory limit exceeded \
+                     (see `[container] memory` or `--memory`)\n"
+                        .as_bytes(),
+                )?;
+            }
+            Some(status) if !status.success() => {
+                io::stdout().write_fmt(format_args!("\nbifrost: run {}\n", status))?;
+            }
+            _ => {}
+        }
         Ok(())
     };
 
@@ -31,13 +56,15 @@ pub fn run(config: Config, args: &ArgMatches) -> BifrostResult<()> {
         // [TODO] search similar path names and attempt to resolve manifest
         // modifications.
         let path = bifrost_path::handle_bad_path(path);
-        io::stdout().write_fmt(format_args!(
-            "failed: to `run` {{{}}} are you sure you have called `bifrost load`?\n",
+        failure::bail!(
+            "error: failed to `run` {{{}}} are you sure you have called `bifrost load`?",
             path
-        ))?;
-        process::exit(1);
+        );
     }
 
     let op_info = ws.build()?.exec()?;
-    return Ok(success(op_info)?);
+    let status = op_info.status;
+    success(op_info)?;
+    io::stdout().flush()?;
+    Ok(status)
 }
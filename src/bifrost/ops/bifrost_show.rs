@@ -4,17 +4,34 @@ use std::io::{self, Write};
 use std::process;
 
 use crate::core::config::Config;
+use crate::core::lock::Lock;
+use crate::core::meta::WorkSpaceMeta;
 use crate::core::workspace::{BifrostOperable, WorkSpace};
 use crate::util::{bifrost_path, BifrostResult, OperationInfo};
 use crate::ArgMatches;
 
 pub fn show(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let lock = Lock::load(config.cwd())?;
+    let home_path = config.home_path().clone();
     let success = |op_info: OperationInfo| -> BifrostResult<()> {
         io::stdout().write_fmt(format_args!(
             "bifrost: workspace realm {{{}}} {}",
             op_info.name,
             String::from_utf8_lossy(&op_info.text.unwrap())
         ))?;
+        if let Some(loaded) = WorkSpaceMeta::load(&home_path, &op_info.name)?
+            .as_ref()
+            .and_then(WorkSpaceMeta::describe)
+        {
+            io::stdout().write_fmt(format_args!("bifrost: {}\n", loaded))?;
+        }
+        if let Some(ref lock) = lock {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: pinned image `{}` ({})\n",
+                lock.image.tag,
+                lock.image.pin()
+            ))?;
+        }
         Ok(())
     };
 
@@ -27,8 +44,9 @@ pub fn show(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     // Get the `BifrostPath`'s underlying `PathBuf`.
     let path = bifrost_path::get_path_or_empty(ws.target());
 
-    // If the path does not exist, then...
-    if fs::metadata(&path).is_err() {
+    // If the path does not exist (which only matters if the workspace is
+    // shown), then...
+    if !args.is_present("ignored") && fs::metadata(&path).is_err() {
         // [TODO] search similar path names and attempt to resolve manifest
         // modifications.
         let path = bifrost_path::handle_bad_path(path);
@@ -40,5 +58,42 @@ pub fn show(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     }
 
     let op_info = ws.build()?.exec()?;
+
+    // Differences are reported with a non-zero exit so that `--diff` can
+    // tell whether a load is stale.
+    if args.is_present("diff") || args.is_present("patch") {
+        let text = op_info.text.unwrap_or_default();
+        if text.is_empty() {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: workspace realm {{{}}} is up to date\n",
+                op_info.name
+            ))?;
+            return Ok(());
+        }
+        io::stdout().write_fmt(format_args!(
+            "bifrost: workspace realm {{{}}} differs from the loaded workspace\n",
+            op_info.name
+        ))?;
+        io::stdout().write_all(&text)?;
+        process::exit(1);
+    }
+
+    if args.is_present("ignored") {
+        let text = op_info.text.unwrap_or_default();
+        if text.is_empty() {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: realm {{{}}} excludes nothing from being loaded\n",
+                op_info.name
+            ))?;
+        } else {
+            io::stdout().write_fmt(format_args!(
+                "bifrost: realm {{{}}} excludes the following from being loaded\n",
+                op_info.name
+            ))?;
+            io::stdout().write_all(&text)?;
+        }
+        return Ok(());
+    }
+
     return Ok(success(op_info)?);
 }
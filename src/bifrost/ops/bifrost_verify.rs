@@ -0,0 +1,51 @@
+//! Implementation details of the `verify` subcommand.
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+use crate::core::config::Config;
+use crate::core::workspace::{BifrostOperable, WorkSpace};
+use crate::util::{bifrost_path, BifrostResult};
+use crate::ArgMatches;
+
+/// Verifies a loaded Bifrost Workspace: each loaded file is hashed and
+/// compared with the checksum recorded when it was loaded. Files that are
+/// missing or differ are reported with a non-zero exit.
+pub fn verify(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    // Construct the `VerifySpace`.
+    let mut ws = WorkSpace::to_verify_space(config, args);
+
+    // Prepare the `VerifySpace`.
+    let ws = ws.prep()?;
+
+    // Get the `BifrostPath`'s underlying `PathBuf`.
+    let path = bifrost_path::get_path_or_empty(ws.target());
+
+    // If the path does not exist, then...
+    if fs::metadata(&path).is_err() {
+        let path = bifrost_path::handle_bad_path(path);
+        io::stdout().write_fmt(format_args!(
+            "failed: to `verify` {{{}}} are you sure you have called `bifrost load`?\n",
+            path
+        ))?;
+        process::exit(1);
+    }
+
+    let op_info = ws.build()?.exec()?;
+    let text = op_info.text.unwrap_or_default();
+    if text.is_empty() {
+        io::stdout().write_fmt(format_args!(
+            "bifrost: verified {{{}}} bytes of workspace realm {{{}}}\n",
+            op_info.bytes.unwrap_or(0),
+            op_info.name
+        ))?;
+        return Ok(());
+    }
+
+    io::stdout().write_fmt(format_args!(
+        "bifrost: workspace realm {{{}}} does not match what was loaded\n",
+        op_info.name
+    ))?;
+    io::stdout().write_all(&text)?;
+    process::exit(1);
+}
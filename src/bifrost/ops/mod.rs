@@ -1,5 +1,9 @@
+pub mod bifrost_cache;
+pub mod bifrost_image;
 pub mod bifrost_init;
 pub mod bifrost_load;
+pub mod bifrost_reload;
 pub mod bifrost_run;
 pub mod bifrost_show;
 pub mod bifrost_unload;
+pub mod bifrost_verify;
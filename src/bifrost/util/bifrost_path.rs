@@ -57,7 +57,7 @@ impl BifrostPath {
             io::stderr().write_fmt(format_args!(
                 "error: the proposed path already exists:
   `{}`
-hint: did you mean to reload?
+hint: did you mean `bifrost reload` (or `bifrost load --modified`)?
 ",
                 path
             ))?;
@@ -82,7 +82,12 @@ hint: did you mean to reload?
         })
     }
 
-    fn from_existing<P>(home_path: P, name: Option<&String>) -> BifrostResult<Self>
+    /// Constructs a `BifrostPath` to an existing workspace.
+    ///
+    /// # Errors
+    ///
+    /// If the workspace does not exist, then this function returns an error.
+    pub fn from_existing<P>(home_path: P, name: Option<&String>) -> BifrostResult<Self>
     where
         P: AsRef<Path>,
     {
@@ -103,6 +108,71 @@ hint: did you mean to reload?
         Ok(BifrostPath { path })
     }
 
+    /// Constructs a `BifrostPath` to the staging directory a workspace is
+    /// reloaded into (`.<name>.staging` next to the workspace) before it
+    /// replaces the workspace, see [`replace`](#method.replace).
+    pub fn staging<P>(home_path: P, name: Option<&String>) -> BifrostResult<Self>
+    where
+        P: AsRef<Path>,
+    {
+        BifrostPath::check(name)?;
+        let name = name.expect("BUG: `WorkSpace::name` should not be `None` here");
+
+        let path = home_path
+            .as_ref()
+            .join(DOT_BIFROST)
+            .join(CONTAINER)
+            .join(BIFROST_CONTAINER)
+            .join(format!(".{}.staging", name));
+
+        Ok(BifrostPath { path })
+    }
+
+    /// Moves this (fully loaded) staging directory into place as the workspace
+    /// `name` and removes the copy it replaces (if any). The workspace is
+    /// swapped with a rename, so it is either the old copy or the new one but
+    /// never a partially loaded one.
+    ///
+    /// # Errors
+    ///
+    /// If the staging directory cannot be moved into place, then this method
+    /// returns an error and the existing workspace is left as it was.
+    pub fn replace(self, name: Option<&String>) -> BifrostResult<BifrostPath> {
+        BifrostPath::check(name)?;
+        let name = name.expect("BUG: `WorkSpace::name` should not be `None` here");
+        let path = self
+            .path
+            .parent()
+            .expect("BUG: a staging `BifrostPath` should have a parent")
+            .join(name);
+
+        if fs::symlink_metadata(&path).is_err() {
+            if let Err(e) = fs::rename(&self.path, &path) {
+                failure::bail!(
+                    "error: could not move reloaded workspace into place due to {}",
+                    e
+                );
+            }
+            return Ok(BifrostPath { path });
+        }
+
+        // The old copy ends up in the staging directory.
+        if let Err(e) = exchange(&self.path, &path) {
+            failure::bail!(
+                "error: could not move reloaded workspace into place due to {}",
+                e
+            );
+        }
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            failure::bail!(
+                "error: reloaded workspace but could not remove its old copy `{}` due to {}",
+                self.path.display(),
+                e
+            );
+        }
+        Ok(BifrostPath { path })
+    }
+
     /// Checks that the `proposed_name` does not contain any black-listed names.
     fn check(proposed_name: Option<&String>) -> BifrostResult<()> {
         let black_list: Vec<&str> = vec![
@@ -126,6 +196,49 @@ hint: did you mean to reload?
     }
 }
 
+/// Atomically exchanges the directories `a` and `b` (both must exist).
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let to_c = |p: &Path| {
+        CString::new(p.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    };
+    let (a, b) = (to_c(a)?, to_c(b)?);
+    let result = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Exchanges the directories `a` and `b` (both must exist) with two renames;
+/// `b` is briefly missing in between, but never partially populated.
+#[cfg(not(target_os = "linux"))]
+fn exchange(a: &Path, b: &Path) -> io::Result<()> {
+    let mut aside = a.as_os_str().to_os_string();
+    aside.push(".old");
+    let aside = PathBuf::from(aside);
+
+    fs::rename(b, &aside)?;
+    if let Err(e) = fs::rename(a, b) {
+        // Put the old copy back.
+        fs::rename(&aside, b)?;
+        return Err(e);
+    }
+    fs::rename(&aside, a)
+}
+
 pub fn get_path_or_empty(maybe_path: Option<BifrostPath>) -> PathBuf {
     maybe_path.map(|b| b.path).unwrap_or(PathBuf::new())
 }
@@ -143,3 +256,35 @@ pub fn handle_bad_path(path: PathBuf) -> String {
         None => String::from("None"),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_replace() -> BifrostResult<()> {
+        let home = env::temp_dir().join(format!("bifrost-path-{}", process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let name = String::from("realm");
+
+        // A first reload moves the staged workspace into place.
+        let staging = BifrostPath::staging(&home, Some(&name))?;
+        fs::create_dir_all(staging.path.join("realm"))?;
+        fs::write(staging.path.join("realm").join("a.txt"), "old")?;
+        let path = staging.replace(Some(&name))?;
+        assert_eq!("old", fs::read_to_string(path.path.join("realm/a.txt"))?);
+
+        // Later reloads replace it and remove the old copy.
+        let staging = BifrostPath::staging(&home, Some(&name))?;
+        fs::create_dir_all(staging.path.join("realm"))?;
+        fs::write(staging.path.join("realm").join("b.txt"), "new")?;
+        let path = staging.replace(Some(&name))?;
+        assert!(!path.path.join("realm/a.txt").exists());
+        assert_eq!("new", fs::read_to_string(path.path.join("realm/b.txt"))?);
+        assert!(!BifrostPath::staging(&home, Some(&name))?.path.exists());
+
+        fs::remove_dir_all(&home)?;
+        Ok(())
+    }
+}
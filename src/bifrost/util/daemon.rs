@@ -0,0 +1,375 @@
+//! Strategies for starting (and stopping) the container engine's daemon.
+//!
+//! How the daemon is started depends on the platform: on macOS the Docker
+//! application is opened in the background, on Linux the daemon is started
+//! through its systemd unit (a user unit first, e.g. rootless docker, then the
+//! system unit) or, failing that, with `dockerd-rootless.sh`.
+//!
+//! The strategy bifrost started the daemon with is recorded in
+//! `~/.bifrost/daemon.toml`, so that later invocations talk to (and stop) that
+//! daemon, e.g. the rootless daemon on the user's own socket.
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::core::hofund;
+use crate::util::{BifrostResult, ProcessBuilder};
+
+/// The environment variable naming the socket engine commands talk to.
+pub const HOST_VAR: &str = "DOCKER_HOST";
+
+/// The environment variable holding the number of seconds to wait for the
+/// daemon to start.
+pub const START_TIMEOUT_VAR: &str = "BIFROST_START_TIMEOUT";
+
+/// The number of seconds to wait for the daemon to start by default.
+const DEFAULT_START_TIMEOUT: u64 = 60;
+
+/// A way of starting the container engine's daemon.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "strategy", rename_all = "lowercase")]
+pub enum StartStrategy {
+    /// Open the Docker application in the background (macOS).
+    App,
+    /// Start the `docker` systemd unit of the user (`systemctl --user`) or of
+    /// the system.
+    Systemd { user: bool },
+    /// Run the rootless daemon with `dockerd-rootless.sh`.
+    Rootless,
+}
+
+impl StartStrategy {
+    /// Picks the strategy for the current platform.
+    #[cfg(target_os = "macos")]
+    pub fn detect() -> Option<StartStrategy> {
+        Some(StartStrategy::App)
+    }
+
+    /// Picks the strategy for the current platform.
+    #[cfg(target_os = "linux")]
+    pub fn detect() -> Option<StartStrategy> {
+        let has_unit = |user: bool| {
+            let mut args = vec![String::from("cat"), String::from("docker.service")];
+            if user {
+                args.insert(0, String::from("--user"));
+            }
+            let systemctl = ProcessBuilder {
+                program: String::from("systemctl"),
+                args,
+                cwd: None,
+            };
+            systemctl.exec().is_ok()
+        };
+
+        if has_unit(true) {
+            Some(StartStrategy::Systemd { user: true })
+        } else if has_unit(false) {
+            Some(StartStrategy::Systemd { user: false })
+        } else if in_path("dockerd-rootless.sh") {
+            Some(StartStrategy::Rootless)
+        } else {
+            None
+        }
+    }
+
+    /// Picks the strategy for the current platform.
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    pub fn detect() -> Option<StartStrategy> {
+        None
+    }
+
+    /// Returns the socket a daemon started this way listens on if it is not
+    /// the system's (i.e. the rootless daemon's socket in the user's runtime
+    /// directory).
+    pub fn host(self) -> Option<String> {
+        match self {
+            StartStrategy::Systemd { user: true } | StartStrategy::Rootless => Some(format!(
+                "unix://{}",
+                runtime_dir().join("docker.sock").display()
+            )),
+            StartStrategy::App | StartStrategy::Systemd { user: false } => None,
+        }
+    }
+
+    /// Returns the strategy bifrost last started the daemon with (if it has
+    /// not been stopped since).
+    pub fn recorded(home: &Path) -> Option<StartStrategy> {
+        toml::from_str(&fs::read_to_string(record_path(home)).ok()?).ok()
+    }
+
+    /// Records that bifrost started the daemon with this strategy. Nothing is
+    /// recorded before `bifrost setup`.
+    ///
+    /// # Errors
+    ///
+    /// If the record cannot be written, then this method returns an error.
+    pub fn record(self, home: &Path) -> BifrostResult<()> {
+        if fs::metadata(home.join(".bifrost")).is_err() {
+            return Ok(());
+        }
+        hofund::write(&record_path(home), toml::to_string(&self)?.as_bytes())
+    }
+
+    /// Forgets the recorded strategy.
+    pub fn forget(home: &Path) {
+        let _ = fs::remove_file(record_path(home));
+    }
+
+    /// Starts the daemon. This does not wait for the daemon to be ready.
+    ///
+    /// # Errors
+    ///
+    /// If the daemon could not be started, then this method returns an error.
+    pub fn start(self, home: &Path) -> BifrostResult<()> {
+        match self {
+            StartStrategy::App => {
+                let open = ProcessBuilder {
+                    program: String::from("open"),
+                    args: vec![
+                        String::from("--background"),
+                        String::from("-a"),
+                        String::from("Docker"),
+                    ],
+                    cwd: Some(home.to_path_buf()),
+                };
+                open.exec()?;
+            }
+            StartStrategy::Systemd { user } => {
+                // Inherit the terminal so that starting the system unit can
+                // ask for authentication.
+                systemctl(user, "start").status()?;
+            }
+            StartStrategy::Rootless => {
+                let log = home.join(".bifrost").join("dockerd-rootless.log");
+                let log = match fs::metadata(home.join(".bifrost")) {
+                    Ok(_) => File::create(&log)?,
+                    Err(_) => File::create(env::temp_dir().join("dockerd-rootless.log"))?,
+                };
+
+                // The daemon outlives bifrost; it is not waited on.
+                Command::new("dockerd-rootless.sh")
+                    .current_dir(home)
+                    .stdin(Stdio::null())
+                    .stdout(log.try_clone()?)
+                    .stderr(log)
+                    .spawn()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops the daemon.
+    ///
+    /// # Errors
+    ///
+    /// If the daemon could not be stopped, then this method returns an error.
+    pub fn stop(self) -> BifrostResult<()> {
+        let process = match self {
+            StartStrategy::App => ProcessBuilder {
+                program: String::from("osascript"),
+                args: vec![String::from("-e"), String::from("quit app \"Docker\"")],
+                cwd: None,
+            },
+            StartStrategy::Systemd { user } => systemctl(user, "stop"),
+            StartStrategy::Rootless => ProcessBuilder {
+                program: String::from("pkill"),
+                args: vec![String::from("-f"), String::from("dockerd-rootless.sh")],
+                cwd: None,
+            },
+        };
+        process.exec()?;
+        Ok(())
+    }
+}
+
+/// Returns the path of the record of the strategy the daemon was started with.
+fn record_path(home: &Path) -> PathBuf {
+    home.join(".bifrost").join("daemon.toml")
+}
+
+/// Returns the user's runtime directory, where the rootless daemon puts its
+/// socket.
+#[cfg(unix)]
+fn runtime_dir() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })),
+    }
+}
+
+/// Returns the user's runtime directory, where the rootless daemon puts its
+/// socket.
+#[cfg(not(unix))]
+fn runtime_dir() -> PathBuf {
+    env::temp_dir()
+}
+
+/// Returns a `systemctl [--user] <action> docker` process.
+fn systemctl(user: bool, action: &str) -> ProcessBuilder {
+    let mut args = vec![String::from(action), String::from("docker")];
+    if user {
+        args.insert(0, String::from("--user"));
+    }
+    ProcessBuilder {
+        program: String::from("systemctl"),
+        args,
+        cwd: None,
+    }
+}
+
+/// Returns whether or not `program` is an executable in `$PATH`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH")
+        .map(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// Delays between checks while waiting for something (e.g. the daemon) that
+/// grow exponentially up to a maximum, for at most `timeout` in total.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Backoff {
+    pub initial: Duration,
+    pub max: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(2),
+            timeout: Duration::from_secs(DEFAULT_START_TIMEOUT),
+        }
+    }
+}
+
+impl Backoff {
+    /// Returns the default backoff with the timeout taken from
+    /// `BIFROST_START_TIMEOUT` (in seconds) if it is set.
+    ///
+    /// # Errors
+    ///
+    /// If `BIFROST_START_TIMEOUT` is set but is not a number of seconds, then
+    /// this function returns an error.
+    pub fn from_env() -> BifrostResult<Self> {
+        let mut backoff = Backoff::default();
+        if let Ok(value) = env::var(START_TIMEOUT_VAR) {
+            match value.trim().parse::<u64>() {
+                Ok(secs) => backoff.timeout = Duration::from_secs(secs),
+                Err(_) => failure::bail!(
+                    "error: `{}` must be a number of seconds, not `{}`",
+                    START_TIMEOUT_VAR,
+                    value
+                ),
+            }
+        }
+        Ok(backoff)
+    }
+
+    /// Calls `ready` until it returns `true`, sleeping between calls. Returns
+    /// whether or not `ready` returned `true` before the timeout.
+    pub fn poll<F>(&self, ready: F) -> bool
+    where
+        F: FnMut() -> bool,
+    {
+        self.poll_with(ready, thread::sleep)
+    }
+
+    /// Like `poll`, but sleeps with `sleep`.
+    fn poll_with<F, S>(&self, mut ready: F, mut sleep: S) -> bool
+    where
+        F: FnMut() -> bool,
+        S: FnMut(Duration),
+    {
+        let start = Instant::now();
+        let mut waited = Duration::from_secs(0);
+        let mut delay = self.initial;
+        loop {
+            if ready() {
+                return true;
+            }
+            // Both the time spent checking and sleeping count towards the
+            // timeout.
+            let elapsed = start.elapsed().max(waited);
+            if elapsed >= self.timeout {
+                return false;
+            }
+
+            let delay_now = delay.min(self.timeout - elapsed);
+            sleep(delay_now);
+            waited += delay_now;
+            delay = (delay * 2).min(self.max);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_backoff_poll() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(400),
+            timeout: Duration::from_secs(1),
+        };
+
+        // The delays double up to the maximum and stop at the timeout.
+        let mut delays = vec![];
+        let ready = backoff.poll_with(|| false, |d| delays.push(d.as_millis()));
+        assert!(!ready);
+        assert_eq!(vec![100, 200, 400, 300], delays);
+
+        let mut checks = 0;
+        let ready = backoff.poll_with(
+            || {
+                checks += 1;
+                checks == 3
+            },
+            |_| (),
+        );
+        assert!(ready);
+        assert_eq!(3, checks);
+    }
+
+    #[test]
+    fn test_recorded_strategy() -> BifrostResult<()> {
+        let home = env::temp_dir().join(format!("bifrost-daemon-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+
+        // Nothing is recorded before `bifrost setup`.
+        StartStrategy::Rootless.record(&home)?;
+        assert_eq!(None, StartStrategy::recorded(&home));
+
+        fs::create_dir_all(home.join(".bifrost"))?;
+        for strategy in &[
+            StartStrategy::App,
+            StartStrategy::Systemd { user: true },
+            StartStrategy::Rootless,
+        ] {
+            strategy.record(&home)?;
+            assert_eq!(Some(*strategy), StartStrategy::recorded(&home));
+        }
+        StartStrategy::forget(&home);
+        assert_eq!(None, StartStrategy::recorded(&home));
+
+        assert!(StartStrategy::Rootless.host().is_some());
+        assert_eq!(None, StartStrategy::Systemd { user: false }.host());
+
+        fs::remove_dir_all(&home)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_in_path() {
+        assert!(in_path("sh"));
+        assert!(!in_path("not@çtu@llyar34lp4th"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Line-based unified diffs (as printed by `diff -u`).
+use std::fmt::Write;
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+/// The most entries kept while searching for the edit script: the search keeps
+/// `2 * (n + m) + 3` entries per edit, so files that differ too much are only
+/// reported as different.
+const MAX_TRACE: usize = 1 << 22;
+
+/// A step of the edit script that turns `old` into `new`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Returns whether or not `bytes` look like text (UTF-8 without NUL bytes).
+pub fn is_text(bytes: &[u8]) -> bool {
+    !bytes.contains(&0) && std::str::from_utf8(bytes).is_ok()
+}
+
+/// Returns the unified diff of `old` and `new` with the given file names, or
+/// an empty string if they are the same. Files that differ too much to diff
+/// are only reported as different.
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = match edits(&a, &b, MAX_TRACE) {
+        Some(edits) => edits,
+        None => return format!("Files {} and {} differ\n", old_name, new_name),
+    };
+    if edits.iter().all(|e| *e == Edit::Equal) {
+        return String::new();
+    }
+
+    // The number of lines of `old` and `new` before each edit.
+    let mut before = Vec::with_capacity(edits.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    before.push((i, j));
+    for edit in &edits {
+        match edit {
+            Edit::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Edit::Delete => i += 1,
+            Edit::Insert => j += 1,
+        }
+        before.push((i, j));
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (start, end) in hunks(&edits) {
+        let (a_start, b_start) = before[start];
+        let (a_end, b_end) = before[end];
+        let _ = writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(a_start, a_end - a_start),
+            range(b_start, b_end - b_start)
+        );
+
+        for (k, edit) in edits[start..end].iter().enumerate() {
+            let (i, j) = before[start + k];
+            let (prefix, line) = match edit {
+                Edit::Equal => (' ', a[i]),
+                Edit::Delete => ('-', a[i]),
+                Edit::Insert => ('+', b[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// Formats the range of a hunk header; empty ranges start before their line.
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Returns the `[start, end)` ranges of edits that make up each hunk: the
+/// changes with up to `CONTEXT` unchanged lines around them, merging changes
+/// whose context would overlap.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (k, _) in edits.iter().enumerate().filter(|(_, e)| **e != Edit::Equal) {
+        let start = k.saturating_sub(CONTEXT);
+        let end = (k + CONTEXT + 1).min(edits.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    hunks
+}
+
+/// Returns the shortest edit script that turns `a` into `b` (Myers' greedy
+/// algorithm), or `None` if finding it would keep more than `max_trace`
+/// entries.
+fn edits(a: &[&str], b: &[&str], max_trace: usize) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    let mut trace: Vec<Vec<isize>> = vec![];
+
+    let at = |k: isize| (k + offset) as usize;
+    'search: for d in 0..=(n + m) {
+        if (trace.len() + 1) * v.len() > max_trace {
+            return None;
+        }
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut script = vec![];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        while x > prev_x && y > prev_y {
+            script.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            script.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    script.reverse();
+    Some(script)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unified() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\nj\nk";
+        let expected = "\
+--- a/file.txt
++++ b/file.txt
+@@ -2,9 +2,10 @@
+ b
+ c
+ d
+-e
++E
+ f
+ g
+ h
+ i
+ j
++k
+\\ No newline at end of file
+";
+        assert_eq!(expected, unified(old, new, "a/file.txt", "b/file.txt"));
+        assert_eq!("", unified(old, old, "a", "b"));
+    }
+
+    #[test]
+    fn test_unified_added() {
+        let expected = "--- /dev/null\n+++ b/new.txt\n@@ -0,0 +1,2 @@\n+x\n+y\n";
+        assert_eq!(expected, unified("", "x\ny\n", "/dev/null", "b/new.txt"));
+    }
+
+    #[test]
+    fn test_unified_too_different() {
+        let old: String = (0..2000).map(|i| format!("a{}\n", i)).collect();
+        let new: String = (0..2000).map(|i| format!("b{}\n", i)).collect();
+        assert_eq!(
+            "Files a/file.txt and b/file.txt differ\n",
+            unified(&old, &new, "a/file.txt", "b/file.txt")
+        );
+
+        let a = ["x\n", "y\n"];
+        assert_eq!(None, edits(&a, &["z\n"], 8));
+        assert_eq!(
+            Some(vec![Edit::Equal, Edit::Delete]),
+            edits(&a, &["x\n"], MAX_TRACE)
+        );
+    }
+
+    #[test]
+    fn test_is_text() {
+        assert!(is_text(b"fn main() {}\n"));
+        assert!(!is_text(b"\x7fELF\x00\x01"));
+    }
+}
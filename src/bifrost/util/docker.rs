@@ -1,11 +1,23 @@
 //! Structures and functions for working with docker.
-use crate::core::hofund;
-use crate::util::{BifrostResult, ProcessBuilder};
+use crate::core::config::EnvVar;
+use crate::core::workingdir::{self, Link};
+use crate::util::daemon::{self, Backoff, StartStrategy};
+use crate::util::dockerfile;
+use crate::util::hash::{self, HashCache};
+use crate::util::inventory::{ImageRecord, Inventory};
+use crate::util::operation_info::RunStatus;
+use crate::util::{BifrostResult, OperationInfo, ProcessBuilder};
 
-use std::path::Path;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::process;
 
 use dirs;
+use regex::Regex;
 use spinner::SpinnerBuilder;
+use subprocess::{self, Popen, PopenConfig, Redirection};
 
 /// Checks whether or not docker is currently installed.
 pub fn is_installed() -> bool {
@@ -27,7 +39,25 @@ pub fn is_installed() -> bool {
     }
 }
 
-/// Checks whether or not docker is currently up and running.
+/// Points engine commands at the daemon bifrost started if it listens on a
+/// socket of its own (e.g. the rootless daemon), unless `DOCKER_HOST` is set.
+pub fn connect<P: AsRef<Path>>(home: &P) {
+    if let Some(strategy) = StartStrategy::recorded(home.as_ref()) {
+        use_host(strategy);
+    }
+}
+
+/// Makes engine commands (which inherit bifrost's environment) talk to the
+/// daemon started with `strategy`, unless `DOCKER_HOST` is set.
+fn use_host(strategy: StartStrategy) {
+    if env::var_os(daemon::HOST_VAR).is_none() {
+        if let Some(host) = strategy.host() {
+            env::set_var(daemon::HOST_VAR, host);
+        }
+    }
+}
+
+/// Checks whether or not docker is currently up and running (see `connect`).
 pub fn is_running() -> bool {
     let info = ProcessBuilder {
         program: String::from("docker"),
@@ -37,116 +67,325 @@ pub fn is_running() -> bool {
     return info.exec().is_ok();
 }
 
-/// Starts docker in the background.
+/// Starts docker in the background with the strategy of the current platform
+/// and waits for it to be running. How long to wait (60 seconds by default) is
+/// configurable through `BIFROST_START_TIMEOUT`.
 ///
 /// # Errors
 ///
-/// If the process failed to execute or if the process was executed, but
-/// an error occurred during execution, then this function returns an
-/// error.
+/// If there is no strategy for starting docker on this platform, if starting
+/// it failed, or if it did not come up before the timeout, then this function
+/// returns an error.
 pub fn start<P: AsRef<Path>>(home: &P) -> BifrostResult<Option<i32>> {
-    let open = ProcessBuilder {
-        program: String::from("open"),
-        args: vec![
-            String::from("--background"),
-            String::from("-a"),
-            String::from("Docker"),
-        ],
-
-        cwd: Some(home.as_ref().to_path_buf()),
+    let strategy = match StartStrategy::detect() {
+        Some(strategy) => strategy,
+        None => failure::bail!(
+            "error: do not know how to start docker on this platform; please start it manually"
+        ),
     };
+    let backoff = Backoff::from_env()?;
 
-    let output = open.exec()?;
-    if !output.status.success() {
-        failure::bail!(
-            "error: failed to start docker with status: {:#?}",
-            output.status.code()
-        );
+    if let Err(e) = strategy.start(home.as_ref()) {
+        failure::bail!("error: failed to start docker due to {}", e);
     }
+    // Probe (and later stop) the daemon that was just started.
+    use_host(strategy);
+    strategy.record(home.as_ref())?;
 
-    {
+    let running = {
         let _sp = SpinnerBuilder::new("Starting Docker...".into())
             .spinner(vec![
                 "⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏",
             ])
             .start();
-        while !is_running() {}
-    }
+        backoff.poll(is_running)
+    };
 
-    Ok(output.status.code())
+    if !running {
+        failure::bail!(
+            "error: docker did not start within {} seconds (see `{}`)",
+            backoff.timeout.as_secs(),
+            daemon::START_TIMEOUT_VAR
+        );
+    }
+    Ok(Some(0))
 }
 
-/// Stops docker by looking for its PID and killing that process.
+/// Stops docker with the strategy bifrost started it with or, if it did not
+/// start it, the strategy of the current platform.
 ///
 /// # Errors
 ///
-/// This method errors if any of the following fail:
+/// If there is no strategy for stopping docker on this platform or stopping
+/// it failed, then this function returns an error.
+pub fn stop<P: AsRef<Path>>(home: &P) -> BifrostResult<()> {
+    let strategy = match StartStrategy::recorded(home.as_ref()).or_else(StartStrategy::detect) {
+        Some(strategy) => strategy,
+        None => failure::bail!("error: do not know how to stop docker on this platform"),
+    };
+    strategy.stop()?;
+    StartStrategy::forget(home.as_ref());
+    Ok(())
+}
+
+/// The image built by `bifrost setup` and used by realms that do not select
+/// an image of their own.
+pub const DEFAULT_IMAGE: &str = "bifrost:0.1";
+
+/// Returns the tag of the image built from a realm's Dockerfile. Tags are
+/// derived from the realm name and the Dockerfile's hash so that an unchanged
+/// Dockerfile reuses its image.
+pub fn realm_image_tag(realm: &str, dockerfile: &[u8]) -> String {
+    let repository: String = sanitize(realm)
+        .to_lowercase()
+        .trim_start_matches(['.', '-'])
+        .to_owned();
+    format!(
+        "bifrost-{}:{}",
+        repository,
+        &hash::sha256_hex(dockerfile)[..12]
+    )
+}
+
+/// The image a realm's container is created from.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum RealmImage {
+    /// The image built by `bifrost setup`.
+    #[default]
+    Default,
+    /// An existing image; the engine pulls it if it is not present.
+    Image(String),
+    /// An image built from a Dockerfile within the realm.
+    Dockerfile {
+        /// The path to the Dockerfile.
+        path: PathBuf,
+        /// The build context (the Dockerfile's directory unless `[container]
+        /// context` is set).
+        context: PathBuf,
+        /// The tag derived from the realm name and the Dockerfile's hash.
+        tag: String,
+    },
+}
+
+impl RealmImage {
+    /// Returns the tag of the image.
+    pub fn tag(&self) -> &str {
+        match self {
+            RealmImage::Default => DEFAULT_IMAGE,
+            RealmImage::Image(tag) | RealmImage::Dockerfile { tag, .. } => tag,
+        }
+    }
+
+    /// Makes sure the image exists and is up to date, (re)building it when
+    /// its Dockerfile or build context changed since it was last built, and
+    /// records that `realm` uses it.
+    ///
+    /// # Errors
+    ///
+    /// If the image needs to be built and the build fails, then this method
+    /// returns an error.
+    pub fn ensure(&self, engine: &str, home_path: &Path, realm: &str) -> BifrostResult<()> {
+        let image = match self.builder(engine, home_path)? {
+            Some(image) => image,
+            None => {
+                // Pull up front so that the image's digest can be pinned.
+                if !image_exists(engine, self.tag()) {
+                    pull_image(engine, self.tag())?;
+                }
+                return Ok(());
+            }
+        };
+
+        image.rebuild(home_path, false)?;
+
+        let mut inventory = Inventory::load(home_path)?;
+        if inventory.reference(&image.tag, realm) {
+            inventory.save(home_path)?;
+        }
+        Ok(())
+    }
+}
+
+impl RealmImage {
+    /// Returns the builder of the image if bifrost builds it.
+    ///
+    /// # Errors
+    ///
+    /// If the build context of the default image cannot be created, then
+    /// this method returns an error.
+    pub fn builder(&self, engine: &str, home_path: &Path) -> BifrostResult<Option<ImageBuilder>> {
+        match self {
+            RealmImage::Default => Ok(Some(default_image(home_path)?)),
+            RealmImage::Image(_) => Ok(None),
+            RealmImage::Dockerfile { path, context, tag } => Ok(Some(ImageBuilder {
+                name: String::from(engine),
+                tag: tag.clone(),
+                path: context.to_string_lossy().to_string(),
+                dockerfile: Some(path.to_string_lossy().to_string()),
+            })),
+        }
+    }
+
+    /// Returns the base image of the image if bifrost builds it.
+    pub fn base(&self, home_path: &Path) -> Option<String> {
+        let path = match self {
+            RealmImage::Default => dockerfile::global_path(home_path),
+            RealmImage::Image(_) => return None,
+            RealmImage::Dockerfile { path, .. } => path.clone(),
+        };
+        dockerfile::base_image(&fs::read_to_string(path).ok()?)
+    }
+}
+
+/// Pulls the image tagged `tag`, streaming the engine's progress.
 ///
-/// * getting the docker pid
-/// * if the process executing the above function returns un`success`fully
-/// * conversion from a `Vec<u8>` to `String::from_utf8` fails
-/// * killing the docker process failed
-pub fn stop() -> BifrostResult<()> {
-    let get_docker_pid = ProcessBuilder {
-        program: String::from("bash"),
-        args: vec![String::from("-c"), String::from("printf $(pgrep Docker)")],
+/// # Errors
+///
+/// If the engine fails to pull the image, then this function returns an
+/// error.
+pub fn pull_image(engine: &str, tag: &str) -> BifrostResult<()> {
+    let pull = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![String::from("pull"), String::from(tag)],
         cwd: None,
     };
 
-    let output = get_docker_pid.exec()?;
-    if !output.status.success() {
-        failure::bail!(
-            "error: failed to `get_docker_pid` docker with status: {}",
-            output.status.code().unwrap_or(1)
-        );
+    if let Err(e) = pull.status() {
+        failure::bail!("error: failed to pull image `{}` due to {}", tag, e);
     }
+    Ok(())
+}
 
-    let pid = String::from_utf8(output.stdout)?;
+/// Returns whether or not the engine has an image tagged `tag`.
+pub fn image_exists(engine: &str, tag: &str) -> bool {
+    let inspect = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("image"),
+            String::from("inspect"),
+            String::from(tag),
+        ],
+        cwd: None,
+    };
 
-    let kill = ProcessBuilder {
-        program: String::from("kill"),
-        args: vec![pid],
+    match inspect.exec() {
+        Ok(output) => output.status.success(),
+        Err(_) => false,
+    }
+}
+
+/// Returns whether the engine's daemon runs rootless or with user namespace
+/// remapping, in which case root within a container is already mapped to an
+/// unprivileged user on the host.
+pub fn remaps_users(engine: &str) -> bool {
+    let info = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("info"),
+            String::from("--format"),
+            String::from("{{join .SecurityOptions \" \"}}"),
+        ],
         cwd: None,
     };
 
-    let output = kill.exec()?;
-    if !output.status.success() {
-        failure::bail!(
-            "error: failed to `kill` docker with status: {}",
-            output.status.code().unwrap_or(1)
-        );
+    match info.exec() {
+        Ok(output) => String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .any(|o| o == "name=rootless" || o == "name=userns"),
+        Err(_) => false,
+    }
+}
+
+/// Returns the registry digest (e.g. `debian@sha256:...`) of the image tagged
+/// `tag`, or `None` if the engine does not have it or it did not come from a
+/// registry. Unlike the image id, the registry digest is the same on every
+/// machine.
+pub fn repo_digest(engine: &str, tag: &str) -> Option<String> {
+    let inspect = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("image"),
+            String::from("inspect"),
+            String::from("--format"),
+            String::from("{{join .RepoDigests \" \"}}"),
+            String::from(tag),
+        ],
+        cwd: None,
     };
-    Ok(())
+
+    let output = inspect.exec().ok()?;
+    let digests: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+
+    // Prefer the digest of the repository the tag names.
+    let repository = repository(tag);
+    digests
+        .iter()
+        .find(|d| d.split('@').next() == Some(repository))
+        .or_else(|| digests.first())
+        .cloned()
 }
 
-/// A near-transliteration of linux's apt-get. That is, the name
-/// of this function is nearly what will appear in the Dockerfile.
-///
-/// For example, calling this function with the name 'app' translates
-/// to 'RUN apt-get install build-essential app -y'. This function provides
-/// a way of adding to the Dockerfile.
+/// Returns the repository of an image reference, i.e. the reference without
+/// its tag or digest (e.g. `localhost:5000/app` of `localhost:5000/app:1.0`).
+fn repository(reference: &str) -> &str {
+    let reference = reference.split('@').next().unwrap_or(reference);
+    match reference.rfind(':') {
+        Some(i) if !reference[i..].contains('/') => &reference[..i],
+        _ => reference,
+    }
+}
+
+/// What the engine knows about an image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageDetails {
+    /// The image id (e.g. `sha256:...`).
+    pub id: String,
+    /// The size of the image in bytes.
+    pub size: u64,
+}
+
+/// Returns the id and size of the image tagged `tag`, or `None` if the engine
+/// does not have it.
+pub fn image_details(engine: &str, tag: &str) -> Option<ImageDetails> {
+    let inspect = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("image"),
+            String::from("inspect"),
+            String::from("--format"),
+            String::from("{{.Id}} {{.Size}}"),
+            String::from(tag),
+        ],
+        cwd: None,
+    };
+
+    let output = inspect.exec().ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+    let mut fields = output.split_whitespace();
+    Some(ImageDetails {
+        id: String::from(fields.next()?),
+        size: fields.next()?.parse().ok()?,
+    })
+}
+
+/// Removes the image tagged `tag`.
 ///
 /// # Errors
 ///
-/// If `hofund::append` fails to append to the Dockerfile, this function
-/// returns the error produced by that failure.
-pub fn apt_get_install_build_essential(name: &str) -> BifrostResult<()> {
-    if let Some(path) = dirs::home_dir() {
-        let docker_file = path
-            .join(".bifrost")
-            .join("container")
-            .join("bifrost")
-            .join("Dockerfile");
-        let contents = format!(
-            r#"
-
-# Bifrost Appended:
-RUN apt-get install build-essential {} -y
+/// If the engine fails to remove the image (e.g. a container uses it), then
+/// this function returns an error.
+pub fn remove_image(engine: &str, tag: &str) -> BifrostResult<()> {
+    let rm = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![String::from("image"), String::from("rm"), String::from(tag)],
+        cwd: None,
+    };
 
-"#,
-            name
-        );
-        hofund::append(&docker_file, &contents.as_bytes())?;
+    if let Err(e) = rm.exec() {
+        failure::bail!("error: failed to remove image `{}` due to {}", tag, e);
     }
     Ok(())
 }
@@ -159,50 +398,896 @@ pub struct ImageBuilder {
     pub tag: String,
     /// The path to the mounted directory.
     pub path: String,
+    /// The path to the Dockerfile if it is not `<path>/Dockerfile`.
+    pub dockerfile: Option<String>,
 }
 
 impl ImageBuilder {
-    /// Builds the docker image by executing a process.
+    /// Returns the arguments (excluding the engine) used to build the image.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![String::from("build"), String::from("-t"), self.tag.clone()];
+        if let Some(ref dockerfile) = self.dockerfile {
+            args.push(String::from("-f"));
+            args.push(dockerfile.clone());
+        }
+        args.push(self.path.clone());
+        args
+    }
+
+    /// Builds the docker image by executing a process. The build output is
+    /// streamed to the terminal. Only what the build context keeps (see
+    /// [`hash::context_entries`](../hash/fn.context_entries.html)) is staged
+    /// and sent to the engine.
     ///
     /// # Errors
     ///
-    /// This method returns an error if the process execution
-    /// fails or the process returns an error itself.
+    /// This method returns an error if the context cannot be staged, if the
+    /// process execution fails or the process returns an error itself.
     /// If the process executed successfully, but the status
     /// output by this execution indicates its function was
     /// unsuccessful, then this method returns an error.
     pub fn build(&self) -> BifrostResult<()> {
-        let docker_process = ProcessBuilder {
-            program: self.name.clone(),
-            args: vec![
-                String::from("build"),
-                String::from("-t"),
-                self.tag.clone(),
-                self.path.clone(),
-            ],
-            cwd: None,
-        };
+        let staging = env::temp_dir().join(format!("bifrost-context-{}", process::id()));
+        let _ = fs::remove_dir_all(&staging);
+        let result = stage_context(Path::new(&self.path), &staging).and_then(|_| {
+            let dockerfile = match self.dockerfile {
+                Some(ref dockerfile) => dockerfile.clone(),
+                None => Path::new(&self.path)
+                    .join("Dockerfile")
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            let staged = ImageBuilder {
+                name: self.name.clone(),
+                tag: self.tag.clone(),
+                path: staging.to_string_lossy().to_string(),
+                dockerfile: Some(dockerfile),
+            };
+            let docker_process = ProcessBuilder {
+                program: self.name.clone(),
+                args: staged.args(),
+                cwd: None,
+            };
+            if let Err(e) = docker_process.status() {
+                failure::bail!("error: failed to build image `{}` due to {}", self.tag, e);
+            }
+            Ok(())
+        });
+        let _ = fs::remove_dir_all(&staging);
+        result
+    }
 
-        let _sp = SpinnerBuilder::new("Building Image...".into())
-            .spinner(vec![
-                "⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏",
-            ])
-            .start();
+    /// Returns the hash of the Dockerfile and build context of the image. Only
+    /// the files that changed since the last hash of the context are read.
+    ///
+    /// # Errors
+    ///
+    /// If the build context cannot be read, then this method returns an error.
+    pub fn hash(&self, home_path: &Path) -> BifrostResult<String> {
+        let cache_path = hash::cache_path(home_path, Path::new(&self.path));
+        let mut cache = HashCache::load(&cache_path);
+        let hash = hash::build_context(
+            Path::new(&self.path),
+            self.dockerfile.as_ref().map(Path::new),
+            &mut cache,
+        )?;
+        // The cache only saves reading files again.
+        let _ = cache.save(&cache_path);
+        Ok(hash)
+    }
+
+    /// Returns whether or not the image is missing or was built from a
+    /// different Dockerfile or build context than the current one.
+    ///
+    /// # Errors
+    ///
+    /// If the inventory or build context cannot be read, then this method
+    /// returns an error.
+    pub fn is_stale(&self, home_path: &Path) -> BifrostResult<bool> {
+        let inventory = Inventory::load(home_path)?;
+        let hash = self.hash(home_path)?;
+        match inventory.get(&self.tag) {
+            Some(record) if record.hash == hash => Ok(!image_exists(&self.name, &self.tag)),
+            _ => Ok(true),
+        }
+    }
+
+    /// Builds the image (unless `force` is `false` and it is not stale) and
+    /// records the hash it was built from in the inventory. Returns whether
+    /// or not the image was built.
+    ///
+    /// # Errors
+    ///
+    /// If the build fails or the inventory cannot be written, then this
+    /// method returns an error.
+    pub fn rebuild(&self, home_path: &Path, force: bool) -> BifrostResult<bool> {
+        if !force && !self.is_stale(home_path)? {
+            return Ok(false);
+        }
+
+        // Hash before building so that edits made during the build leave the
+        // image stale.
+        let hash = self.hash(home_path)?;
+        self.build()?;
+
+        let digest = image_details(&self.name, &self.tag)
+            .map(|d| d.id)
+            .unwrap_or_default();
+        let mut inventory = Inventory::load(home_path)?;
+        inventory.insert(&self.tag, ImageRecord::new(hash, digest));
+        inventory.save(home_path)?;
+        Ok(true)
+    }
+}
+
+/// Copies what the build `context` keeps to `to`, so that nothing else (e.g.
+/// the realm's secrets file) reaches the engine.
+///
+/// # Errors
+///
+/// If the context cannot be walked or copied, then this function returns an
+/// error.
+fn stage_context(context: &Path, to: &Path) -> BifrostResult<()> {
+    for entry in hash::context_entries(context)? {
+        let staged = to.join(entry.path().strip_prefix(context)?);
+        let file_type = entry.file_type();
+        if file_type.is_dir() {
+            fs::create_dir_all(&staged)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &staged)?;
+        } else if file_type.is_symlink() {
+            let link = Link {
+                target: fs::read_link(entry.path())?,
+                path: entry.into_path(),
+            };
+            workingdir::load_link(&link, &staged)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the builder of the image built by `bifrost setup`, creating its
+/// (otherwise empty) build context if needed.
+///
+/// # Errors
+///
+/// If the build context cannot be created, then this function returns an
+/// error.
+pub fn default_image(home_path: &Path) -> BifrostResult<ImageBuilder> {
+    let context = dockerfile::global_context(home_path);
+    fs::create_dir_all(&context)?;
+
+    Ok(ImageBuilder {
+        name: String::from("docker"),
+        tag: String::from(DEFAULT_IMAGE),
+        path: context.to_string_lossy().to_string(),
+        dockerfile: Some(
+            dockerfile::global_path(home_path)
+                .to_string_lossy()
+                .to_string(),
+        ),
+    })
+}
+
+/// Resource limits applied to a container so that a runaway command cannot
+/// freeze the host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// The number of CPUs the container may use (e.g. `1.5`).
+    pub cpus: Option<f64>,
+    /// The memory limit (e.g. `512m` or `2g`).
+    pub memory: Option<String>,
+    /// The maximum number of processes within the container.
+    pub pids_limit: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Checks that each limit is something the container engine accepts.
+    ///
+    /// # Errors
+    ///
+    /// If `cpus` is not positive or `memory` is not a number followed by an
+    /// optional unit (`b`, `k`, `m`, or `g`), then this method returns an error.
+    pub fn validate(&self) -> BifrostResult<()> {
+        if let Some(cpus) = self.cpus {
+            if cpus <= 0.0 || !cpus.is_finite() {
+                failure::bail!("error: `cpus` must be a positive number, found `{}`", cpus);
+            }
+        }
+
+        if let Some(ref memory) = self.memory {
+            let re = Regex::new(r"^(?i)[0-9]+[bkmg]?$")?;
+            if !re.is_match(memory) {
+                failure::bail!(
+                    "error: `memory` must be a number with an optional unit \
+                     (b, k, m, g), found `{}`",
+                    memory
+                );
+            }
+        }
+
+        if self.pids_limit == Some(0) {
+            failure::bail!("error: `pids_limit` must be greater than zero");
+        }
+        Ok(())
+    }
+
+    /// Returns the container engine arguments for these limits.
+    pub fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![];
+        if let Some(cpus) = self.cpus {
+            args.push(String::from("--cpus"));
+            args.push(cpus.to_string());
+        }
+        if let Some(ref memory) = self.memory {
+            // Setting the swap limit to the memory limit disables swap so the
+            // limit is actually enforced.
+            args.push(String::from("--memory"));
+            args.push(memory.clone());
+            args.push(String::from("--memory-swap"));
+            args.push(memory.clone());
+        }
+        if let Some(pids_limit) = self.pids_limit {
+            args.push(String::from("--pids-limit"));
+            args.push(pids_limit.to_string());
+        }
+        args
+    }
+}
+
+/// Network options of a container: the networking mode and the ports that are
+/// published to the host.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkOptions {
+    /// One of `none`, `bridge` (the engine default), or `host`.
+    pub mode: Option<String>,
+    /// Ports published to the host as `[ip:][host_port:]container_port[/proto]`.
+    pub ports: Vec<String>,
+}
+
+impl NetworkOptions {
+    /// Checks that the networking mode is supported and that each port is a
+    /// valid port specification.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the mode is unknown, a port cannot be
+    /// parsed, or ports are published while networking is `none` or `host`
+    /// (where publishing has no effect).
+    pub fn validate(&self) -> BifrostResult<()> {
+        if let Some(ref mode) = self.mode {
+            match mode.as_ref() {
+                "none" | "bridge" | "host" => {}
+                _ => failure::bail!(
+                    "error: `network` must be one of `none`, `bridge`, or `host`, found `{}`",
+                    mode
+                ),
+            }
+
+            if mode != "bridge" && !self.ports.is_empty() {
+                failure::bail!(
+                    "error: ports cannot be published when `network = \"{}\"`",
+                    mode
+                );
+            }
+        }
+
+        let re =
+            Regex::new(r"^(?:(?:\d{1,3}\.){3}\d{1,3}:)?(?:(\d+):)?(\d+)(?:/(?:tcp|udp|sctp))?$")?;
+        for port in &self.ports {
+            let valid = match re.captures(port) {
+                Some(caps) => caps
+                    .iter()
+                    .skip(1)
+                    .flatten()
+                    .all(|c| c.as_str().parse::<u16>().is_ok_and(|p| p > 0)),
+                None => false,
+            };
+
+            if !valid {
+                failure::bail!(
+                    "error: invalid port `{}`, expected `[ip:][host_port:]container_port[/proto]`",
+                    port
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the container engine arguments for these options.
+    pub fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![];
+        if let Some(ref mode) = self.mode {
+            args.push(String::from("--network"));
+            args.push(mode.clone());
+        }
+        for port in &self.ports {
+            args.push(String::from("--publish"));
+            args.push(port.clone());
+        }
+        args
+    }
+}
+
+/// The label that associates engine objects (e.g. volumes) with a realm.
+pub const REALM_LABEL: &str = "bifrost.realm";
+
+/// The mount of a workspace into the container.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkspaceMount {
+    /// The workspace's path on the host.
+    pub source: PathBuf,
+    /// The absolute path the workspace is mounted at within the container.
+    pub target: String,
+    /// The directory, relative to the workspace, commands are run from.
+    pub workdir: Option<String>,
+}
+
+impl WorkspaceMount {
+    /// Checks that the workspace mount can be expressed as a `--volume`
+    /// specification and that the working directory stays within the
+    /// workspace.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the mount point is not absolute, is the
+    /// container's root, or contains `:` or `..`; if the host path is empty or
+    /// contains `:`; or if the working directory is not a relative path to an existing
+    /// directory of the workspace.
+    pub fn validate(&self) -> BifrostResult<()> {
+        let target = Path::new(&self.target);
+        if !target.is_absolute() || target.parent().is_none() {
+            failure::bail!(
+                "error: mount point `{}` must be an absolute path other than `/`",
+                self.target
+            );
+        }
+        if self.target.contains(':') || has_parent_dir(target) {
+            failure::bail!(
+                "error: mount point `{}` cannot contain `:` or `..`",
+                self.target
+            );
+        }
+        if self.source.as_os_str().is_empty() {
+            failure::bail!("error: workspace path cannot be empty");
+        }
+        if self.source.to_string_lossy().contains(':') {
+            failure::bail!(
+                "error: workspace path `{}` cannot contain `:`",
+                self.source.display()
+            );
+        }
+
+        if let Some(ref workdir) = self.workdir {
+            let path = Path::new(workdir);
+            if path.is_absolute() || has_parent_dir(path) || workdir.contains(':') {
+                failure::bail!(
+                    "error: workdir `{}` must be a path relative to (and within) the workspace",
+                    workdir
+                );
+            }
+            if !self.source.join(path).is_dir() {
+                failure::bail!(
+                    "error: workdir `{}` is not a directory of the workspace",
+                    workdir
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the `host:container` volume specification of the workspace.
+    pub fn volume(&self) -> String {
+        format!("{}:{}", self.source.display(), self.target)
+    }
 
-        match docker_process.exec() {
-            Ok(output) => {
-                if !output.status.success() {
+    /// Returns the absolute path commands are run from within the container.
+    pub fn workdir(&self) -> String {
+        let target = self.target.trim_end_matches('/');
+        match self.workdir.as_ref().map(|w| w.trim_matches('/')) {
+            Some(workdir) if !workdir.is_empty() && workdir != "." => {
+                format!("{}/{}", target, workdir)
+            }
+            _ => String::from(target),
+        }
+    }
+
+    /// Returns the container engine arguments for the workspace mount.
+    pub fn args(&self) -> Vec<String> {
+        vec![
+            String::from("--volume"),
+            self.volume(),
+            String::from("--workdir"),
+            self.workdir(),
+        ]
+    }
+}
+
+/// Returns whether or not `path` contains a `..` component.
+fn has_parent_dir(path: &Path) -> bool {
+    path.components().any(|c| c == Component::ParentDir)
+}
+
+/// A mount of a container other than the workspace itself.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mount {
+    /// A host directory (or file) bind mounted into the container.
+    Bind {
+        source: PathBuf,
+        target: String,
+        read_only: bool,
+    },
+    /// A named volume managed by the container engine and labeled with the
+    /// realm it belongs to.
+    Volume {
+        name: String,
+        realm: String,
+        target: String,
+        read_only: bool,
+    },
+}
+
+impl Mount {
+    /// Returns the path the mount is mounted at within the container.
+    pub fn target(&self) -> &str {
+        match self {
+            Mount::Bind { target, .. } | Mount::Volume { target, .. } => target,
+        }
+    }
+
+    /// Checks that this mount can be mounted without shadowing the workspace,
+    /// which is mounted at `workspace`.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the target is not absolute, is (or is
+    /// an ancestor or descendant of) the workspace mount point, if a bind
+    /// source does not exist, or if any part of the mount cannot be expressed
+    /// as a `--mount` specification.
+    pub fn validate(&self, workspace: &str) -> BifrostResult<()> {
+        let target = Path::new(self.target());
+        if !target.is_absolute() {
+            failure::bail!("error: mount target `{}` must be absolute", self.target());
+        }
+
+        let workspace = Path::new(workspace);
+        if target.starts_with(workspace) || workspace.starts_with(target) {
+            failure::bail!(
+                "error: mount target `{}` would shadow the workspace mounted at `{}`",
+                self.target(),
+                workspace.display()
+            );
+        }
+
+        if self.target().contains(',') {
+            failure::bail!("error: mount target `{}` cannot contain `,`", self.target());
+        }
+
+        match self {
+            Mount::Bind { source, .. } => {
+                if source.to_string_lossy().contains(',') {
                     failure::bail!(
-                        "failed: `ImageBuilder::build` failed to successfully build the image"
+                        "error: mount source `{}` cannot contain `,`",
+                        source.display()
                     );
                 }
+                if fs::metadata(source).is_err() {
+                    failure::bail!("error: mount source `{}` does not exist", source.display());
+                }
             }
-            Err(e) => {
-                failure::bail!("error: failed to build image due to {}", e);
+            Mount::Volume { name, .. } => {
+                let re = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9_.-]*$")?;
+                if !re.is_match(name) {
+                    failure::bail!("error: invalid volume name `{}`", name);
+                }
             }
         }
         Ok(())
     }
+
+    /// Returns the container engine arguments for this mount.
+    pub fn args(&self) -> Vec<String> {
+        let (kind, source, read_only) = match self {
+            Mount::Bind {
+                source, read_only, ..
+            } => ("bind", source.to_string_lossy().to_string(), read_only),
+            Mount::Volume {
+                name, read_only, ..
+            } => ("volume", name.clone(), read_only),
+        };
+
+        let mut spec = format!("type={},source={},target={}", kind, source, self.target());
+        if *read_only {
+            spec.push_str(",readonly");
+        }
+        vec![String::from("--mount"), spec]
+    }
+}
+
+/// Returns `name` with every character the container engine does not accept
+/// in object names replaced by an underscore.
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '.' | '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Returns a container name for a run of the workspace `name`. The name is
+/// unique per bifrost process so the container can be inspected after it exits.
+pub fn container_name(name: &str) -> String {
+    format!("bifrost-{}-{}", sanitize(name), process::id())
+}
+
+/// Returns the engine volume name of the cache volume `name` of `realm`.
+pub fn volume_name(realm: &str, name: &str) -> String {
+    format!("bifrost-{}-{}", sanitize(realm), name)
+}
+
+/// Creates the named volume (if it does not already exist) and labels it with
+/// the realm it belongs to.
+///
+/// # Errors
+///
+/// If the engine fails to create the volume, then this function returns an
+/// error.
+pub fn create_volume(engine: &str, name: &str, realm: &str) -> BifrostResult<()> {
+    let create = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("volume"),
+            String::from("create"),
+            String::from("--label"),
+            format!("{}={}", REALM_LABEL, realm),
+            String::from(name),
+        ],
+        cwd: None,
+    };
+
+    if let Err(e) = create.exec() {
+        failure::bail!("error: failed to create volume `{}` due to {}", name, e);
+    }
+    Ok(())
+}
+
+/// Returns the arguments (excluding the engine) of a one-shot container that
+/// gives `user` ownership of the volume `name` mounted at `target` of `image`.
+/// The volume is mounted at its `target` so that anything the engine copies
+/// into it from the image is owned by `user` too.
+fn chown_volume_args(name: &str, image: &str, target: &str, user: &str) -> Vec<String> {
+    vec![
+        String::from("run"),
+        String::from("--rm"),
+        String::from("--user"),
+        String::from("0:0"),
+        String::from("--mount"),
+        format!("type=volume,source={},target={}", name, target),
+        String::from(image),
+        String::from("chown"),
+        String::from("-R"),
+        String::from(user),
+        String::from(target),
+    ]
+}
+
+/// Gives `user` ownership of the (newly created) volume `name`, which is
+/// otherwise owned by root and could not be written to by a run as `user`.
+///
+/// # Errors
+///
+/// If the engine fails to change the owner of the volume, then the volume is
+/// removed (so the next run tries again) and this function returns an error.
+pub fn chown_volume(
+    engine: &str,
+    name: &str,
+    image: &str,
+    target: &str,
+    user: &str,
+) -> BifrostResult<()> {
+    let chown = ProcessBuilder {
+        program: String::from(engine),
+        args: chown_volume_args(name, image, target, user),
+        cwd: None,
+    };
+
+    if let Err(e) = chown.exec() {
+        let _ = remove_volume(engine, name);
+        failure::bail!(
+            "error: failed to give `{}` ownership of volume `{}` due to {}",
+            user,
+            name,
+            e
+        );
+    }
+    Ok(())
+}
+
+/// Lists the names of volumes created by bifrost for `realm`, or for every
+/// realm if `realm` is `None`.
+///
+/// # Errors
+///
+/// If the engine fails to list volumes, then this function returns an error.
+pub fn list_volumes(engine: &str, realm: Option<&str>) -> BifrostResult<Vec<String>> {
+    let filter = match realm {
+        Some(realm) => format!("label={}={}", REALM_LABEL, realm),
+        None => format!("label={}", REALM_LABEL),
+    };
+
+    let ls = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("volume"),
+            String::from("ls"),
+            String::from("--quiet"),
+            String::from("--filter"),
+            filter,
+        ],
+        cwd: None,
+    };
+
+    let output = match ls.exec() {
+        Ok(output) => output,
+        Err(e) => failure::bail!("error: failed to list volumes due to {}", e),
+    };
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Removes the named volume.
+///
+/// # Errors
+///
+/// If the engine fails to remove the volume (e.g. it is in use by a running
+/// container), then this function returns an error.
+pub fn remove_volume(engine: &str, name: &str) -> BifrostResult<()> {
+    let rm = ProcessBuilder {
+        program: String::from(engine),
+        args: vec![
+            String::from("volume"),
+            String::from("rm"),
+            String::from(name),
+        ],
+        cwd: None,
+    };
+
+    if let Err(e) = rm.exec() {
+        failure::bail!("error: failed to remove volume `{}` due to {}", name, e);
+    }
+    Ok(())
+}
+
+/// Primary structure for running commands within a container.
+pub struct ContainerRunner {
+    /// The name of the container engine.
+    pub name: String,
+    /// The image the container is created from.
+    pub image: String,
+    /// The name given to the container.
+    pub container: String,
+    /// The workspace mounted into the container.
+    pub workspace: WorkspaceMount,
+    /// The environment variables passed to the container.
+    pub env: Vec<EnvVar>,
+    /// The user (`name|uid[:gid]`) commands are run as.
+    pub user: Option<String>,
+    /// The resource limits applied to the container.
+    pub limits: ResourceLimits,
+    /// The network options of the container.
+    pub network: NetworkOptions,
+    /// Additional bind mounts and named volumes.
+    pub mounts: Vec<Mount>,
+    /// The shell command run within the container.
+    pub command: String,
+}
+
+impl ContainerRunner {
+    /// Returns the full argument list (including the engine) used to run the
+    /// container. Only the names of environment variables appear here; their
+    /// values are passed through the environment of the engine process so that
+    /// they never show up in a process listing.
+    pub fn args(&self) -> Vec<String> {
+        let mut args: Vec<String> = vec![
+            self.name.clone(),
+            String::from("run"),
+            String::from("-i"),
+            String::from("--name"),
+            self.container.clone(),
+        ];
+        args.extend(self.workspace.args());
+
+        if let Some(ref user) = self.user {
+            // The host user rarely exists in the image; give it a writable home.
+            args.push(String::from("--user"));
+            args.push(user.clone());
+            args.push(String::from("--env"));
+            args.push(String::from("HOME=/tmp"));
+        }
+
+        for var in &self.env {
+            args.push(String::from("--env"));
+            args.push(var.name.clone());
+        }
+
+        for mount in &self.mounts {
+            args.extend(mount.args());
+        }
+
+        args.extend(self.limits.args());
+        args.extend(self.network.args());
+        args.push(self.image.clone());
+
+        // The command is run by a shell explicitly instead of relying on the
+        // image's `CMD` to read it from stdin.
+        args.push(String::from("sh"));
+        args.push(String::from("-c"));
+        args.push(self.command.clone());
+        args
+    }
+
+    /// Runs the container's command and collects its output and exit status.
+    /// The container is removed once it has exited, even if the run is
+    /// interrupted (Ctrl-C reaches the engine, which stops the container) or
+    /// bifrost panics.
+    ///
+    /// # Errors
+    ///
+    /// If the container engine could not be executed or communicated with,
+    /// then this method returns an error.
+    pub fn run(&self) -> BifrostResult<OperationInfo> {
+        // Named volumes are created up front so they carry the realm label
+        // that `bifrost cache clear` relies on and are owned by the user
+        // commands run as; only the first run creates them.
+        let mut existing: Option<Vec<String>> = None;
+        for mount in &self.mounts {
+            if let Mount::Volume {
+                name,
+                realm,
+                target,
+                ..
+            } = mount
+            {
+                if existing.is_none() {
+                    existing = Some(list_volumes(&self.name, Some(realm))?);
+                }
+                if !existing.as_ref().is_some_and(|v| v.contains(name)) {
+                    create_volume(&self.name, name, realm)?;
+                    if let Some(ref user) = self.user {
+                        chown_volume(&self.name, name, &self.image, target, user)?;
+                    }
+                }
+            }
+        }
+
+        let mut env = PopenConfig::current_env();
+        for var in &self.env {
+            env.push((var.name.clone().into(), var.value.clone().into()));
+        }
+
+        let _guard = RunGuard::new(self);
+        let mut process = Popen::create(
+            &self.args(),
+            PopenConfig {
+                stdout: Redirection::Pipe,
+                stdin: Redirection::Pipe,
+                stderr: Redirection::Pipe,
+                env: Some(env),
+                ..Default::default()
+            },
+        )?;
+
+        let (stdout, stderr) = {
+            let _sp = SpinnerBuilder::new("Running...".into())
+                .spinner(vec![
+                    "⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏",
+                ])
+                .start();
+            process.communicate(Some(""))?
+        };
+        let exit_status = process.wait()?;
+
+        // The container is kept around until it has been inspected; a limit
+        // exceeded termination looks just like any other failure otherwise.
+        let status = if self.oom_killed() {
+            RunStatus::OutOfMemory
+        } else {
+            match exit_status {
+                subprocess::ExitStatus::Exited(code) => RunStatus::Exited(code as i32),
+                subprocess::ExitStatus::Signaled(signal) => RunStatus::Signaled(signal),
+                subprocess::ExitStatus::Other(code) => RunStatus::Exited(code),
+                subprocess::ExitStatus::Undetermined => RunStatus::Unknown,
+            }
+        };
+
+        Ok(OperationInfo {
+            stdout: stdout.unwrap_or_default(),
+            stderr: stderr.unwrap_or_default(),
+            status: Some(status),
+            ..Default::default()
+        })
+    }
+
+    /// Returns whether or not the container was killed for exceeding its
+    /// memory limit.
+    fn oom_killed(&self) -> bool {
+        let inspect = ProcessBuilder {
+            program: self.name.clone(),
+            args: vec![
+                String::from("inspect"),
+                String::from("--format"),
+                String::from("{{.State.OOMKilled}}"),
+                self.container.clone(),
+            ],
+            cwd: None,
+        };
+
+        match inspect.exec() {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == "true",
+            Err(_) => false,
+        }
+    }
+
+    /// Removes the (exited) container.
+    fn remove(&self) -> BifrostResult<()> {
+        let rm = ProcessBuilder {
+            program: self.name.clone(),
+            args: vec![
+                String::from("rm"),
+                String::from("--force"),
+                self.container.clone(),
+            ],
+            cwd: None,
+        };
+
+        if let Err(e) = rm.exec() {
+            failure::bail!(
+                "error: failed to remove container `{}` due to {}",
+                self.container,
+                e
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Removes a container when it goes out of scope, so that neither an error,
+/// an interrupt, nor a panic leaves it behind. While the guard is alive,
+/// Ctrl-C only reaches the engine (which stops the container) instead of
+/// killing bifrost.
+struct RunGuard<'a> {
+    runner: &'a ContainerRunner,
+    previous: libc::sighandler_t,
+}
+
+extern "C" fn on_interrupt(_: libc::c_int) {}
+
+impl<'a> RunGuard<'a> {
+    fn new(runner: &'a ContainerRunner) -> Self {
+        // A handler (unlike `SIG_IGN`) is reset in the engine process, so the
+        // engine still receives Ctrl-C.
+        let handler = on_interrupt as extern "C" fn(libc::c_int);
+        let previous = unsafe { libc::signal(libc::SIGINT, handler as libc::sighandler_t) };
+        RunGuard { runner, previous }
+    }
+}
+
+impl<'a> Drop for RunGuard<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::signal(libc::SIGINT, self.previous);
+        }
+        // Failing to clean up does not invalidate the run's output.
+        if let Err(e) = self.runner.remove() {
+            let _ = io::stderr().write_fmt(format_args!("warn: {}\n", e));
+        }
+    }
 }
 
 // Allow these tests to remain for documentation purposes. Eventually, they
@@ -254,11 +1339,294 @@ mod test {
             docker::start(&home_path)?;
             let code = _start_bifrost_container(&home_path)?;
             assert_eq!(Some(0i32), code);
-            docker::stop()?;
+            docker::stop(&home_path)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_resource_limits_validate() {
+        let limits = ResourceLimits {
+            cpus: Some(1.5),
+            memory: Some(String::from("2g")),
+            pids_limit: Some(256),
+        };
+        assert!(limits.validate().is_ok());
+
+        let limits = ResourceLimits {
+            memory: Some(String::from("two gigs")),
+            ..Default::default()
+        };
+        assert!(limits.validate().is_err());
+
+        let limits = ResourceLimits {
+            cpus: Some(0.0),
+            ..Default::default()
+        };
+        assert!(limits.validate().is_err());
+    }
+
+    #[test]
+    fn test_network_options_validate() {
+        let network = NetworkOptions {
+            mode: Some(String::from("bridge")),
+            ports: vec![
+                String::from("8080:80"),
+                String::from("127.0.0.1:5432:5432/tcp"),
+                String::from("3000"),
+            ],
+        };
+        assert!(network.validate().is_ok());
+
+        let network = NetworkOptions {
+            mode: Some(String::from("overlay")),
+            ports: vec![],
+        };
+        assert!(network.validate().is_err());
+
+        let network = NetworkOptions {
+            mode: Some(String::from("none")),
+            ports: vec![String::from("8080:80")],
+        };
+        assert!(network.validate().is_err());
+
+        for port in &["80:", "99999:80", "http", "0:80"] {
+            let network = NetworkOptions {
+                mode: None,
+                ports: vec![String::from(*port)],
+            };
+            assert!(network.validate().is_err(), "`{}` should be invalid", port);
         }
+    }
+
+    #[test]
+    fn test_realm_image_tag() {
+        let tag = realm_image_tag("My Realm", b"FROM rust:1.70\n");
+        assert!(tag.starts_with("bifrost-my_realm:"));
+        assert_eq!("bifrost-my_realm:".len() + 12, tag.len());
+
+        // The same Dockerfile reuses the tag; a changed one does not.
+        assert_eq!(tag, realm_image_tag("My Realm", b"FROM rust:1.70\n"));
+        assert_ne!(tag, realm_image_tag("My Realm", b"FROM rust:1.71\n"));
+    }
+
+    #[test]
+    fn test_repository() {
+        assert_eq!("debian", repository("debian:bookworm"));
+        assert_eq!("debian", repository("debian"));
+        assert_eq!("localhost:5000/app", repository("localhost:5000/app:1.0"));
+        assert_eq!("localhost:5000/app", repository("localhost:5000/app"));
+        assert_eq!("app", repository("app@sha256:0123"));
+    }
+
+    #[test]
+    fn test_image_builder_args() {
+        let image = ImageBuilder {
+            name: String::from("docker"),
+            tag: String::from("bifrost-test:0123456789ab"),
+            path: String::from("/home/test/realm"),
+            dockerfile: Some(String::from("/home/test/realm/Bifrost.Dockerfile")),
+        };
+        let left = vec![
+            "build",
+            "-t",
+            "bifrost-test:0123456789ab",
+            "-f",
+            "/home/test/realm/Bifrost.Dockerfile",
+            "/home/test/realm",
+        ];
+        assert_eq!(left, image.args());
+    }
+
+    #[test]
+    fn test_default_image_ignores_workspaces() -> BifrostResult<()> {
+        let home = std::env::temp_dir().join(format!("bifrost-default-image-{}", process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let dockerfile = dockerfile::global_path(&home);
+        let workspace = dockerfile.with_file_name("realm");
+        fs::create_dir_all(&workspace)?;
+        fs::write(&dockerfile, "FROM ubuntu:18.04\n")?;
+
+        let image = default_image(&home)?;
+        let hash = image.hash(&home)?;
+
+        // Loading (or changing) a workspace does not make the image stale.
+        fs::write(workspace.join("main.rs"), "fn main() {}")?;
+        assert_eq!(hash, image.hash(&home)?);
+
+        fs::write(&dockerfile, "FROM ubuntu:20.04\n")?;
+        assert_ne!(hash, image.hash(&home)?);
+
+        fs::remove_dir_all(&home)?;
         Ok(())
     }
 
+    #[test]
+    fn test_workspace_mount_validate() {
+        let mount = |target: &str, workdir: Option<&str>| WorkspaceMount {
+            source: PathBuf::from("tests").join("test_user"),
+            target: String::from(target),
+            workdir: workdir.map(String::from),
+        };
+
+        assert!(mount("/workspace", None).validate().is_ok());
+        assert!(mount("/src", Some("test_app_dir")).validate().is_ok());
+        assert!(mount("workspace", None).validate().is_err());
+        assert!(mount("/", None).validate().is_err());
+        assert!(mount("/work:space", None).validate().is_err());
+        assert!(mount("/workspace/../etc", None).validate().is_err());
+        let unloaded = WorkspaceMount {
+            source: PathBuf::new(),
+            ..mount("/workspace", None)
+        };
+        assert!(unloaded.validate().is_err());
+        assert!(mount("/workspace", Some("/test_app_dir"))
+            .validate()
+            .is_err());
+        assert!(mount("/workspace", Some("../test_dir")).validate().is_err());
+        assert!(mount("/workspace", Some("not@çtu@llyar34lp4th"))
+            .validate()
+            .is_err());
+
+        assert_eq!("/workspace", mount("/workspace/", None).workdir());
+        assert_eq!("/workspace", mount("/workspace", Some(".")).workdir());
+        assert_eq!(
+            "/workspace/test_app_dir",
+            mount("/workspace", Some("test_app_dir/")).workdir()
+        );
+    }
+
+    #[test]
+    fn test_mount_validate() {
+        let volume = |target: &str| Mount::Volume {
+            name: volume_name("test", "cache"),
+            realm: String::from("test"),
+            target: String::from(target),
+            read_only: false,
+        };
+
+        assert!(volume("/cache").validate("/bifrost").is_ok());
+        assert!(volume("/bifrostcache").validate("/bifrost").is_ok());
+        // Relative targets, targets at or within the workspace, and ancestors
+        // of the workspace are not allowed.
+        assert!(volume("cache").validate("/bifrost").is_err());
+        assert!(volume("/bifrost").validate("/bifrost").is_err());
+        assert!(volume("/bifrost/bifrost/cache")
+            .validate("/bifrost")
+            .is_err());
+        assert!(volume("/").validate("/bifrost").is_err());
+
+        let bind = Mount::Bind {
+            source: PathBuf::from("tests").join("not@çtu@llyar34lp4th"),
+            target: String::from("/data"),
+            read_only: true,
+        };
+        assert!(bind.validate("/bifrost").is_err());
+
+        let bind = Mount::Bind {
+            source: PathBuf::from("tests").join("test_dir"),
+            target: String::from("/data"),
+            read_only: true,
+        };
+        assert!(bind.validate("/bifrost").is_ok());
+        assert_eq!(
+            vec![
+                "--mount",
+                "type=bind,source=tests/test_dir,target=/data,readonly"
+            ],
+            bind.args()
+        );
+    }
+
+    #[test]
+    fn test_chown_volume_args() {
+        let args = chown_volume_args(
+            "bifrost-test-cargo",
+            "rust:1.70",
+            "/usr/local/cargo/registry",
+            "1000:1000",
+        );
+        let left = vec![
+            "run",
+            "--rm",
+            "--user",
+            "0:0",
+            "--mount",
+            "type=volume,source=bifrost-test-cargo,target=/usr/local/cargo/registry",
+            "rust:1.70",
+            "chown",
+            "-R",
+            "1000:1000",
+            "/usr/local/cargo/registry",
+        ];
+        assert_eq!(left, args);
+    }
+
+    #[test]
+    fn test_container_runner_args() {
+        let runner = ContainerRunner {
+            name: String::from("docker"),
+            image: String::from("bifrost:0.1"),
+            container: String::from("bifrost-test"),
+            workspace: WorkspaceMount {
+                source: PathBuf::from("/home/test/.bifrost/container/bifrost/test"),
+                target: String::from("/workspace"),
+                workdir: Some(String::from("src")),
+            },
+            env: vec![EnvVar {
+                name: String::from("API_TOKEN"),
+                value: String::from("hunter2"),
+                secret: true,
+            }],
+            user: None,
+            limits: ResourceLimits {
+                memory: Some(String::from("2g")),
+                ..Default::default()
+            },
+            network: NetworkOptions {
+                mode: None,
+                ports: vec![String::from("8080:80")],
+            },
+            mounts: vec![Mount::Volume {
+                name: volume_name("test", "cargo"),
+                realm: String::from("test"),
+                target: String::from("/usr/local/cargo/registry"),
+                read_only: false,
+            }],
+            command: String::from("cargo test"),
+        };
+
+        let args = runner.args();
+        assert!(!args.iter().any(|a| a.contains("hunter2")));
+
+        let left: Vec<&str> = vec![
+            "docker",
+            "run",
+            "-i",
+            "--name",
+            "bifrost-test",
+            "--volume",
+            "/home/test/.bifrost/container/bifrost/test:/workspace",
+            "--workdir",
+            "/workspace/src",
+            "--env",
+            "API_TOKEN",
+            "--mount",
+            "type=volume,source=bifrost-test-cargo,target=/usr/local/cargo/registry",
+            "--memory",
+            "2g",
+            "--memory-swap",
+            "2g",
+            "--publish",
+            "8080:80",
+            "bifrost:0.1",
+            "sh",
+            "-c",
+            "cargo test",
+        ];
+        assert_eq!(left, args);
+    }
+
     fn _test_docker_is_installed() -> BifrostResult<()> {
         // Will fail as long as Docker is not installed.
         assert_eq!(true, is_installed());
@@ -279,8 +1647,9 @@ mod test {
 
         let image = ImageBuilder {
             name: String::from("docker"),
-            tag: String::from("bifrost:0.1"),
+            tag: String::from(DEFAULT_IMAGE),
             path,
+            dockerfile: None,
         };
 
         assert!(image.build().is_ok());
@@ -0,0 +1,490 @@
+//! Structured editing of the bifrost Dockerfile.
+//!
+//! Packages installed through `bifrost image add` live in a section of the
+//! Dockerfile delimited by marker comments:
+//!
+//! ```dockerfile
+//! # bifrost:packages:begin
+//! # packages: curl git
+//! RUN apt-get update && apt-get install -y curl git && rm -rf /var/lib/apt/lists/*
+//! # bifrost:packages:end
+//! ```
+//!
+//! Everything outside of this section is left untouched, so the Dockerfile can
+//! still be edited by hand.
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::util::BifrostResult;
+
+const BEGIN: &str = "# bifrost:packages:begin";
+const END: &str = "# bifrost:packages:end";
+const PACKAGES: &str = "# packages:";
+
+/// Returns the path to the Dockerfile of the default bifrost image.
+pub fn global_path(home_path: &Path) -> PathBuf {
+    home_path
+        .join(".bifrost")
+        .join("container")
+        .join("bifrost")
+        .join("Dockerfile")
+}
+
+/// Returns the build context of the default bifrost image: a directory of its
+/// own, so that the workspaces loaded next to its Dockerfile are neither sent
+/// to the engine nor hashed.
+pub fn global_context(home_path: &Path) -> PathBuf {
+    home_path.join(".bifrost").join("container").join("image")
+}
+
+/// Returns the base image of the (last stage of a) Dockerfile, i.e. the image
+/// named by its last `FROM` instruction.
+pub fn base_image(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| l.to_uppercase().starts_with("FROM "))
+        .filter_map(|l| l.split_whitespace().skip(1).find(|w| !w.starts_with("--")))
+        .next_back()
+        .map(String::from)
+}
+
+/// The package managers of the base images bifrost knows how to install
+/// packages into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PackageManager {
+    Apt,
+    Apk,
+    Dnf,
+}
+
+impl PackageManager {
+    /// Detects the package manager from the (last) `FROM` instruction of a
+    /// Dockerfile, falling back to the package manager its `RUN` instructions
+    /// use. Official images tagged with an alpine variant (e.g.
+    /// `node:20-alpine`) use apk whatever their name.
+    pub fn detect(contents: &str) -> Option<PackageManager> {
+        let base = base_image(contents).map(|image| image.to_lowercase());
+
+        if let Some(image) = base {
+            // Strip the registry (if any), the digest and the tag.
+            let name = image.rsplit('/').next().unwrap_or(&image);
+            let name = name.split('@').next().unwrap_or(name);
+            let (name, tag) = name.split_once(':').unwrap_or((name, ""));
+            if tag.contains("alpine") {
+                return Some(PackageManager::Apk);
+            }
+            match name {
+                "ubuntu" | "debian" | "buildpack-deps" | "rust" | "gcc" | "golang" | "python"
+                | "node" => return Some(PackageManager::Apt),
+                "alpine" => return Some(PackageManager::Apk),
+                "fedora" | "centos" | "rockylinux" | "almalinux" | "amazonlinux" => {
+                    return Some(PackageManager::Dnf)
+                }
+                _ => (),
+            }
+        }
+
+        if contents.contains("apt-get") {
+            Some(PackageManager::Apt)
+        } else if contents.contains("apk add") {
+            Some(PackageManager::Apk)
+        } else if contents.contains("dnf install") || contents.contains("yum install") {
+            Some(PackageManager::Dnf)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the instruction that installs `packages`.
+    pub fn install(self, packages: &[String]) -> String {
+        let packages = packages.join(" ");
+        match self {
+            PackageManager::Apt => format!(
+                "RUN apt-get update && apt-get install -y {} && rm -rf /var/lib/apt/lists/*",
+                packages
+            ),
+            PackageManager::Apk => format!("RUN apk add --no-cache {}", packages),
+            PackageManager::Dnf => format!("RUN dnf install -y {} && dnf clean all", packages),
+        }
+    }
+}
+
+/// A Dockerfile whose package section can be edited.
+#[derive(Debug)]
+pub struct Dockerfile {
+    contents: String,
+}
+
+impl Dockerfile {
+    pub fn new(contents: String) -> Self {
+        Dockerfile { contents }
+    }
+
+    /// Returns the packages in the package section (sorted).
+    pub fn packages(&self) -> Vec<String> {
+        let mut in_section = false;
+        for line in self.contents.lines() {
+            let line = line.trim();
+            if line == BEGIN {
+                in_section = true;
+            } else if line == END {
+                break;
+            } else if in_section && line.starts_with(PACKAGES) {
+                return line[PACKAGES.len()..]
+                    .split_whitespace()
+                    .map(String::from)
+                    .collect();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Adds `packages` to the package section and returns the packages that
+    /// were not already present.
+    ///
+    /// # Errors
+    ///
+    /// If a package name is invalid or the package manager of the base image
+    /// cannot be detected, then this method returns an error.
+    pub fn add(&mut self, packages: &[String]) -> BifrostResult<Vec<String>> {
+        check_names(packages)?;
+
+        let mut current = self.packages();
+        let mut added = Vec::new();
+        for package in packages {
+            if !current.contains(package) && !added.contains(package) {
+                added.push(package.clone());
+            }
+        }
+
+        if !added.is_empty() {
+            current.extend(added.iter().cloned());
+            self.set_packages(current)?;
+        }
+        Ok(added)
+    }
+
+    /// Removes `packages` from the package section and returns the packages
+    /// that were present.
+    ///
+    /// # Errors
+    ///
+    /// If the package manager of the base image cannot be detected, then this
+    /// method returns an error.
+    pub fn remove(&mut self, packages: &[String]) -> BifrostResult<Vec<String>> {
+        let current = self.packages();
+        let removed: Vec<String> = current
+            .iter()
+            .filter(|p| packages.contains(p))
+            .cloned()
+            .collect();
+
+        if !removed.is_empty() {
+            let remaining = current
+                .into_iter()
+                .filter(|p| !removed.contains(p))
+                .collect();
+            self.set_packages(remaining)?;
+        }
+        Ok(removed)
+    }
+
+    /// Returns the contents of the Dockerfile.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    /// Replaces the package section with one installing `packages`. The
+    /// section is removed altogether when there are no packages.
+    fn set_packages(&mut self, mut packages: Vec<String>) -> BifrostResult<()> {
+        packages.sort();
+        packages.dedup();
+
+        let section = if packages.is_empty() {
+            String::new()
+        } else {
+            let manager = match PackageManager::detect(&self.contents) {
+                Some(manager) => manager,
+                None => failure::bail!(
+                    "error: could not detect the package manager of the base image \
+                     (apt, apk, and dnf are supported)"
+                ),
+            };
+            format!(
+                "{}\n{} {}\n{}\n{}\n",
+                BEGIN,
+                PACKAGES,
+                packages.join(" "),
+                manager.install(&packages),
+                END
+            )
+        };
+
+        let mut before = String::new();
+        let mut after = String::new();
+        let mut state = 0;
+        for line in self.contents.lines() {
+            match state {
+                0 if line.trim() == BEGIN => state = 1,
+                1 if line.trim() == END => state = 2,
+                0 => {
+                    before.push_str(line);
+                    before.push('\n');
+                }
+                1 => (),
+                _ => {
+                    after.push_str(line);
+                    after.push('\n');
+                }
+            }
+        }
+
+        // The section goes at the end of the file the first time around.
+        if state == 0 && !section.is_empty() && !before.ends_with("\n\n") {
+            before.push('\n');
+        }
+        self.contents = format!("{}{}{}", before, section, after);
+        Ok(())
+    }
+}
+
+/// The base image of the Dockerfile written by `bifrost setup`.
+pub const DEFAULT_BASE: &str = "debian:bookworm";
+
+/// The names of the built-in templates `bifrost setup --template` accepts.
+pub const TEMPLATES: &[&str] = &["minimal", "cpp", "rust", "python", "node"];
+
+/// The built-in Dockerfile templates.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Template {
+    /// Just enough to fetch things over https.
+    Minimal,
+    /// A C/C++ toolchain (the default).
+    #[default]
+    Cpp,
+    /// A C toolchain and Rust installed with rustup.
+    Rust,
+    /// Python 3 with pip and venv.
+    Python,
+    /// Node.js with npm.
+    Node,
+}
+
+impl FromStr for Template {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> BifrostResult<Self> {
+        match s {
+            "minimal" => Ok(Template::Minimal),
+            "cpp" => Ok(Template::Cpp),
+            "rust" => Ok(Template::Rust),
+            "python" => Ok(Template::Python),
+            "node" => Ok(Template::Node),
+            _ => failure::bail!(
+                "error: unknown template `{}` (expected one of: {})",
+                s,
+                TEMPLATES.join(", ")
+            ),
+        }
+    }
+}
+
+impl Template {
+    /// Returns the packages the template installs with `manager`.
+    fn packages(self, manager: PackageManager) -> Vec<&'static str> {
+        let mut packages = vec!["ca-certificates", "curl"];
+        packages.extend(match (self, manager) {
+            (Template::Minimal, _) => vec![],
+            (Template::Cpp, PackageManager::Apt) => vec!["build-essential", "cmake", "gdb"],
+            (Template::Cpp, PackageManager::Apk) => vec!["build-base", "cmake", "gdb"],
+            (Template::Cpp, PackageManager::Dnf) => vec!["gcc", "gcc-c++", "make", "cmake", "gdb"],
+            (Template::Rust, PackageManager::Apt) => vec!["build-essential"],
+            (Template::Rust, PackageManager::Apk) => vec!["build-base"],
+            (Template::Rust, PackageManager::Dnf) => vec!["gcc", "make"],
+            (Template::Python, PackageManager::Apt) => {
+                vec!["python3", "python3-pip", "python3-venv"]
+            }
+            (Template::Python, PackageManager::Apk) => vec!["python3", "py3-pip"],
+            (Template::Python, PackageManager::Dnf) => vec!["python3", "python3-pip"],
+            (Template::Node, _) => vec!["nodejs", "npm"],
+        });
+        packages
+    }
+
+    /// Returns the instructions that follow the package section.
+    fn extra(self) -> &'static str {
+        match self {
+            // Installed outside of $HOME so that any user can run it.
+            Template::Rust => {
+                "
+ENV RUSTUP_HOME=/usr/local/rustup CARGO_HOME=/usr/local/cargo PATH=/usr/local/cargo/bin:$PATH
+RUN curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y --profile minimal \\
+    && chmod -R a+w $RUSTUP_HOME $CARGO_HOME
+"
+            }
+            _ => "",
+        }
+    }
+
+    /// Returns the contents of a Dockerfile built from `base` with this
+    /// template. The template's packages are written to the package section so
+    /// they can be managed with `bifrost image add/remove` afterwards.
+    ///
+    /// # Errors
+    ///
+    /// If `base` is not a valid image reference or its package manager cannot
+    /// be detected, then this method returns an error.
+    pub fn dockerfile(self, base: &str) -> BifrostResult<String> {
+        let re = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9._/:@-]*$")?;
+        if !re.is_match(base) {
+            failure::bail!("error: invalid base image `{}`", base);
+        }
+
+        let mut dockerfile = Dockerfile::new(format!("FROM {}\n", base));
+        let manager = match PackageManager::detect(dockerfile.contents()) {
+            Some(manager) => manager,
+            None => failure::bail!(
+                "error: could not detect the package manager of base image `{}` \
+                 (apt, apk, and dnf are supported); use `--dockerfile` instead",
+                base
+            ),
+        };
+
+        let packages: Vec<String> = self
+            .packages(manager)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        dockerfile.add(&packages)?;
+
+        Ok(format!("{}{}", dockerfile.contents(), self.extra()))
+    }
+}
+
+/// Checks that each package name is something a package manager accepts
+/// (optionally with a version, e.g. `curl=7.68.0-1`) and cannot break out of
+/// the `RUN` instruction.
+fn check_names(packages: &[String]) -> BifrostResult<()> {
+    let re = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9.+_:=~-]*$")?;
+    for package in packages {
+        if !re.is_match(package) {
+            failure::bail!("error: invalid package name `{}`", package);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UBUNTU: &str = "FROM ubuntu:16.04\n\nRUN apt-get update\n";
+
+    fn to_vec(packages: &[&str]) -> Vec<String> {
+        packages.iter().map(|p| String::from(*p)).collect()
+    }
+
+    #[test]
+    fn test_base_image() {
+        assert_eq!(Some(String::from("ubuntu:16.04")), base_image(UBUNTU));
+        assert_eq!(
+            Some(String::from("debian:bookworm")),
+            base_image("FROM rust:1.70 AS build\nfrom --platform=linux/amd64 debian:bookworm\n")
+        );
+        assert_eq!(None, base_image("RUN true\n"));
+    }
+
+    #[test]
+    fn test_detect_package_manager() {
+        let detect = PackageManager::detect;
+        assert_eq!(Some(PackageManager::Apt), detect(UBUNTU));
+        assert_eq!(Some(PackageManager::Apk), detect("FROM alpine:3.18\n"));
+        assert_eq!(Some(PackageManager::Apk), detect("FROM node:20-alpine\n"));
+        assert_eq!(Some(PackageManager::Apk), detect("FROM python:3-alpine\n"));
+        assert_eq!(
+            Some(PackageManager::Apk),
+            detect("FROM docker.io/library/rust:1.70-alpine3.18 AS build\n")
+        );
+        assert_eq!(Some(PackageManager::Apt), detect("FROM node:20-bookworm\n"));
+        assert_eq!(
+            Some(PackageManager::Dnf),
+            detect("FROM registry.fedoraproject.org/fedora:38\n")
+        );
+        assert_eq!(
+            Some(PackageManager::Apk),
+            detect("FROM example.com/base\nRUN apk add git\n")
+        );
+        assert_eq!(None, detect("FROM scratch\n"));
+    }
+
+    #[test]
+    fn test_add_and_remove_packages() {
+        let mut dockerfile = Dockerfile::new(String::from(UBUNTU));
+        assert!(dockerfile.packages().is_empty());
+
+        let added = dockerfile.add(&to_vec(&["git", "curl"])).unwrap();
+        assert_eq!(to_vec(&["git", "curl"]), added);
+        assert_eq!(to_vec(&["curl", "git"]), dockerfile.packages());
+        assert!(dockerfile.contents().starts_with(UBUNTU));
+        assert!(dockerfile
+            .contents()
+            .contains("apt-get install -y curl git"));
+
+        // Adding is idempotent.
+        let contents = dockerfile.contents().to_owned();
+        assert!(dockerfile.add(&to_vec(&["git"])).unwrap().is_empty());
+        assert_eq!(contents, dockerfile.contents());
+
+        let removed = dockerfile.remove(&to_vec(&["git", "vim"])).unwrap();
+        assert_eq!(to_vec(&["git"]), removed);
+        assert_eq!(to_vec(&["curl"]), dockerfile.packages());
+
+        // Removing the last package removes the section.
+        dockerfile.remove(&to_vec(&["curl"])).unwrap();
+        assert!(!dockerfile.contents().contains(BEGIN));
+        assert!(dockerfile.contents().starts_with(UBUNTU));
+    }
+
+    #[test]
+    fn test_template_dockerfile() -> BifrostResult<()> {
+        let contents = Template::default().dockerfile(DEFAULT_BASE)?;
+        assert!(contents.starts_with("FROM debian:bookworm\n"));
+        let dockerfile = Dockerfile::new(contents);
+        assert!(dockerfile
+            .packages()
+            .contains(&String::from("build-essential")));
+
+        let contents = "python".parse::<Template>()?.dockerfile("alpine:3.18")?;
+        assert!(contents.contains("apk add --no-cache"));
+        assert!(Dockerfile::new(contents)
+            .packages()
+            .contains(&String::from("py3-pip")));
+
+        let contents = Template::Rust.dockerfile("fedora:38")?;
+        assert!(contents.contains("dnf install -y"));
+        assert!(contents.contains("sh.rustup.rs"));
+
+        assert!("java".parse::<Template>().is_err());
+        assert!(Template::Minimal.dockerfile("scratch").is_err());
+        assert!(Template::Minimal
+            .dockerfile("debian\nRUN rm -rf /")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_invalid_package() {
+        let mut dockerfile = Dockerfile::new(String::from(UBUNTU));
+        assert!(dockerfile.add(&to_vec(&["curl; rm -rf /"])).is_err());
+        // `<` and `>` would be shell redirections within the `RUN` instruction.
+        assert!(dockerfile.add(&to_vec(&["curl>/etc/passwd"])).is_err());
+        assert!(dockerfile.add(&to_vec(&["curl<input"])).is_err());
+        assert!(dockerfile.add(&to_vec(&["vim"])).is_ok());
+
+        let mut dockerfile = Dockerfile::new(String::from("FROM scratch\n"));
+        assert!(dockerfile.add(&to_vec(&["vim"])).is_err());
+    }
+}
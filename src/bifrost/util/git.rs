@@ -0,0 +1,144 @@
+//! Reading what git tracks in a realm (`bifrost load --git` and `--rev`).
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::util::{BifrostResult, ProcessBuilder};
+
+fn git(dir: &Path, args: &[&str]) -> ProcessBuilder {
+    ProcessBuilder {
+        program: String::from("git"),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        cwd: Some(dir.to_path_buf()),
+    }
+}
+
+/// Returns the absolute paths of the files below `dir` that are tracked in
+/// the index of its git repository.
+///
+/// # Errors
+///
+/// If `dir` is not in a git repository, then this function returns an error.
+pub fn tracked_files(dir: &Path) -> BifrostResult<HashSet<PathBuf>> {
+    let output = match git(dir, &["ls-files", "-z", "--cached"]).exec() {
+        Ok(output) => output,
+        Err(e) => failure::bail!(
+            "error: could not list the files git tracks in `{}` (is it a git repository?): {}",
+            dir.display(),
+            e
+        ),
+    };
+
+    Ok(output
+        .stdout
+        .split(|b| *b == 0)
+        .filter(|p| !p.is_empty())
+        .map(|p| dir.join(String::from_utf8_lossy(p).as_ref()))
+        .collect())
+}
+
+/// Returns the id of the commit that `rev` (e.g. a branch, tag, or
+/// abbreviated id) names in the git repository of `dir`.
+///
+/// # Errors
+///
+/// If `rev` does not name a commit, then this function returns an error.
+pub fn resolve(dir: &Path, rev: &str) -> BifrostResult<String> {
+    let commit = format!("{}^{{commit}}", rev);
+    match git(dir, &["rev-parse", "--verify", "--quiet", &commit]).exec() {
+        Ok(output) => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        Err(_) => failure::bail!(
+            "error: `{}` is not a commit of the realm's git repository",
+            rev
+        ),
+    }
+}
+
+/// Returns the id of the commit checked out in the git repository of `dir`
+/// (if any).
+pub fn head(dir: &Path) -> Option<String> {
+    resolve(dir, "HEAD").ok()
+}
+
+/// Writes the files below `dir` as of `commit` to `to` (which must exist).
+///
+/// # Errors
+///
+/// If the tree cannot be archived or extracted, then this function returns an
+/// error.
+pub fn archive(dir: &Path, commit: &str, to: &Path) -> BifrostResult<()> {
+    let mut archive = git(dir, &["archive", "--format=tar", commit])
+        .build_command()
+        .stdout(Stdio::piped())
+        .spawn()?;
+    let stdout = archive
+        .stdout
+        .take()
+        .expect("BUG: `git archive` should have a piped stdout");
+
+    let extracted = Command::new("tar")
+        .arg("-x")
+        .arg("-C")
+        .arg(to)
+        .stdin(stdout)
+        .status()?;
+    let archived = archive.wait()?;
+
+    if !archived.success() || !extracted.success() {
+        failure::bail!(
+            "error: could not extract `{}` from the realm's git repository",
+            commit
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    #[test]
+    fn test_tracked_files_and_archive() -> BifrostResult<()> {
+        let tmp = env::temp_dir().join(format!("bifrost-git-{}", process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        let repo = tmp.join("repo");
+        fs::create_dir_all(repo.join("src"))?;
+        let run = |args: &[&str]| git(&repo, args).exec().map(|_| ());
+
+        run(&["init", "--quiet"])?;
+        fs::write(repo.join("src").join("main.c"), "v1")?;
+        run(&["add", "src/main.c"])?;
+        run(&[
+            "-c",
+            "user.name=bifrost",
+            "-c",
+            "user.email=bifrost@localhost",
+            "commit",
+            "--quiet",
+            "--message=v1",
+        ])?;
+        fs::write(repo.join("src").join("main.c"), "v2")?;
+        fs::write(repo.join("untracked.txt"), "")?;
+
+        let tracked = tracked_files(&repo)?;
+        assert_eq!(1, tracked.len());
+        assert!(tracked.contains(&repo.join("src/main.c")));
+
+        let commit = resolve(&repo, "HEAD")?;
+        assert_eq!(40, commit.len());
+        assert_eq!(Some(commit.clone()), head(&repo));
+        assert!(resolve(&repo, "no-such-branch").is_err());
+
+        let checkout = tmp.join("checkout");
+        fs::create_dir_all(&checkout)?;
+        archive(&repo, &commit, &checkout)?;
+        assert_eq!("v1", fs::read_to_string(checkout.join("src/main.c"))?);
+        assert!(!checkout.join("untracked.txt").exists());
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+}
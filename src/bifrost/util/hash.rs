@@ -0,0 +1,385 @@
+//! Content hashing utilities.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use filetime::FileTime;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use walkdir::{DirEntry, WalkDir};
+
+use crate::core::config::SECRETS_FILE;
+use crate::core::hofund;
+use crate::core::lock::LOCK_FILE;
+use crate::util::BifrostResult;
+
+/// What is never part of a build context (relative to its root), whatever its
+/// `.dockerignore` says: what bifrost writes next to the Dockerfile (so that
+/// pinning an image does not change its context), the realm's secrets, build
+/// output, and git's own files.
+const CONTEXT_EXCLUDED: &[&str] = &[LOCK_FILE, SECRETS_FILE, "target/", ".git"];
+
+/// Returns the hex encoded SHA-256 digest of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Returns the hex encoded SHA-256 digest of the contents of the file at
+/// `path` without reading it into memory.
+///
+/// # Errors
+///
+/// If the file cannot be read, then this function returns an error.
+pub fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.result()))
+}
+
+/// Copies the file at `from` to `to` (including its permissions, as
+/// `fs::copy` does) and returns the number of bytes copied and the hex encoded
+/// SHA-256 digest of what was written.
+///
+/// # Errors
+///
+/// If the file cannot be read or written, then this function returns an error.
+pub fn copy_sha256(from: &Path, to: &Path) -> io::Result<(u64, String)> {
+    let mut source = File::open(from)?;
+    let mut writer = HashingWriter {
+        inner: File::create(to)?,
+        hasher: Sha256::new(),
+    };
+    let n = io::copy(&mut source, &mut writer)?;
+    writer.flush()?;
+    fs::set_permissions(to, source.metadata()?.permissions())?;
+    Ok((n, format!("{:x}", writer.hasher.result())))
+}
+
+/// A writer that hashes what it writes.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.input(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// The content hashes of files, by their absolute path, along with the size
+/// and modification time they had when they were hashed. Only files whose
+/// size or modification time changed since are read again.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct HashCache {
+    #[serde(default)]
+    files: BTreeMap<String, CachedHash>,
+    /// The files looked up since the cache was loaded (the rest are dropped
+    /// when it is saved).
+    #[serde(skip)]
+    used: BTreeSet<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct CachedHash {
+    len: u64,
+    mtime: i64,
+    nanos: u32,
+    sha256: String,
+}
+
+impl HashCache {
+    /// Loads the cache at `path`; a missing or unreadable cache is empty.
+    pub fn load(path: &Path) -> Self {
+        hofund::read(path)
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Writes the entries of the files looked up since the cache was loaded.
+    ///
+    /// # Errors
+    ///
+    /// If the cache cannot be written, then this method returns an error.
+    pub fn save(&mut self, path: &Path) -> BifrostResult<()> {
+        let used = &self.used;
+        self.files.retain(|file, _| used.contains(file));
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        hofund::write(path, toml::to_string(self)?.as_bytes())
+    }
+
+    /// Returns the hex encoded SHA-256 digest of the contents of the file at
+    /// `path`, reading it only if it changed since it was last hashed.
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read, then this method returns an error.
+    pub fn file_sha256(&mut self, path: &Path) -> io::Result<String> {
+        let meta = fs::metadata(path)?;
+        let mtime = FileTime::from_last_modification_time(&meta);
+        let key = path.to_string_lossy().into_owned();
+        self.used.insert(key.clone());
+
+        let cached = self.files.get(&key).filter(|c| {
+            c.len == meta.len() && c.mtime == mtime.unix_seconds() && c.nanos == mtime.nanoseconds()
+        });
+        if let Some(cached) = cached {
+            return Ok(cached.sha256.clone());
+        }
+
+        let sha256 = file_sha256(path)?;
+        self.files.insert(
+            key,
+            CachedHash {
+                len: meta.len(),
+                mtime: mtime.unix_seconds(),
+                nanos: mtime.nanoseconds(),
+                sha256: sha256.clone(),
+            },
+        );
+        Ok(sha256)
+    }
+}
+
+/// Returns the path of the `HashCache` of the build `context`.
+pub fn cache_path(home_path: &Path, context: &Path) -> PathBuf {
+    let name = sha256_hex(context.to_string_lossy().as_bytes());
+    home_path
+        .join(".bifrost")
+        .join("container")
+        .join("hashes")
+        .join(format!("{}.toml", &name[..16]))
+}
+
+/// Returns the hex encoded SHA-256 digest of an image's build context: every
+/// entry of [`context_entries`](fn.context_entries.html) (paths and content
+/// hashes) and, if it lives elsewhere, the Dockerfile. Content hashes are
+/// taken from (and added to) the `cache`.
+///
+/// # Errors
+///
+/// If the context cannot be walked or a file cannot be read, then this
+/// function returns an error.
+pub fn build_context(
+    context: &Path,
+    dockerfile: Option<&Path>,
+    cache: &mut HashCache,
+) -> BifrostResult<String> {
+    let mut hasher = Sha256::new();
+
+    if let Some(dockerfile) = dockerfile {
+        hasher.input(b"Dockerfile\0");
+        hasher.input(fs::read(dockerfile)?);
+        hasher.input(b"\0");
+    }
+
+    for entry in context_entries(context)? {
+        let relative = entry.path().strip_prefix(context)?;
+        let file_type = entry.file_type();
+
+        if file_type.is_file() {
+            hasher.input(relative.to_string_lossy().as_bytes());
+            hasher.input(b"\0");
+            hasher.input(cache.file_sha256(entry.path())?.as_bytes());
+            hasher.input(b"\0");
+        } else if file_type.is_symlink() {
+            hasher.input(relative.to_string_lossy().as_bytes());
+            hasher.input(b"\0->");
+            hasher.input(fs::read_link(entry.path())?.to_string_lossy().as_bytes());
+            hasher.input(b"\0");
+        }
+    }
+
+    Ok(format!("{:x}", hasher.result()))
+}
+
+/// Returns the entries of the build `context` (itself first, parents before
+/// their children, sorted by name) that its `.dockerignore` does not exclude,
+/// leaving out what is never part of a context.
+///
+/// # Errors
+///
+/// If the context cannot be walked or its `.dockerignore` is invalid, then
+/// this function returns an error.
+pub fn context_entries(context: &Path) -> BifrostResult<Vec<DirEntry>> {
+    let ignore = dockerignore(context)?;
+    let walker = WalkDir::new(context)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|e| {
+            let relative = e.path().strip_prefix(context).unwrap_or(e.path());
+            relative.as_os_str().is_empty()
+                || !ignore.matched(relative, e.file_type().is_dir()).is_ignore()
+        });
+
+    let mut entries = vec![];
+    for entry in walker {
+        entries.push(entry?);
+    }
+    Ok(entries)
+}
+
+/// Builds a matcher from the `.dockerignore` of `context` (if it has one) and
+/// `CONTEXT_EXCLUDED`. Unlike `.gitignore` patterns, `.dockerignore` patterns
+/// are relative to the root of the context, so each pattern is anchored.
+fn dockerignore(context: &Path) -> BifrostResult<Gitignore> {
+    let mut builder = GitignoreBuilder::new(context);
+
+    if let Ok(contents) = fs::read_to_string(context.join(".dockerignore")) {
+        for line in contents.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (negate, pattern) = match line.strip_prefix('!') {
+                Some(pattern) => ("!", pattern),
+                None => ("", line),
+            };
+            let pattern = pattern.trim_start_matches('/');
+            builder.add_line(None, &format!("{}/{}", negate, pattern))?;
+        }
+    }
+    // Last, so that `.dockerignore` cannot take them back.
+    for excluded in CONTEXT_EXCLUDED {
+        builder.add_line(None, &format!("/{}", excluded))?;
+    }
+
+    Ok(builder.build()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            sha256_hex(b"")
+        );
+    }
+
+    #[test]
+    fn test_file_sha256() -> BifrostResult<()> {
+        let path = PathBuf::from("tests").join("test_dir").join("file.txt");
+        assert_eq!(sha256_hex(&fs::read(&path)?), file_sha256(&path)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_sha256() -> BifrostResult<()> {
+        let from = PathBuf::from("tests").join("test_dir").join("file.txt");
+        let to = std::env::temp_dir().join(format!("bifrost-copy-{}", std::process::id()));
+        let (n, hash) = copy_sha256(&from, &to)?;
+        assert_eq!(fs::metadata(&from)?.len(), n);
+        assert_eq!(file_sha256(&from)?, hash);
+        assert_eq!(fs::read(&from)?, fs::read(&to)?);
+        fs::remove_file(&to)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_context() -> BifrostResult<()> {
+        let context = PathBuf::from("tests").join("test_dir");
+        let mut cache = HashCache::default();
+        let hash = build_context(&context, None, &mut cache)?;
+        assert_eq!(hash, build_context(&context, None, &mut cache)?);
+        assert_eq!(
+            hash,
+            build_context(&context, None, &mut HashCache::default())?
+        );
+
+        // An external Dockerfile is part of the context.
+        let dockerfile = PathBuf::from("tests")
+            .join("test_user")
+            .join("test_app_dir")
+            .join("cat.txt");
+        assert_ne!(
+            hash,
+            build_context(&context, Some(&dockerfile), &mut cache)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_cache() -> BifrostResult<()> {
+        let tmp = std::env::temp_dir().join(format!("bifrost-hash-cache-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp)?;
+        let (a, b) = (tmp.join("a.txt"), tmp.join("b.txt"));
+        fs::write(&a, "a")?;
+        fs::write(&b, "b")?;
+
+        let mut cache = HashCache::default();
+        assert_eq!(file_sha256(&a)?, cache.file_sha256(&a)?);
+        cache.file_sha256(&b)?;
+        let path = tmp.join("hashes.toml");
+        cache.save(&path)?;
+
+        // Only files that were looked up are kept, and a cached hash is used
+        // as long as the file's size and modification time do not change.
+        let mut cache = HashCache::load(&path);
+        assert_eq!(2, cache.files.len());
+        cache
+            .files
+            .get_mut(&a.to_string_lossy().into_owned())
+            .unwrap()
+            .sha256 = String::from("cached");
+        assert_eq!("cached", cache.file_sha256(&a)?);
+        cache.save(&path)?;
+        assert_eq!(1, HashCache::load(&path).files.len());
+
+        fs::write(&a, "changed")?;
+        assert_eq!(file_sha256(&a)?, HashCache::load(&path).file_sha256(&a)?);
+
+        fs::remove_dir_all(&tmp)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_entries() -> BifrostResult<()> {
+        let context =
+            std::env::temp_dir().join(format!("bifrost-context-entries-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&context);
+        fs::create_dir_all(context.join("src"))?;
+        fs::create_dir_all(context.join("logs"))?;
+        fs::write(context.join("src").join("main.rs"), "fn main() {}")?;
+        fs::write(context.join("logs").join("run.log"), "")?;
+        fs::write(context.join(SECRETS_FILE), "[env]\n")?;
+        fs::write(context.join(LOCK_FILE), "")?;
+        fs::create_dir_all(context.join("target"))?;
+        fs::create_dir_all(context.join(".git"))?;
+        fs::write(
+            context.join(".dockerignore"),
+            format!("logs\n!{}\n", SECRETS_FILE),
+        )?;
+
+        let entries: Vec<PathBuf> = context_entries(&context)?
+            .iter()
+            .map(|e| e.path().strip_prefix(&context).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            vec![
+                PathBuf::new(),
+                PathBuf::from(".dockerignore"),
+                PathBuf::from("src"),
+                PathBuf::from("src/main.rs"),
+            ],
+            entries
+        );
+
+        fs::remove_dir_all(&context)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! The inventory of images bifrost has built, kept at `~/.bifrost/images.toml`.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::core::hofund;
+use crate::util::BifrostResult;
+
+/// Images built by bifrost keyed by their tag.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Inventory {
+    #[serde(default)]
+    images: BTreeMap<String, ImageRecord>,
+}
+
+/// What bifrost knows about an image it has built.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ImageRecord {
+    /// The hash of the Dockerfile and build context the image was built from.
+    pub hash: String,
+    /// The engine's id (digest) of the image.
+    #[serde(default)]
+    pub digest: String,
+    /// When the image was built (seconds since the unix epoch).
+    #[serde(default)]
+    pub built: u64,
+    /// The realms whose runs use the image.
+    #[serde(default)]
+    pub realms: Vec<String>,
+    /// The ids of the images that earlier builds gave the tag, which are left
+    /// behind untagged until they are pruned.
+    #[serde(default)]
+    pub superseded: Vec<String>,
+}
+
+impl ImageRecord {
+    /// Returns a record of an image built just now from `hash`.
+    pub fn new(hash: String, digest: String) -> Self {
+        let built = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        ImageRecord {
+            hash,
+            digest,
+            built,
+            realms: Vec::new(),
+            superseded: Vec::new(),
+        }
+    }
+}
+
+impl Inventory {
+    /// Returns the path of the inventory.
+    pub fn path(home_path: &Path) -> PathBuf {
+        home_path.join(".bifrost").join("images.toml")
+    }
+
+    /// Loads the inventory; a missing inventory is empty.
+    ///
+    /// # Errors
+    ///
+    /// If the inventory exists but cannot be read or parsed, then this
+    /// function returns an error.
+    pub fn load(home_path: &Path) -> BifrostResult<Self> {
+        let path = Inventory::path(home_path);
+        if fs::metadata(&path).is_err() {
+            return Ok(Inventory::default());
+        }
+
+        match toml::from_str(&hofund::read(&path)?) {
+            Ok(inventory) => Ok(inventory),
+            Err(e) => failure::bail!(
+                "error: could not parse image inventory `{}` due to {}",
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    /// Writes the inventory.
+    pub fn save(&self, home_path: &Path) -> BifrostResult<()> {
+        let contents = toml::to_string(self)?;
+        hofund::write(&Inventory::path(home_path), contents.as_bytes())
+    }
+
+    /// Returns the record of the image tagged `tag`.
+    pub fn get(&self, tag: &str) -> Option<&ImageRecord> {
+        self.images.get(tag)
+    }
+
+    /// Returns every record keyed by tag.
+    pub fn images(&self) -> &BTreeMap<String, ImageRecord> {
+        &self.images
+    }
+
+    /// Records the image tagged `tag`. Realms that already used an image with
+    /// the same tag keep using it, and the image it replaces is remembered as
+    /// superseded.
+    pub fn insert(&mut self, tag: &str, mut record: ImageRecord) {
+        if let Some(previous) = self.images.remove(tag) {
+            record.realms = previous.realms;
+            record.superseded = previous.superseded;
+            let replaced = !previous.digest.is_empty() && previous.digest != record.digest;
+            if replaced && !record.superseded.contains(&previous.digest) {
+                record.superseded.push(previous.digest);
+            }
+        }
+        self.images.insert(String::from(tag), record);
+    }
+
+    /// Forgets the superseded image `id` of the image tagged `tag` (e.g. once
+    /// it has been removed).
+    pub fn forget_superseded(&mut self, tag: &str, id: &str) {
+        if let Some(record) = self.images.get_mut(tag) {
+            record.superseded.retain(|s| s != id);
+        }
+    }
+
+    /// Removes the record of the image tagged `tag`.
+    pub fn remove(&mut self, tag: &str) -> Option<ImageRecord> {
+        self.images.remove(tag)
+    }
+
+    /// Records that `realm` now uses the image tagged `tag` (and no other).
+    /// Returns whether or not the inventory changed.
+    pub fn reference(&mut self, tag: &str, realm: &str) -> bool {
+        let mut changed = false;
+        for (t, record) in self.images.iter_mut() {
+            let uses = record.realms.iter().any(|r| r == realm);
+            if t == tag && !uses {
+                record.realms.push(String::from(realm));
+                record.realms.sort();
+                changed = true;
+            } else if t != tag && uses {
+                record.realms.retain(|r| r != realm);
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// Returns the tags of images no live realm uses, except those in `keep`.
+    pub fn unreferenced<F>(&self, keep: &[&str], is_live: F) -> Vec<String>
+    where
+        F: Fn(&str) -> bool,
+    {
+        self.images
+            .iter()
+            .filter(|(tag, _)| !keep.contains(&tag.as_str()))
+            .filter(|(_, record)| !record.realms.iter().any(|r| is_live(r)))
+            .map(|(tag, _)| tag.clone())
+            .collect()
+    }
+}
+
+/// Formats seconds since the unix epoch as a UTC date and time
+/// (e.g. `2019-07-11 18:24:30`).
+pub fn format_timestamp(secs: u64) -> String {
+    // Days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+/// Formats a number of bytes for humans (e.g. `1.5 GB`).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "kB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_inventory_round_trip() -> BifrostResult<()> {
+        let home_path = PathBuf::from("tests").join("test_user");
+        assert!(Inventory::load(&home_path)?.get("bifrost:0.1").is_none());
+
+        let mut inventory = Inventory::default();
+        let record = ImageRecord::new(String::from("0123456789abcdef"), String::from("sha256:01"));
+        inventory.insert("bifrost:0.1", record.clone());
+
+        let contents = toml::to_string(&inventory)?;
+        let inventory: Inventory = toml::from_str(&contents)?;
+        assert_eq!(Some(&record), inventory.get("bifrost:0.1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inventory_references() {
+        let mut inventory = Inventory::default();
+        let record = || ImageRecord::new(String::from("hash"), String::from("sha256:01"));
+        inventory.insert("bifrost:0.1", record());
+        inventory.insert("bifrost-app:aaaaaaaaaaaa", record());
+        inventory.insert("bifrost-app:bbbbbbbbbbbb", record());
+
+        assert!(inventory.reference("bifrost-app:aaaaaaaaaaaa", "app"));
+        assert!(!inventory.reference("bifrost-app:aaaaaaaaaaaa", "app"));
+
+        // A rebuild keeps the realms of the tag it replaces.
+        inventory.insert("bifrost-app:aaaaaaaaaaaa", record());
+        let live = |realm: &str| realm == "app";
+        assert_eq!(
+            vec![String::from("bifrost-app:bbbbbbbbbbbb")],
+            inventory.unreferenced(&["bifrost:0.1"], live)
+        );
+
+        // Moving to a new image leaves the old one unreferenced.
+        assert!(inventory.reference("bifrost-app:bbbbbbbbbbbb", "app"));
+        assert_eq!(
+            vec![String::from("bifrost-app:aaaaaaaaaaaa")],
+            inventory.unreferenced(&["bifrost:0.1"], live)
+        );
+
+        // Realms that no longer exist do not keep images around.
+        assert_eq!(2, inventory.unreferenced(&["bifrost:0.1"], |_| false).len());
+    }
+
+    #[test]
+    fn test_inventory_superseded() {
+        let mut inventory = Inventory::default();
+        let record = |digest: &str| ImageRecord::new(String::from("hash"), String::from(digest));
+        inventory.insert("bifrost:0.1", record("sha256:01"));
+        inventory.insert("bifrost:0.1", record("sha256:01"));
+        assert!(inventory.get("bifrost:0.1").unwrap().superseded.is_empty());
+
+        // Rebuilding under the same tag leaves the previous image behind.
+        inventory.insert("bifrost:0.1", record("sha256:02"));
+        inventory.insert("bifrost:0.1", record("sha256:03"));
+        assert_eq!(
+            vec![String::from("sha256:01"), String::from("sha256:02")],
+            inventory.get("bifrost:0.1").unwrap().superseded
+        );
+
+        inventory.forget_superseded("bifrost:0.1", "sha256:01");
+        assert_eq!(
+            vec![String::from("sha256:02")],
+            inventory.get("bifrost:0.1").unwrap().superseded
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!("1970-01-01 00:00:00", format_timestamp(0));
+        assert_eq!("2019-07-11 18:24:30", format_timestamp(1_562_869_470));
+        assert_eq!("2024-02-29 12:00:00", format_timestamp(1_709_208_000));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("512 B", format_size(512));
+        assert_eq!("1.5 kB", format_size(1_500));
+        assert_eq!("72.8 MB", format_size(72_800_000));
+    }
+}
@@ -6,7 +6,13 @@ pub type BifrostResult<T> = failure::Fallible<T>;
 pub struct BifrostOptions {
     pub verbose: bool,
     pub diff: bool,
+    pub patch: bool,
+    pub ignored: bool,
     pub max_depth: u64,
+    pub update_lock: bool,
+    pub git: bool,
+    pub rev: Option<String>,
+    pub dry_run: bool,
 }
 
 impl Default for BifrostOptions {
@@ -14,7 +20,13 @@ impl Default for BifrostOptions {
         BifrostOptions {
             verbose: false,
             diff: false,
+            patch: false,
+            ignored: false,
             max_depth: 0u64,
+            update_lock: false,
+            git: false,
+            rev: None,
+            dry_run: false,
         }
     }
 }
@@ -23,12 +35,21 @@ impl Default for BifrostOptions {
 mod macros;
 
 pub mod bifrost_path;
+pub mod daemon;
+pub mod diff;
 pub mod docker;
+pub mod dockerfile;
 pub mod error;
+pub mod git;
+pub mod hash;
+pub mod inventory;
 pub mod operation_info;
 pub mod process_builder;
+pub mod secret;
+pub mod services;
 pub mod template;
 
 pub use bifrost_path::BifrostPath;
 pub use operation_info::OperationInfo;
 pub use process_builder::ProcessBuilder;
+pub use secret::Secrets;
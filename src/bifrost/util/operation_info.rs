@@ -1,5 +1,7 @@
 use std::fmt::{self, Debug};
 
+use crate::util::Secrets;
+
 /// The information that results from performing Bifrost operations.
 pub struct OperationInfo {
     /// The name of the current operable workspace.
@@ -12,6 +14,51 @@ pub struct OperationInfo {
     pub stdout: String,
     /// Stderr generated by the given operation.
     pub stderr: String,
+    /// How the process run by the given operation terminated (if any).
+    pub status: Option<RunStatus>,
+}
+
+/// Describes how a process run within the bifrost container terminated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RunStatus {
+    /// The process exited with the given code.
+    Exited(i32),
+    /// The process was killed by the given signal.
+    Signaled(u8),
+    /// The container was killed for exceeding its memory limit.
+    OutOfMemory,
+    /// The termination status could not be determined.
+    Unknown,
+}
+
+impl RunStatus {
+    /// Returns whether or not the process exited successfully.
+    pub fn success(&self) -> bool {
+        *self == RunStatus::Exited(0)
+    }
+
+    /// Returns the code bifrost exits with after a run, following the shell
+    /// convention of `128 + signal` for killed processes.
+    pub fn code(&self) -> i32 {
+        match self {
+            RunStatus::Exited(code) => *code,
+            RunStatus::Signaled(signal) => 128 + i32::from(*signal),
+            // The engine kills the container with SIGKILL.
+            RunStatus::OutOfMemory => 137,
+            RunStatus::Unknown => 1,
+        }
+    }
+}
+
+impl fmt::Display for RunStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunStatus::Exited(code) => write!(f, "exited with code {}", code),
+            RunStatus::Signaled(signal) => write!(f, "killed by signal {}", signal),
+            RunStatus::OutOfMemory => write!(f, "killed: memory limit exceeded (OOM)"),
+            RunStatus::Unknown => write!(f, "unknown"),
+        }
+    }
 }
 
 impl OperationInfo {
@@ -20,6 +67,18 @@ impl OperationInfo {
             ..Default::default()
         }
     }
+
+    /// Consumes this `OperationInfo` and returns it with every secret value
+    /// masked from its `text`, `stdout`, and `stderr`.
+    pub fn redact(mut self, secrets: &Secrets) -> Self {
+        if secrets.is_empty() {
+            return self;
+        }
+        self.text = self.text.map(|t| secrets.mask_bytes(&t));
+        self.stdout = secrets.mask(&self.stdout);
+        self.stderr = secrets.mask(&self.stderr);
+        self
+    }
 }
 
 impl Default for OperationInfo {
@@ -30,6 +89,7 @@ impl Default for OperationInfo {
             text: None,
             stdout: String::new(),
             stderr: String::new(),
+            status: None,
         }
     }
 }
@@ -40,6 +100,7 @@ impl Debug for OperationInfo {
             .field("workspace", &self.name)
             .field("size", &self.bytes)
             .field("text", &self.text)
+            .field("status", &self.status)
             .finish()
     }
 }
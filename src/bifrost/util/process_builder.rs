@@ -69,6 +69,26 @@ impl ProcessBuilder {
         }
     }
 
+    /// Executes the process with its stdout and stderr inherited so that its
+    /// output is streamed to the terminal.
+    pub fn status(&self) -> io::Result<ExitStatus> {
+        let mut command = self.build_command();
+
+        let status = command.status()?;
+
+        if status.success() {
+            Ok(status)
+        } else {
+            Err(Error::other(ProcessError {
+                msg: String::from("process did not exit successfully"),
+                args: self.get_args().to_vec(),
+                cwd: self.get_cwd().map(|c| c.to_path_buf()).unwrap_or_default(),
+                status: Some(status),
+                output: None,
+            }))
+        }
+    }
+
     pub fn build_command(&self) -> Command {
         let mut command = Command::new(&self.program);
         if let Some(cwd) = self.get_cwd() {
@@ -184,6 +204,19 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_status() {
+        let mut process = ProcessBuilder {
+            program: String::from("true"),
+            args: vec![],
+            cwd: None,
+        };
+        assert!(process.status().is_ok());
+
+        process.program("false");
+        assert!(process.status().is_err());
+    }
+
     #[test]
     fn test_process_error_display() {
         let cwd = Path::new("tests").join("test_user").join("test_app_dir");
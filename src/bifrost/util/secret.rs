@@ -0,0 +1,99 @@
+//! Redaction of secret values from output that bifrost displays or stores.
+use crate::core::config::EnvVar;
+
+/// The string that replaces every occurrence of a secret value.
+pub const MASK: &str = "********";
+
+/// A collection of secret values that must never be displayed.
+#[derive(Clone, Debug, Default)]
+pub struct Secrets {
+    values: Vec<String>,
+}
+
+impl Secrets {
+    /// Collects the values of all `EnvVar`s that have been marked secret.
+    pub fn from_env(vars: &[EnvVar]) -> Self {
+        let mut values: Vec<String> = vars
+            .iter()
+            .filter(|v| v.secret && !v.value.is_empty())
+            .map(|v| v.value.clone())
+            .collect();
+
+        // Mask longer values first so that a secret containing another secret
+        // is not partially revealed. Equal values of the same length end up
+        // next to each other, so `dedup` removes every duplicate.
+        values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        values.dedup();
+        Secrets { values }
+    }
+
+    /// Returns whether or not there is anything to mask.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns `text` with every secret value replaced by `MASK`.
+    pub fn mask(&self, text: &str) -> String {
+        let mut masked = String::from(text);
+        for value in &self.values {
+            masked = masked.replace(value.as_str(), MASK);
+        }
+        masked
+    }
+
+    /// Returns `bytes` with every secret value replaced by `MASK`. Bytes that
+    /// are not valid UTF-8 are replaced lossily.
+    pub fn mask_bytes(&self, bytes: &[u8]) -> Vec<u8> {
+        if self.is_empty() {
+            return bytes.to_vec();
+        }
+        self.mask(&String::from_utf8_lossy(bytes)).into_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn var(name: &str, value: &str, secret: bool) -> EnvVar {
+        EnvVar {
+            name: String::from(name),
+            value: String::from(value),
+            secret,
+        }
+    }
+
+    #[test]
+    fn test_mask_only_secrets() {
+        let secrets = Secrets::from_env(&[
+            var("API_TOKEN", "hunter2", true),
+            var("RUST_LOG", "debug", false),
+        ]);
+
+        let left = "token=******** level=debug";
+        assert_eq!(left, secrets.mask("token=hunter2 level=debug"));
+    }
+
+    #[test]
+    fn test_mask_overlapping_secrets() {
+        let secrets = Secrets::from_env(&[var("SHORT", "abc", true), var("LONG", "abcdef", true)]);
+        assert_eq!("********", secrets.mask("abcdef"));
+    }
+
+    #[test]
+    fn test_duplicate_values_are_removed() {
+        let secrets = Secrets::from_env(&[
+            var("A", "hunter2", true),
+            var("B", "abcdefg", true),
+            var("C", "hunter2", true),
+        ]);
+        assert_eq!(vec!["abcdefg", "hunter2"], secrets.values);
+    }
+
+    #[test]
+    fn test_empty_values_are_not_masked() {
+        let secrets = Secrets::from_env(&[var("EMPTY", "", true)]);
+        assert!(secrets.is_empty());
+        assert_eq!("unchanged", secrets.mask("unchanged"));
+    }
+}
@@ -0,0 +1,442 @@
+//! Sidecar services (e.g. databases) that run alongside a realm's container.
+//!
+//! Services are started on a private network created for a single run and are
+//! reachable from the realm's container by their name. Once every service is
+//! healthy the run proceeds; afterwards the services and the network are torn
+//! down. The container engine is abstracted by the `Engine` trait so that the
+//! orchestration can be exercised without one.
+use std::time::Duration;
+
+use regex::Regex;
+
+use crate::core::config::EnvVar;
+use crate::util::daemon::Backoff;
+use crate::util::docker::NetworkOptions;
+use crate::util::{BifrostResult, ProcessBuilder};
+
+/// The operations on the container engine services need.
+pub trait Engine {
+    /// Creates a network.
+    fn create_network(&self, network: &str) -> BifrostResult<()>;
+    /// Removes a network.
+    fn remove_network(&self, network: &str) -> BifrostResult<()>;
+    /// Starts (in the background) the container of a service on a network.
+    fn start(&self, container: &str, network: &str, service: &Service) -> BifrostResult<()>;
+    /// Returns whether or not the container is running.
+    fn is_running(&self, container: &str) -> bool;
+    /// Runs a shell command in the container and returns whether or not it
+    /// succeeded.
+    fn exec(&self, container: &str, cmd: &str) -> bool;
+    /// Returns the (last lines of the) logs of the container.
+    fn logs(&self, container: &str) -> String;
+    /// Removes the container, stopping it if it is running.
+    fn remove_container(&self, container: &str) -> BifrostResult<()>;
+}
+
+/// A sidecar service.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Service {
+    /// The name of the service; also its hostname on the run's network.
+    pub name: String,
+    /// The image the service's container is created from.
+    pub image: String,
+    /// Environment variables passed to the service's container.
+    pub env: Vec<EnvVar>,
+    /// Ports published to the host.
+    pub ports: Vec<String>,
+    /// A shell command, run within the service's container, that succeeds once
+    /// the service is ready (e.g. `pg_isready`).
+    pub health_check: Option<String>,
+    /// How long to wait for the service to become healthy.
+    pub health_timeout: Duration,
+}
+
+impl Service {
+    /// Checks that the service name is a valid hostname and its ports can be
+    /// published.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if the name or a port is invalid.
+    pub fn validate(&self) -> BifrostResult<()> {
+        let re = Regex::new(r"^[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?$")?;
+        if !re.is_match(&self.name) {
+            failure::bail!(
+                "error: service name `{}` must be a valid hostname",
+                self.name
+            );
+        }
+
+        let network = NetworkOptions {
+            mode: None,
+            ports: self.ports.clone(),
+        };
+        network.validate()
+    }
+}
+
+/// Services started for a single run. Dropping a `ServiceStack` tears it down.
+pub struct ServiceStack<'a, E: Engine> {
+    engine: &'a E,
+    network: String,
+    containers: Vec<String>,
+    network_created: bool,
+}
+
+impl<'a, E: Engine> ServiceStack<'a, E> {
+    /// Creates `network`, starts each service on it, and waits for each of
+    /// them to be healthy. Service containers are named `<network>-<service>`.
+    ///
+    /// # Errors
+    ///
+    /// If the network or a service cannot be started, or a service does not
+    /// become healthy in time, then whatever was started is torn down and
+    /// this function returns an error.
+    pub fn up(engine: &'a E, network: &str, services: &[Service]) -> BifrostResult<Self> {
+        let mut stack = ServiceStack {
+            engine,
+            network: String::from(network),
+            containers: Vec::new(),
+            network_created: false,
+        };
+
+        engine.create_network(network)?;
+        stack.network_created = true;
+
+        for service in services {
+            let container = format!("{}-{}", network, service.name);
+            engine.start(&container, network, service)?;
+            stack.containers.push(container);
+        }
+
+        for (service, container) in services.iter().zip(stack.containers.iter()) {
+            let backoff = Backoff {
+                timeout: service.health_timeout,
+                ..Default::default()
+            };
+            let healthy = backoff.poll(|| match service.health_check {
+                Some(ref cmd) => engine.exec(container, cmd),
+                None => engine.is_running(container),
+            });
+
+            if !healthy {
+                failure::bail!(
+                    "error: service `{}` did not become healthy within {} seconds; its logs:\n{}",
+                    service.name,
+                    service.health_timeout.as_secs(),
+                    engine.logs(container)
+                );
+            }
+        }
+
+        Ok(stack)
+    }
+
+    /// Returns the name of the network the services are on.
+    pub fn network(&self) -> &str {
+        &self.network
+    }
+
+    /// Removes the services' containers and network.
+    ///
+    /// # Errors
+    ///
+    /// If any of them could not be removed, then this method returns the first
+    /// error (after attempting to remove the rest).
+    pub fn down(mut self) -> BifrostResult<()> {
+        self.teardown()
+    }
+
+    fn teardown(&mut self) -> BifrostResult<()> {
+        let mut result = Ok(());
+        while let Some(container) = self.containers.pop() {
+            if let Err(e) = self.engine.remove_container(&container) {
+                result = result.and(Err(e));
+            }
+        }
+        if self.network_created {
+            self.network_created = false;
+            if let Err(e) = self.engine.remove_network(&self.network) {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+impl<'a, E: Engine> Drop for ServiceStack<'a, E> {
+    fn drop(&mut self) {
+        let _ = self.teardown();
+    }
+}
+
+/// The docker implementation of `Engine`.
+pub struct Docker {
+    /// The name of the container engine's executable.
+    pub name: String,
+}
+
+impl Docker {
+    fn process(&self, args: &[&str]) -> ProcessBuilder {
+        ProcessBuilder {
+            program: self.name.clone(),
+            args: args.iter().map(|a| String::from(*a)).collect(),
+            cwd: None,
+        }
+    }
+}
+
+impl Engine for Docker {
+    fn create_network(&self, network: &str) -> BifrostResult<()> {
+        if let Err(e) = self.process(&["network", "create", network]).exec() {
+            failure::bail!("error: failed to create network `{}` due to {}", network, e);
+        }
+        Ok(())
+    }
+
+    fn remove_network(&self, network: &str) -> BifrostResult<()> {
+        if let Err(e) = self.process(&["network", "rm", network]).exec() {
+            failure::bail!("error: failed to remove network `{}` due to {}", network, e);
+        }
+        Ok(())
+    }
+
+    fn start(&self, container: &str, network: &str, service: &Service) -> BifrostResult<()> {
+        let mut run = self.process(&[
+            "run",
+            "--detach",
+            "--name",
+            container,
+            "--network",
+            network,
+            "--network-alias",
+            &service.name,
+            "--hostname",
+            &service.name,
+        ]);
+        // Only the names of environment variables are passed as arguments;
+        // their values are passed through the environment of the engine
+        // process so that they never show up in a process listing.
+        for var in &service.env {
+            run.arg("--env").arg(&var.name);
+        }
+        for port in &service.ports {
+            run.arg("--publish").arg(port);
+        }
+        run.arg(&service.image);
+
+        let mut command = run.build_command();
+        for var in &service.env {
+            command.env(&var.name, &var.value);
+        }
+        match command.output() {
+            Ok(ref output) if output.status.success() => Ok(()),
+            Ok(output) => failure::bail!(
+                "error: failed to start service `{}` due to {}",
+                service.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            Err(e) => failure::bail!(
+                "error: failed to start service `{}` due to {}",
+                service.name,
+                e
+            ),
+        }
+    }
+
+    fn is_running(&self, container: &str) -> bool {
+        match self
+            .process(&["inspect", "--format", "{{.State.Running}}", container])
+            .exec()
+        {
+            Ok(output) => String::from_utf8_lossy(&output.stdout).trim() == "true",
+            Err(_) => false,
+        }
+    }
+
+    fn exec(&self, container: &str, cmd: &str) -> bool {
+        self.process(&["exec", container, "sh", "-c", cmd])
+            .exec()
+            .is_ok()
+    }
+
+    fn logs(&self, container: &str) -> String {
+        // `docker logs` fails for containers that are gone, which is exactly
+        // when its output (stdout and stderr) would be useful; take either.
+        let output = self
+            .process(&["logs", "--tail", "20", container])
+            .build_command()
+            .output();
+        match output {
+            Ok(output) => format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+            Err(e) => format!("(could not get logs due to {})", e),
+        }
+    }
+
+    fn remove_container(&self, container: &str) -> BifrostResult<()> {
+        if let Err(e) = self.process(&["rm", "--force", container]).exec() {
+            failure::bail!(
+                "error: failed to remove container `{}` due to {}",
+                container,
+                e
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// An engine that records what it is asked to do and reports a service as
+    /// healthy after a number of health checks.
+    struct FakeEngine {
+        calls: RefCell<Vec<String>>,
+        checks_until_healthy: RefCell<u32>,
+    }
+
+    impl FakeEngine {
+        fn new(checks_until_healthy: u32) -> Self {
+            FakeEngine {
+                calls: RefCell::new(vec![]),
+                checks_until_healthy: RefCell::new(checks_until_healthy),
+            }
+        }
+
+        fn record(&self, call: String) {
+            self.calls.borrow_mut().push(call);
+        }
+
+        fn calls(&self) -> Vec<String> {
+            self.calls.borrow().clone()
+        }
+    }
+
+    impl Engine for FakeEngine {
+        fn create_network(&self, network: &str) -> BifrostResult<()> {
+            self.record(format!("create-network {}", network));
+            Ok(())
+        }
+
+        fn remove_network(&self, network: &str) -> BifrostResult<()> {
+            self.record(format!("remove-network {}", network));
+            Ok(())
+        }
+
+        fn start(&self, container: &str, network: &str, service: &Service) -> BifrostResult<()> {
+            self.record(format!("start {} {} {}", container, network, service.image));
+            Ok(())
+        }
+
+        fn is_running(&self, container: &str) -> bool {
+            self.record(format!("is-running {}", container));
+            true
+        }
+
+        fn exec(&self, container: &str, cmd: &str) -> bool {
+            self.record(format!("exec {} {}", container, cmd));
+            let mut checks = self.checks_until_healthy.borrow_mut();
+            if *checks == 0 {
+                return true;
+            }
+            *checks -= 1;
+            false
+        }
+
+        fn logs(&self, _container: &str) -> String {
+            String::from("database system is starting up")
+        }
+
+        fn remove_container(&self, container: &str) -> BifrostResult<()> {
+            self.record(format!("remove {}", container));
+            Ok(())
+        }
+    }
+
+    fn services() -> Vec<Service> {
+        vec![
+            Service {
+                name: String::from("db"),
+                image: String::from("postgres:15"),
+                env: vec![EnvVar {
+                    name: String::from("POSTGRES_PASSWORD"),
+                    value: String::from("test"),
+                    secret: true,
+                }],
+                ports: vec![],
+                health_check: Some(String::from("pg_isready")),
+                health_timeout: Duration::from_secs(1),
+            },
+            Service {
+                name: String::from("cache"),
+                image: String::from("redis:7"),
+                env: vec![],
+                ports: vec![],
+                health_check: None,
+                health_timeout: Duration::from_secs(1),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_service_stack_up_and_down() -> BifrostResult<()> {
+        let engine = FakeEngine::new(1);
+        let stack = ServiceStack::up(&engine, "bifrost-test-net", &services())?;
+        assert_eq!("bifrost-test-net", stack.network());
+        stack.down()?;
+
+        let expected = vec![
+            "create-network bifrost-test-net",
+            "start bifrost-test-net-db bifrost-test-net postgres:15",
+            "start bifrost-test-net-cache bifrost-test-net redis:7",
+            "exec bifrost-test-net-db pg_isready",
+            "exec bifrost-test-net-db pg_isready",
+            "is-running bifrost-test-net-cache",
+            "remove bifrost-test-net-cache",
+            "remove bifrost-test-net-db",
+            "remove-network bifrost-test-net",
+        ];
+        assert_eq!(expected, engine.calls());
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_stack_unhealthy() {
+        let engine = FakeEngine::new(u32::MAX);
+        let mut services = services();
+        services[0].health_timeout = Duration::from_millis(250);
+
+        let result = ServiceStack::up(&engine, "bifrost-test-net", &services);
+        let e = result.err().expect("expected an unhealthy service to fail");
+        assert!(e.to_string().contains("database system is starting up"));
+
+        // Everything that was started is torn down.
+        let calls = engine.calls();
+        let teardown = &calls[calls.len() - 3..];
+        assert_eq!(
+            vec![
+                "remove bifrost-test-net-cache",
+                "remove bifrost-test-net-db",
+                "remove-network bifrost-test-net",
+            ],
+            teardown
+        );
+    }
+
+    #[test]
+    fn test_service_validate() {
+        let mut service = services().remove(0);
+        assert!(service.validate().is_ok());
+        service.name = String::from("my_db");
+        assert!(service.validate().is_err());
+        service.name = String::from("db");
+        service.ports = vec![String::from("not-a-port")];
+        assert!(service.validate().is_err());
+    }
+}
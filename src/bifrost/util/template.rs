@@ -59,14 +59,18 @@ FLAGS:
 
 
 SUBCOMMANDS:
+    cache       Manage the named cache volumes of bifrost realms
     help        Prints this message or the help of the given subcommand(s)
+    image       Manage bifrost images and their packages
     init        Initialize a bifrost directory within the current working directory
     load        Load directory, file, or files into the bifrost container
+    reload      Replace the loaded workspace with a fresh copy of the realm
     run         Run command string(s) on a bifrost workspace
     setup       Setup the utilities bifrost requires to operate
     show        Display files currently in the bifrost container
     teardown    Teardown the utilities bifrost requires to operate
     unload      Unload a workspace from the bifrost container
+    verify      Check a loaded workspace against the checksums of its files
 
 USAGE:
     bifrost [COMMAND] [OPTION]
@@ -0,0 +1,11 @@
+//! Executes `bifrost cache`.
+use bifrost::core::config::Config;
+use bifrost::ops::bifrost_cache;
+use bifrost::util::BifrostResult;
+
+use clap::ArgMatches;
+
+pub fn exec(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let config = config.config_manifest(args);
+    bifrost_cache::cache(config, args)
+}
@@ -0,0 +1,10 @@
+//! Executes `bifrost image`.
+use bifrost::core::config::Config;
+use bifrost::ops::bifrost_image;
+use bifrost::util::BifrostResult;
+
+use clap::ArgMatches;
+
+pub fn exec(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    bifrost_image::image(config, args)
+}
@@ -1,7 +1,11 @@
+pub mod cache;
+pub mod image;
 pub mod init;
 pub mod load;
+pub mod reload;
 pub mod run;
 pub mod setup;
 pub mod show;
 pub mod teardown;
 pub mod unload;
+pub mod verify;
@@ -1,4 +1,6 @@
 //! Executes `bifrost run`.
+use std::process;
+
 use bifrost::core::config::Config;
 use bifrost::ops::bifrost_run;
 use bifrost::util::BifrostResult;
@@ -7,5 +9,11 @@ use clap::ArgMatches;
 
 pub fn exec(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     let config = config.config_manifest(&args);
-    return bifrost_run::run(config, &args);
+    let status = bifrost_run::run(config, &args)?;
+
+    // Exit like the run did, so that scripts can tell a failed run apart.
+    match status {
+        Some(status) if !status.success() => process::exit(status.code()),
+        _ => Ok(()),
+    }
 }
@@ -6,7 +6,8 @@ use std::process;
 
 use bifrost::core::config::Config;
 use bifrost::core::hofund;
-use bifrost::util::docker::ImageBuilder;
+use bifrost::util::docker;
+use bifrost::util::dockerfile::{self, Template};
 use bifrost::util::process_builder::ProcessBuilder;
 use bifrost::util::BifrostResult;
 
@@ -14,7 +15,7 @@ use clap::ArgMatches;
 
 /// Executes the "setup" command which sets up the scaffolding necessary for
 /// the other bifrost ops to be executed.
-pub fn exec(config: Config, _args: &ArgMatches) -> BifrostResult<()> {
+pub fn exec(config: Config, args: &ArgMatches) -> BifrostResult<()> {
     if let Err(e) = check_installation(config.cwd()) {
         io::stderr().write_fmt(format_args!(
             "failed: `bifrost setup` could not find bifrost installation due to: {}\n",
@@ -23,8 +24,19 @@ pub fn exec(config: Config, _args: &ArgMatches) -> BifrostResult<()> {
         process::exit(1);
     }
     check_for_bifrost_directory(config.home_path())?;
+
+    // Resolve the Dockerfile before anything is created so that a bad base
+    // image or template leaves nothing behind.
+    let docker_file_contents = match dockerfile_contents(&config, args) {
+        Ok(contents) => contents,
+        Err(e) => {
+            io::stderr().write_fmt(format_args!("failed: `bifrost setup` {}\n", e))?;
+            process::exit(1);
+        }
+    };
+
     io::stdout().write("bifrost: building image - this could take a while...\n".as_bytes())?;
-    setup(config.home_path())?;
+    setup(config.home_path(), &docker_file_contents)?;
     io::stdout().write("\nbifrost: successfully setup\n".as_bytes())?;
     Ok(())
 }
@@ -58,9 +70,23 @@ fn check_for_bifrost_directory(home_path: &PathBuf) -> BifrostResult<()> {
     Ok(())
 }
 
+/// Returns the contents of the default Dockerfile: either an existing one given
+/// with `--dockerfile` or a built-in `--template` on top of a `--base` image.
+fn dockerfile_contents(config: &Config, args: &ArgMatches) -> BifrostResult<String> {
+    if let Some(path) = args.value_of("dockerfile") {
+        return hofund::read(&config.cwd().join(path));
+    }
+
+    let template = match args.value_of("template") {
+        Some(name) => name.parse()?,
+        None => Template::default(),
+    };
+    template.dockerfile(args.value_of("base").unwrap_or(dockerfile::DEFAULT_BASE))
+}
+
 /// Sets up the bifrost scaffolding (i.e. bifrost and container directories).
-fn setup(home_path: &PathBuf) -> BifrostResult<()> {
-    create_bifrost_directory(home_path)?;
+fn setup(home_path: &PathBuf, docker_file_contents: &str) -> BifrostResult<()> {
+    create_bifrost_directory(home_path, docker_file_contents)?;
     build_image(home_path)?;
     Ok(())
 }
@@ -71,7 +97,7 @@ fn setup(home_path: &PathBuf) -> BifrostResult<()> {
 ///
 /// If at any point this function cannot create a directory or cannot
 /// write to the Dockerfile, this function returns an error.
-fn create_bifrost_directory(home_path: &PathBuf) -> BifrostResult<()> {
+fn create_bifrost_directory(home_path: &PathBuf, docker_file_contents: &str) -> BifrostResult<()> {
     // Create the top-level hidden bifrost directory.
     let bifrost_path = home_path.join(".bifrost");
     fs::create_dir(&bifrost_path)?;
@@ -84,49 +110,22 @@ fn create_bifrost_directory(home_path: &PathBuf) -> BifrostResult<()> {
     let bifrost_container = container_path.join("bifrost");
     fs::create_dir(&bifrost_container)?;
 
-    // Prepare the contents of the default Dockerfile.
-    let docker_file_contents = r#"
-FROM ubuntu:16.04
-
-# Update default packages
-RUN apt-get update
-
-# Get Ubuntu packages
-RUN apt-get install -y \
-    build-essential \
-    curl
-
-# Update new packages
-RUN apt-get update
-"#;
-
     // Write the contents.
     let docker_file = bifrost_container.join("Dockerfile");
     hofund::write(&docker_file, docker_file_contents.as_bytes())?;
     Ok(())
 }
 
-/// Builds the docker image.
+/// Builds the docker image and records the hash of the Dockerfile it was built
+/// from so that later edits can be detected.
 ///
 /// # Errors
 ///
 /// The last step of this function is executes the build process. Building the
 /// image can fail; if it does fail, then this function returns that error.
 fn build_image(home_path: &PathBuf) -> BifrostResult<()> {
-    let path = home_path.join(".bifrost").join("container").join("bifrost");
-
-    let path = String::from(
-        path.to_str()
-            .expect("failed: `setup::build_image` failed to convert path to `str`"),
-    );
-
-    let image = ImageBuilder {
-        name: String::from("docker"),
-        tag: String::from("bifrost:0.1"),
-        path,
-    };
-
-    Ok(image.build()?)
+    docker::default_image(home_path)?.rebuild(home_path, true)?;
+    Ok(())
 }
 
 // Allow these tests to remain for documentation purposes. Eventually, they
@@ -147,6 +146,9 @@ mod test {
     fn _test_setup() {
         let home_path =
             dirs::home_dir().expect("error: `test_setup` expected home path to be `Some`");
-        assert!(setup(&home_path).is_ok());
+        let contents = Template::default()
+            .dockerfile(dockerfile::DEFAULT_BASE)
+            .expect("error: `test_setup` expected the default template to be valid");
+        assert!(setup(&home_path, &contents).is_ok());
     }
 }
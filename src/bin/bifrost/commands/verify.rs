@@ -0,0 +1,11 @@
+//! Executes `bifrost verify`.
+use bifrost::core::config::Config;
+use bifrost::ops::bifrost_verify;
+use bifrost::util::BifrostResult;
+
+use clap::ArgMatches;
+
+pub fn exec(config: Config, args: &ArgMatches) -> BifrostResult<()> {
+    let config = config.config_manifest(&args);
+    return bifrost_verify::verify(config, &args);
+}
@@ -17,6 +17,28 @@ fn main() -> BifrostResult<()> {
     // [TODO] Looks like the only thing `exec` needs is `arg_matches`
     // [TODO] Clean this repetition up
     match app.subcommand() {
+        ("cache", Some(arg_matches)) => {
+            let config = Config::default();
+            exit_if_not_setup(&config)?;
+            // Clearing every realm's cache does not require being in a realm.
+            let all = arg_matches
+                .subcommand_matches("clear")
+                .is_some_and(|m| m.is_present("all"));
+            if !all {
+                exit_if_uninitialized(&config, "clear cache")?;
+            }
+            start_container_or_exit(config.home_path())?;
+            commands::cache::exec(config, arg_matches)?;
+        }
+        ("image", Some(arg_matches)) => {
+            let config = Config::default();
+            exit_if_not_setup(&config)?;
+            // Listing packages only reads the Dockerfile.
+            if arg_matches.subcommand_matches("list").is_none() {
+                start_container_or_exit(config.home_path())?;
+            }
+            commands::image::exec(config, arg_matches)?;
+        }
         ("init", Some(arg_matches)) => {
             let config = Config::default();
             exit_if_not_setup(&config)?;
@@ -26,8 +48,18 @@ fn main() -> BifrostResult<()> {
             let config = Config::default();
             exit_if_not_setup(&config)?;
             exit_if_uninitialized(&config, "load")?;
+            // Only auto loads that run the realm's commands need the container.
+            if arg_matches.is_present("run") {
+                start_container_or_exit(config.home_path())?;
+            }
             commands::load::exec(config, arg_matches)?;
         }
+        ("reload", Some(arg_matches)) => {
+            let config = Config::default();
+            exit_if_not_setup(&config)?;
+            exit_if_uninitialized(&config, "reload")?;
+            commands::reload::exec(config, arg_matches)?;
+        }
         ("show", Some(arg_matches)) => {
             let config = Config::default();
             exit_if_not_setup(&config)?;
@@ -40,6 +72,12 @@ fn main() -> BifrostResult<()> {
             exit_if_uninitialized(&config, "unload")?;
             commands::unload::exec(config, arg_matches)?;
         }
+        ("verify", Some(arg_matches)) => {
+            let config = Config::default();
+            exit_if_not_setup(&config)?;
+            exit_if_uninitialized(&config, "verify")?;
+            commands::verify::exec(config, arg_matches)?;
+        }
         ("run", Some(arg_matches)) => {
             let config = Config::default();
             exit_if_not_setup(&config)?;
@@ -172,8 +210,15 @@ failed: bifrost failed to start container
         )?;
         process::exit(1);
     }
+    docker::connect(&path);
     if !docker::is_running() {
-        docker::start(&path)?;
+        if let Err(e) = docker::start(&path) {
+            io::stderr().write_fmt(format_args!(
+                "failed: bifrost failed to start container\n\n    {}\n",
+                e
+            ))?;
+            process::exit(1);
+        }
     }
     Ok(())
 }